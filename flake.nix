@@ -30,9 +30,9 @@
             libclang
             libpcap
             libopus
-            # package for tts on linux
+            # packages for tts on linux
             piper-tts
-            alsa-utils
+            espeak-ng
           ];
           LD_LIBRARY_PATH = lib.makeLibraryPath buildInputs;
           RUST_SRC_PATH = "${rust.packages.stable.rustPlatform.rustLibSrc}";
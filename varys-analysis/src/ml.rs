@@ -11,12 +11,13 @@ use log::info;
 
 use cnn::training;
 use varys_database::database::interaction::Interaction;
-use varys_network::address::MacAddress;
+use varys_network::address::DeviceAddress;
 
 use crate::error::Error;
 use crate::ml::cnn::training::CNNTrainingConfig;
 use crate::ml::cnn::{inference, CNNModelConfig};
 use crate::ml::data::{NumericTraceDataset, NumericTraceItem};
+use crate::trace::TraceRepresentation;
 
 mod activation;
 mod cnn;
@@ -25,12 +26,16 @@ pub mod data;
 type Backend = Wgpu<AutoGraphicsApi, f32, i32>;
 type AutodiffBackend = Autodiff<Backend>;
 
-pub fn train<P: AsRef<Path>>(data_dir: P, interactions: Vec<Interaction>) -> Result<(), Error> {
+pub fn train<P: AsRef<Path>>(
+    data_dir: P,
+    interactions: Vec<Interaction>,
+    representation: TraceRepresentation,
+) -> Result<(), Error> {
     let data_dir_string = data_dir.as_ref().to_string_lossy().to_string();
     fs::create_dir_all(ml_path(&data_dir_string))?;
 
     let device = WgpuDevice::default();
-    let mut dataset = NumericTraceDataset::load_or_new(&data_dir, interactions)?;
+    let mut dataset = NumericTraceDataset::load_or_new(&data_dir, interactions, representation)?;
     dataset
         .normalise()
         .resize_all(CNNModelConfig::DEFAULT_INPUT_DIMENSIONS)
@@ -85,11 +90,13 @@ pub fn test_dataset<P: AsRef<Path>>(data_dir: P) -> Result<(), Error> {
 pub fn test_single<P: AsRef<Path>>(
     data_dir: P,
     capture_path: P,
-    address: &MacAddress,
+    address: &DeviceAddress,
 ) -> Result<Vec<(String, f32)>, Error> {
     let device = WgpuDevice::default();
-    let trace = NumericTraceDataset::load_trace(capture_path, address)?;
-    let (_, _, testing_dataset) = NumericTraceDataset::load(&data_dir)?.split_default()?;
+    let dataset = NumericTraceDataset::load(&data_dir)?;
+    // the trace must be created like the traces the model was trained on
+    let trace = NumericTraceDataset::load_trace(capture_path, address, dataset.representation)?;
+    let (_, _, testing_dataset) = dataset.split_default()?;
     let output = inference::infer::<AutodiffBackend>(
         data_dir.as_ref().to_string_lossy().as_ref(),
         trace,
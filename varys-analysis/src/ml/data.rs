@@ -1,24 +1,23 @@
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, Write};
 use std::path::Path;
-use std::str::FromStr;
 
 use burn::data::dataloader::batcher::Batcher;
 use burn::data::dataset::Dataset;
 use burn::tensor::backend::Backend;
 use burn::tensor::{Data, ElementConversion, Int, Tensor};
-use log::{debug, info};
+use log::{debug, info, warn};
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 
 use varys_database::database::interaction::Interaction;
 use varys_database::file;
-use varys_network::address::MacAddress;
+use varys_network::address::DeviceAddress;
 use varys_network::packet;
 
 use crate::error::Error;
 use crate::ml;
-use crate::trace::{NumericTrafficTrace, TrafficTrace};
+use crate::trace::{NumericTrafficTrace, TraceRepresentation, TrafficTrace};
 
 pub struct TrafficTraceBatcher<B: Backend> {
     device: B::Device,
@@ -56,6 +55,9 @@ pub struct NumericTraceDataset {
     pub items: Vec<NumericTraceItem>,
     /// The label of a query is the index of the query in this vector
     pub queries: Vec<String>,
+    /// How the traces of the dataset were created from the captured traffic
+    #[serde(default)]
+    pub representation: TraceRepresentation,
 }
 
 impl NumericTraceDataset {
@@ -69,22 +71,33 @@ impl NumericTraceDataset {
     /// If no existing dataset is found, a new one is created.
     ///
     /// Note that this will always prefer loading from disk even if the existing dataset does not
-    /// match the given interactions.
+    /// match the given interactions. It is only created again if its traces use a different
+    /// representation.
     ///
     /// # Arguments
     ///
     /// * `data_path`: The path to the data directory.
     /// * `interactions`: The interactions to create the dataset from if no dataset is found on
-    /// disk.
+    ///   disk.
+    /// * `representation`: How to create the traces from the captured traffic.
     pub fn load_or_new<P: AsRef<Path>>(
         data_path: P,
         interactions: Vec<Interaction>,
+        representation: TraceRepresentation,
     ) -> Result<NumericTraceDataset, Error> {
         if ml::dataset_path(&data_path).exists() {
-            NumericTraceDataset::load(data_path)
-        } else {
-            NumericTraceDataset::new(data_path, interactions)
+            let dataset = NumericTraceDataset::load(&data_path)?;
+            if dataset.representation == representation {
+                return Ok(dataset);
+            }
+
+            info!(
+                "Existing dataset uses {:?} instead of {representation:?}",
+                dataset.representation
+            );
         }
+
+        NumericTraceDataset::new(data_path, interactions, representation)
     }
 
     /// Create a dataset of all numeric traffic traces from a list of interactions.
@@ -96,11 +109,13 @@ impl NumericTraceDataset {
     ///
     /// * `data_path`: The path to the data directory.
     /// * `interactions`: The interactions to create the dataset from.
+    /// * `representation`: How to create the traces from the captured traffic.
     ///
     /// returns: The created dataset or [`Error::TooManyLabels`] if there were too many different queries.
     pub fn new<P: AsRef<Path>>(
         data_path: P,
         interactions: Vec<Interaction>,
+        representation: TraceRepresentation,
     ) -> Result<Self, Error> {
         info!(
             "Creating dataset from {} interactions...",
@@ -111,15 +126,19 @@ impl NumericTraceDataset {
         let mut dataset = Self {
             items: Vec::new(),
             queries: Self::collect_queries(&interactions)?,
+            representation,
         };
 
         dataset.items = interactions
             .into_iter()
             .map(|interaction| {
-                (
-                    Self::load_interaction_trace(&data_path, &interaction),
-                    dataset.get_label(&interaction.query),
-                )
+                let trace = Self::load_interaction_trace(&data_path, &interaction, representation);
+                if trace.is_err() {
+                    // run `varys captures verify` to find out why
+                    warn!("Skipping {interaction} because its trace could not be loaded");
+                }
+
+                (trace, dataset.get_label(&interaction.query))
             })
             // only keep items where the trace could be loaded and the label was found
             .filter_map(|(trace, label)| trace.ok().zip(label))
@@ -229,14 +248,17 @@ impl NumericTraceDataset {
             Self {
                 items: training_items,
                 queries: self.queries.clone(),
+                representation: self.representation,
             },
             Self {
                 items: validation_items,
                 queries: self.queries.clone(),
+                representation: self.representation,
             },
             Self {
                 items: testing_items,
                 queries: self.queries,
+                representation: self.representation,
             },
         ))
     }
@@ -320,14 +342,19 @@ impl NumericTraceDataset {
     ///
     /// * `data_path`: The path to the data directory.
     /// * `interaction`: The interaction to load the traffic trace from.
+    /// * `representation`: How to create the trace from the captured traffic.
     ///
     /// returns: The parsed [`TrafficTrace`] or `None` if the pcap file could not be loaded.
     pub fn load_interaction_trace<P: AsRef<Path>>(
         data_path: P,
         interaction: &Interaction,
+        representation: TraceRepresentation,
     ) -> Result<NumericTrafficTrace, Error> {
-        let address =
-            MacAddress::from_str(&interaction.assistant_mac).map_err(|_| Error::CannotLoadTrace)?;
+        let address = DeviceAddress::parse(
+            &interaction.assistant_mac,
+            interaction.assistant_ip.as_deref(),
+        )
+        .map_err(|_| Error::CannotLoadTrace)?;
 
         let capture_path = interaction
             .capture_file
@@ -335,26 +362,31 @@ impl NumericTraceDataset {
             .map(|path| file::session_path(data_path, interaction.session_id).join(path))
             .ok_or(Error::CannotLoadTrace)?;
 
-        Self::load_trace(capture_path, &address)
+        Self::load_trace(capture_path, &address, representation)
     }
 
     /// Load a [`TrafficTrace`] from a pcap file directly.
     ///
+    /// Only the packet headers are kept in memory while the trace is converted.
+    ///
     /// # Arguments
     ///
     /// * `capture_path`: The path to the pcap file.
     /// * `address`: The address of the assistant.
+    /// * `representation`: How to create the trace from the captured traffic.
     ///
     /// returns: The parsed [`TrafficTrace`] or `None` if the pcap file could not be loaded.
     pub fn load_trace<P: AsRef<Path>>(
         capture_path: P,
-        address: &MacAddress,
+        address: &DeviceAddress,
+        representation: TraceRepresentation,
     ) -> Result<NumericTrafficTrace, Error> {
-        packet::load_packets(capture_path)
+        packet::read_headers(capture_path)
+            .and_then(|headers| headers.collect::<Result<Vec<_>, _>>())
             .ok()
             .map(TrafficTrace::try_from)
             .transpose()?
-            .map(|trace| trace.as_numeric_trace(address))
+            .map(|trace| trace.as_representation(address, representation))
             .ok_or(Error::CannotLoadTrace)
     }
 
@@ -6,7 +6,7 @@ use plotters::prelude::*;
 use plotters::style::SizeDesc;
 
 use crate::ml::data::NumericTraceDataset;
-use crate::trace::NumericTrafficTrace;
+use crate::trace::{NumericTrafficTrace, TraceRepresentation};
 
 const MAX_VALUE: i32 = 1514;
 
@@ -25,10 +25,15 @@ pub fn plot_queries<P: AsRef<Path>>(
 }
 
 pub fn plot<P: AsRef<Path>>(data_path: P, query: &str, dataset: &NumericTraceDataset) {
-    let path = data_path.as_ref().join(format!("plots/plot-{query}.png"));
+    let name = match dataset.representation {
+        TraceRepresentation::Packets => "plot",
+        TraceRepresentation::Bursts { .. } => "bursts",
+    };
+    let path = data_path.as_ref().join(format!("plots/{name}-{query}.png"));
 
     let label = dataset.get_label(query).unwrap();
     let mut traces = dataset.items.iter().filter(|item| item.label == label);
+    let max_value = max_value(dataset);
 
     let drawing_area = BitMapBackend::new(&path, (800, 1400)).into_drawing_area();
     drawing_area.fill(&WHITE).unwrap();
@@ -36,16 +41,36 @@ pub fn plot<P: AsRef<Path>>(data_path: P, query: &str, dataset: &NumericTraceDat
 
     for area in areas {
         if let Some(trace) = traces.next() {
-            plot_trace(&trace.trace, &area, false, (0, 0, 0, 0));
+            plot_trace(&trace.trace, &area, max_value, false, (0, 0, 0, 0));
         } else {
             break;
         }
     }
 }
 
+/// The largest absolute value of the plotted traces, which is the largest frame for packets.
+///
+/// Bursts have no upper bound, so the largest burst of the dataset is used.
+fn max_value(dataset: &NumericTraceDataset) -> i32 {
+    match dataset.representation {
+        TraceRepresentation::Packets => MAX_VALUE,
+        TraceRepresentation::Bursts { .. } => dataset
+            .items
+            .iter()
+            .map(|item| {
+                let (min, max) = item.trace.min_max();
+                min.abs().max(max.abs()).ceil() as i32
+            })
+            .max()
+            .unwrap_or(MAX_VALUE)
+            .max(1),
+    }
+}
+
 fn plot_trace<DB: DrawingBackend, S: SizeDesc>(
     trace: &NumericTrafficTrace,
     drawing_area: &DrawingArea<DB, Shift>,
+    max_value: i32,
     show_mesh: bool,
     margin: (S, S, S, S),
 ) {
@@ -55,7 +80,7 @@ fn plot_trace<DB: DrawingBackend, S: SizeDesc>(
         .margin_right(margin.1)
         .margin_bottom(margin.2)
         .margin_left(margin.3)
-        .build_cartesian_2d(0..data.len() as i32, -MAX_VALUE..(MAX_VALUE + 1))
+        .build_cartesian_2d(0..data.len() as i32, -max_value..(max_value + 1))
         .unwrap();
     if show_mesh {
         chart
@@ -69,11 +94,11 @@ fn plot_trace<DB: DrawingBackend, S: SizeDesc>(
     chart
         .draw_series(data.iter().enumerate().map(|(x, &value)| {
             let x = x as i32;
-            let size = MAX_VALUE / 2 + (value / 2.).round().abs() as i32;
+            let size = max_value / 2 + (value / 2.).round().abs() as i32;
             let style = if value.abs() < 0.001 {
                 TRANSPARENT.filled()
             } else {
-                color(value as f64).filled()
+                color(value as f64, max_value).filled()
             };
 
             Rectangle::new([(x, -size), (x + 1, size)], style)
@@ -81,8 +106,8 @@ fn plot_trace<DB: DrawingBackend, S: SizeDesc>(
         .unwrap();
 }
 
-fn color(value: f64) -> HSLColor {
-    let scaled = value / MAX_VALUE as f64;
+fn color(value: f64, max_value: i32) -> HSLColor {
+    let scaled = value / max_value as f64;
 
     HSLColor(
         if scaled >= 0. { hue(215.) } else { hue(15.) },
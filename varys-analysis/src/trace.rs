@@ -3,23 +3,35 @@ use std::fmt::{Debug, Display, Formatter};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 
-use varys_network::address::MacAddress;
-use varys_network::packet::Packet;
+use varys_network::address::DeviceAddress;
+use varys_network::burst;
+pub use varys_network::burst::Burst;
+use varys_network::flow::Flow;
+use varys_network::hostname::Hostnames;
+use varys_network::packet::{CapturedPacket, Packet};
 
 use crate::error::Error;
 
-pub struct TrafficTrace {
+/// The default number of milliseconds between two packets that separates them into different
+/// bursts, see [`TrafficTrace::bursts`].
+pub const DEFAULT_BURST_GAP: u64 = 500;
+
+/// A time-ordered list of captured packets.
+///
+/// By default, this holds full [`Packet`]s. To keep large numbers of traces in memory, create it
+/// from [`varys_network::packet::PacketHeader`]s instead, which do not carry the packet payload.
+pub struct TrafficTrace<P = Packet> {
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
-    pub packets: Vec<Packet>,
+    pub packets: Vec<P>,
 }
 
-impl TrafficTrace {
+impl<P: CapturedPacket> TrafficTrace<P> {
     pub fn duration(&self) -> Duration {
         self.end_time - self.start_time
     }
 
-    pub fn as_binary_trace(&self, relative_to: &MacAddress) -> BinaryTrafficTrace {
+    pub fn as_binary_trace(&self, relative_to: &DeviceAddress) -> BinaryTrafficTrace {
         BinaryTrafficTrace(
             self.packets
                 .iter()
@@ -29,26 +41,112 @@ impl TrafficTrace {
         )
     }
 
-    pub fn as_numeric_trace(&self, relative_to: &MacAddress) -> NumericTrafficTrace {
+    pub fn as_numeric_trace(&self, relative_to: &DeviceAddress) -> NumericTrafficTrace {
         NumericTrafficTrace(
             self.packets
                 .iter()
                 .filter_map(|packet| {
                     packet
                         .direction(relative_to)
-                        .map(|direction| f32::from(direction) * packet.len as f32)
+                        .map(|direction| f32::from(direction) * packet.length() as f32)
                 })
                 .collect(),
         )
     }
 
-    pub fn as_wang_traffic_trace(&self, relative_to: &MacAddress) -> WangTrafficTrace {
+    /// Group the packets of the trace into bursts, starting a new burst whenever no packet was
+    /// sent or received for longer than `gap`.
+    ///
+    /// Packets that were neither sent nor received by the device are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_to`: The address of the device to get the direction relative to.
+    /// * `gap`: How long the traffic must pause to end a burst.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str::FromStr;
+    /// # use chrono::{Duration, TimeZone, Utc};
+    /// # use varys_analysis::trace::TrafficTrace;
+    /// # use varys_network::address::{DeviceAddress, MacAddress};
+    /// # use varys_network::packet::PacketHeader;
+    /// let assistant = MacAddress::from_str("f0:18:98:12:34:56").unwrap();
+    /// let router = MacAddress::from_str("00:00:5e:00:53:01").unwrap();
+    /// let start = Utc.timestamp_opt(0, 0).unwrap();
+    /// let packet = |milliseconds, len, outgoing| PacketHeader {
+    ///     timestamp: start + Duration::milliseconds(milliseconds),
+    ///     len,
+    ///     source: Some(if outgoing { assistant } else { router }),
+    ///     destination: Some(if outgoing { router } else { assistant }),
+    ///     source_ip: None,
+    ///     destination_ip: None,
+    /// };
+    /// let trace = TrafficTrace::try_from(vec![
+    ///     packet(0, 1000, true),
+    ///     packet(100, 60, false),
+    ///     packet(2000, 1500, false),
+    ///     packet(2100, 1500, false),
+    /// ])
+    /// .unwrap();
+    ///
+    /// let bursts = trace.bursts(&DeviceAddress::from(assistant), Duration::milliseconds(500));
+    /// assert_eq!(bursts.len(), 2);
+    /// assert_eq!((bursts[0].sent_bytes, bursts[0].received_bytes), (1000, 60));
+    /// assert_eq!(bursts[1].received_packets, 2);
+    /// assert_eq!(bursts[1].duration(), Duration::milliseconds(100));
+    /// ```
+    pub fn bursts(&self, relative_to: &DeviceAddress, gap: Duration) -> Vec<Burst> {
+        burst::bursts(&self.packets, relative_to, gap)
+    }
+
+    /// Like [`TrafficTrace::as_numeric_trace`] but with two values per burst instead of one per
+    /// packet: the bytes sent by the device followed by the negated bytes received.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_to`: The address of the device to get the direction relative to.
+    /// * `gap`: How long the traffic must pause to end a burst, see [`TrafficTrace::bursts`].
+    pub fn as_burst_trace(
+        &self,
+        relative_to: &DeviceAddress,
+        gap: Duration,
+    ) -> NumericTrafficTrace {
+        NumericTrafficTrace(
+            self.bursts(relative_to, gap)
+                .iter()
+                .flat_map(|burst| [burst.sent_bytes as f32, -(burst.received_bytes as f32)])
+                .collect(),
+        )
+    }
+
+    /// Convert the trace into the numeric form given by `representation`.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_to`: The address of the device to get the direction relative to.
+    /// * `representation`: Whether to use packets or bursts.
+    pub fn as_representation(
+        &self,
+        relative_to: &DeviceAddress,
+        representation: TraceRepresentation,
+    ) -> NumericTrafficTrace {
+        match representation {
+            TraceRepresentation::Packets => self.as_numeric_trace(relative_to),
+            TraceRepresentation::Bursts { gap } => {
+                self.as_burst_trace(relative_to, Duration::milliseconds(gap as i64))
+            }
+        }
+    }
+
+    pub fn as_wang_traffic_trace(&self, relative_to: &DeviceAddress) -> WangTrafficTrace {
         let start_time = self
             .packets
             .iter()
-            .reduce(|a, b| if a.timestamp < b.timestamp { a } else { b })
-            .expect("Traffic trace was empty")
-            .timestamp;
+            .map(|packet| packet.timestamp())
+            .min()
+            .expect("Traffic trace was empty");
 
         WangTrafficTrace(
             self.packets
@@ -56,9 +154,9 @@ impl TrafficTrace {
                 .filter_map(|packet| {
                     packet.direction(relative_to).map(|direction| {
                         (
-                            (packet.timestamp - start_time).num_nanoseconds().unwrap() as f32
+                            (packet.timestamp() - start_time).num_nanoseconds().unwrap() as f32
                                 / 1000000000.,
-                            packet.len as f32,
+                            packet.length() as f32,
                             f32::from(direction),
                         )
                     })
@@ -68,14 +166,71 @@ impl TrafficTrace {
     }
 }
 
-impl TryFrom<Vec<Packet>> for TrafficTrace {
+impl TrafficTrace<Packet> {
+    /// Remove packets that are unrelated to the interaction with the voice assistant.
+    ///
+    /// This drops ARP, multicast DNS and TCP packets that only acknowledge data. Packets that
+    /// cannot be decoded are kept.
+    pub fn remove_noise(&mut self) -> &mut Self {
+        self.packets
+            .retain(|packet| !packet.decode().is_ok_and(|decoded| decoded.is_noise()));
+
+        self
+    }
+
+    /// Only keep packets exchanged with the given hostname or any of its subdomains.
+    ///
+    /// # Arguments
+    ///
+    /// * `hostnames`: The hostnames of the addresses in this trace, e.g. read from the same
+    ///   capture with [`varys_network::hostname::read_hostnames`].
+    /// * `hostname`: The hostname to keep packets of, e.g. `apple.com`.
+    pub fn retain_hostname(&mut self, hostnames: &Hostnames, hostname: &str) -> &mut Self {
+        self.packets.retain(|packet| {
+            packet
+                .decode()
+                .ok()
+                .and_then(|decoded| decoded.ip)
+                .is_some_and(|ip| {
+                    hostnames.matches(&ip.source, hostname)
+                        || hostnames.matches(&ip.destination, hostname)
+                })
+        });
+
+        self
+    }
+
+    /// Like [`TrafficTrace::as_numeric_trace`] but uses the size of the application payload
+    /// without any headers instead of the length of the whole frame.
+    ///
+    /// Packets that cannot be decoded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_to`: The address of the device to get the direction relative to.
+    pub fn as_payload_trace(&self, relative_to: &DeviceAddress) -> NumericTrafficTrace {
+        NumericTrafficTrace(
+            self.packets
+                .iter()
+                .filter_map(|packet| {
+                    let direction = packet.direction(relative_to)?;
+                    let decoded = packet.decode().ok()?;
+
+                    Some(f32::from(direction) * decoded.payload_len as f32)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<P: CapturedPacket> TryFrom<Vec<P>> for TrafficTrace<P> {
     type Error = Error;
 
-    fn try_from(mut packets: Vec<Packet>) -> Result<Self, Self::Error> {
-        packets.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    fn try_from(mut packets: Vec<P>) -> Result<Self, Self::Error> {
+        packets.sort_by_key(|packet| packet.timestamp());
 
-        let start_time = packets.first().ok_or(Error::EmptyTrace)?.timestamp;
-        let end_time = packets.last().ok_or(Error::EmptyTrace)?.timestamp;
+        let start_time = packets.first().ok_or(Error::EmptyTrace)?.timestamp();
+        let end_time = packets.last().ok_or(Error::EmptyTrace)?.timestamp();
 
         Ok(Self {
             start_time,
@@ -85,7 +240,17 @@ impl TryFrom<Vec<Packet>> for TrafficTrace {
     }
 }
 
-impl Display for TrafficTrace {
+/// Create a trace of a single connection, e.g. one split off with a
+/// [`varys_network::flow::FlowTable`].
+impl<P: CapturedPacket> TryFrom<Flow<P>> for TrafficTrace<P> {
+    type Error = Error;
+
+    fn try_from(flow: Flow<P>) -> Result<Self, Self::Error> {
+        TrafficTrace::try_from(flow.packets)
+    }
+}
+
+impl<P: CapturedPacket> Display for TrafficTrace<P> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
@@ -98,6 +263,18 @@ impl Display for TrafficTrace {
     }
 }
 
+/// How a [`TrafficTrace`] is turned into a [`NumericTrafficTrace`].
+#[derive(Deserialize, Serialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TraceRepresentation {
+    /// One value per packet, see [`TrafficTrace::as_numeric_trace`].
+    #[default]
+    Packets,
+    /// Two values per burst, see [`TrafficTrace::as_burst_trace`].
+    ///
+    /// `gap` is the pause in milliseconds that ends a burst.
+    Bursts { gap: u64 },
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct BinaryTrafficTrace(pub Vec<bool>);
 
@@ -4,11 +4,16 @@ use audiopus::coder::Encoder;
 use audiopus::{Application, Bitrate, Channels, SampleRate};
 use log::{debug, trace};
 
+use crate::audio::resample::ResampleQuality;
 use crate::error::Error;
 
+pub mod resample;
+
 const OPUS_FRAME_TIME: usize = 20; // ms (see https://datatracker.ietf.org/doc/html/rfc6716#section-2.1.4)
 const OPUS_FRAME_RATE: usize = 1000 / OPUS_FRAME_TIME; // 1/s
 pub const OPUS_SAMPLE_RATE: usize = 48000; // 1/s (see https://datatracker.ietf.org/doc/html/rfc7845#section-4)
+/// The sample rates Opus can encode, other rates have to be resampled first.
+pub const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12000, 16000, 24000, 48000];
 /// How many silent samples to keep when trimming silence from the start and end of audio.
 pub const TRIM_SILENCE_PADDING: usize = OPUS_SAMPLE_RATE / 10; // 0.1s
 
@@ -73,8 +78,8 @@ impl AudioData {
     ///
     /// Returns an error if the new sample rate is not a divisor of the current sample rate.
     ///
-    /// This uses the nearest-neighbour algorithm, which works well when downsampling by an
-    /// integer factor.
+    /// This uses the nearest-neighbour algorithm, which is fast but aliases frequencies above the
+    /// new Nyquist frequency. Use [`AudioData::resample`] to convert without aliasing.
     ///
     /// # Arguments
     ///
@@ -119,6 +124,51 @@ impl AudioData {
         Ok(self)
     }
 
+    /// Resample the audio data to any other sample rate.
+    ///
+    /// Does nothing if the sample rate is the same as the current one.
+    ///
+    /// The audio is band-limited with a windowed-sinc filter (see [`resample::resample`]), so
+    /// this does not alias when downsampling and can also upsample, e.g. from 44.1kHz to 48kHz.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate`: The new sample rate.
+    /// * `quality`: How accurately to resample.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_audio::audio::AudioData;
+    /// # use varys_audio::audio::resample::ResampleQuality;
+    /// let mut audio = AudioData {
+    ///     data: vec![0.5_f32; 2 * 44100],
+    ///     channels: 2,
+    ///     sample_rate: 44100,
+    /// };
+    /// audio.resample(48000, ResampleQuality::default());
+    ///
+    /// assert_eq!(audio.sample_rate, 48000);
+    /// assert_eq!(audio.data.len(), 2 * 48000);
+    /// assert!((audio.data[48000] - 0.5).abs() < 0.001);
+    /// ```
+    pub fn resample(&mut self, sample_rate: u32, quality: ResampleQuality) -> &mut Self {
+        if self.sample_rate == sample_rate {
+            return self;
+        }
+
+        self.data = resample::resample(
+            &self.data,
+            self.channels as usize,
+            self.sample_rate,
+            sample_rate,
+            quality,
+        );
+        self.sample_rate = sample_rate;
+
+        self
+    }
+
     /// Trim silent parts of the audio from the start and the end.
     ///
     /// If there is no audio above the threshold, the data is cleared.
@@ -0,0 +1,205 @@
+use std::f64::consts::PI;
+
+use log::debug;
+
+/// The largest number of filter phases to precompute. Conversions that need more phases, e.g.
+/// between rates without a large common divisor, use the nearest precomputed phase.
+const MAX_PHASES: usize = 1024;
+
+/// How accurately [`resample`] converts between sample rates, at the cost of speed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ResampleQuality {
+    /// A short filter, which starts to attenuate frequencies well below the Nyquist frequency.
+    Low,
+    /// A good trade-off for speech.
+    #[default]
+    Medium,
+    /// A long filter with a steep roll-off just below the Nyquist frequency.
+    High,
+}
+
+impl ResampleQuality {
+    /// The number of zero crossings of the sinc function on each side of the filter.
+    fn zero_crossings(&self) -> usize {
+        match self {
+            ResampleQuality::Low => 8,
+            ResampleQuality::Medium => 16,
+            ResampleQuality::High => 32,
+        }
+    }
+
+    /// The cutoff frequency relative to the lower of both Nyquist frequencies.
+    fn rolloff(&self) -> f64 {
+        match self {
+            ResampleQuality::Low => 0.85,
+            ResampleQuality::Medium => 0.92,
+            ResampleQuality::High => 0.96,
+        }
+    }
+}
+
+/// A windowed-sinc low-pass filter, precomputed for each fractional position between two input
+/// samples.
+struct Filter {
+    /// The number of input samples on each side of an output sample that are weighed.
+    half_width: usize,
+    /// The weights of the `2 * half_width` input samples around each phase.
+    phases: Vec<Vec<f32>>,
+}
+
+impl Filter {
+    fn new(from: u32, to: u32, quality: ResampleQuality) -> Self {
+        // the cutoff is relative to the input sample rate and lowered when downsampling to avoid
+        // aliasing
+        let cutoff = quality.rolloff() * (to as f64 / from as f64).min(1.0);
+        let half_width = (quality.zero_crossings() as f64 / cutoff).ceil() as usize;
+        let phase_count = ((to / gcd(from, to)) as usize).min(MAX_PHASES);
+
+        let phases = (0..phase_count)
+            .map(|phase| {
+                let fraction = phase as f64 / phase_count as f64;
+                let mut weights: Vec<f64> = (0..2 * half_width)
+                    .map(|tap| {
+                        // the distance from the output sample to the input sample of this tap
+                        let x = fraction - (tap as f64 - (half_width as f64 - 1.0));
+                        cutoff * sinc(cutoff * x) * blackman(x / half_width as f64)
+                    })
+                    .collect();
+                // normalise the weights, so constant signals keep their level
+                let sum: f64 = weights.iter().sum();
+                weights.iter_mut().for_each(|weight| *weight /= sum);
+                weights.into_iter().map(|weight| weight as f32).collect()
+            })
+            .collect();
+
+        Filter { half_width, phases }
+    }
+}
+
+/// Convert interleaved audio samples from one sample rate to another.
+///
+/// This uses a windowed-sinc filter, which band-limits the audio to below the Nyquist frequency of
+/// the lower sample rate, so no aliasing is introduced when downsampling. Any sample rates can be
+/// converted, upwards and downwards. Samples outside the audio are treated as silence.
+///
+/// # Arguments
+///
+/// * `data`: The interleaved samples.
+/// * `channels`: The number of channels in `data`.
+/// * `from`: The sample rate of `data`.
+/// * `to`: The sample rate to convert to.
+/// * `quality`: How accurately to convert.
+///
+/// # Examples
+///
+/// Resampling keeps the frequency of a sine wave:
+///
+/// ```
+/// # use std::f32::consts::PI;
+/// # use varys_audio::audio::resample::{resample, ResampleQuality};
+/// let sine = |rate: u32, len: usize| -> Vec<f32> {
+///     (0..len)
+///         .map(|i| (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin())
+///         .collect()
+/// };
+///
+/// let resampled = resample(&sine(44100, 44100), 1, 44100, 48000, ResampleQuality::Medium);
+/// let expected = sine(48000, 48000);
+///
+/// assert_eq!(resampled.len(), expected.len());
+/// // ignore the edges, where the filter reaches past the audio
+/// for (sample, expected) in resampled.iter().zip(&expected).skip(100).take(47800) {
+///     assert!((sample - expected).abs() < 0.01);
+/// }
+/// ```
+///
+/// Frequencies that do not fit into the new sample rate are removed instead of folding into lower
+/// frequencies:
+///
+/// ```
+/// # use std::f32::consts::PI;
+/// # use varys_audio::audio::resample::{resample, ResampleQuality};
+/// // 10kHz is above the Nyquist frequency of 16kHz audio
+/// let sine: Vec<f32> = (0..48000)
+///     .map(|i| (2.0 * PI * 10000.0 * i as f32 / 48000.0).sin())
+///     .collect();
+///
+/// let resampled = resample(&sine, 1, 48000, 16000, ResampleQuality::Medium);
+///
+/// assert_eq!(resampled.len(), 16000);
+/// assert!(resampled[100..15900].iter().all(|sample| sample.abs() < 0.01));
+/// ```
+pub fn resample(
+    data: &[f32],
+    channels: usize,
+    from: u32,
+    to: u32,
+    quality: ResampleQuality,
+) -> Vec<f32> {
+    if from == to || data.is_empty() || channels == 0 {
+        return data.to_vec();
+    }
+
+    debug!("Resampling {from}Hz to {to}Hz with {quality:?} quality...");
+
+    let filter = Filter::new(from, to, quality);
+    let phase_count = filter.phases.len() as u64;
+    let frames = data.len() / channels;
+    let output_frames = (frames as u64 * to as u64).div_ceil(from as u64) as usize;
+    let mut output = Vec::with_capacity(output_frames * channels);
+
+    for frame in 0..output_frames as u64 {
+        // the position of the output frame in input frames is `index + remainder / to`
+        let position = frame * from as u64;
+        let mut index = (position / to as u64) as usize;
+        let mut phase = ((position % to as u64) * phase_count + to as u64 / 2) / to as u64;
+        if phase == phase_count {
+            index += 1;
+            phase = 0;
+        }
+        let weights = &filter.phases[phase as usize];
+        // the input frame of the first tap, which may be before the start of the audio
+        let first = index as isize - (filter.half_width as isize - 1);
+
+        for channel in 0..channels {
+            let sample = weights
+                .iter()
+                .enumerate()
+                .filter_map(|(tap, weight)| {
+                    let input = first + tap as isize;
+                    (0..frames as isize)
+                        .contains(&input)
+                        .then(|| weight * data[input as usize * channels + channel])
+                })
+                .sum();
+            output.push(sample);
+        }
+    }
+
+    output
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The Blackman window, where `x` is between -1 and 1.
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        0.0
+    } else {
+        0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+    }
+}
@@ -32,6 +32,10 @@ pub enum Error {
     Cpal(String),
     #[error("Hound error: {0}")]
     Hound(String),
+    #[error("Ogg error: {0}")]
+    Ogg(String),
+    #[error("Invalid Opus file: {0}")]
+    InvalidOpusFile(&'static str),
 
     // tts
     #[error("Required feature {0} is unsupported")]
@@ -40,6 +44,8 @@ pub enum Error {
     VoiceNotAvailable(String),
     #[error("Tts error: {0}")]
     Tts(String),
+    #[error("There is no recording of \"{0}\"")]
+    RecordingNotFound(String),
 
     // stt
     #[error("Recording is too short to be processed by whisper")]
@@ -117,6 +123,15 @@ impl From<hound::Error> for Error {
     }
 }
 
+impl From<ogg::OggReadError> for Error {
+    fn from(value: ogg::OggReadError) -> Self {
+        match value {
+            ogg::OggReadError::ReadError(err) => err.into(),
+            _ => Error::Ogg(value.to_string()),
+        }
+    }
+}
+
 impl From<whisper_rs::WhisperError> for Error {
     fn from(value: whisper_rs::WhisperError) -> Self {
         match value {
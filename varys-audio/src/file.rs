@@ -2,12 +2,15 @@ use std::ffi::OsStr;
 use std::fs::File;
 use std::path::Path;
 
-use hound::WavSpec;
-use log::debug;
-use ogg::{PacketWriteEndInfo, PacketWriter};
+use audiopus::coder::Decoder;
+use audiopus::{Channels, MutSignals, SampleRate};
+use hound::{SampleFormat, WavSpec};
+use log::{debug, trace};
+use ogg::{PacketReader, PacketWriteEndInfo, PacketWriter};
 use rand::RngCore;
 
 use crate::audio;
+use crate::audio::resample::{self, ResampleQuality};
 use crate::audio::AudioData;
 use crate::error::Error;
 
@@ -86,7 +89,7 @@ pub fn write_audio(file_path: &Path, audio: &AudioData) -> Result<(), Error> {
 /// ```
 pub fn write_wav(file_path: &Path, audio: &AudioData) -> Result<(), Error> {
     let wav_config = WavSpec {
-        channels: 1,
+        channels: audio.channels.into(),
         sample_rate: audio.sample_rate,
         bits_per_sample: 32,
         sample_format: hound::SampleFormat::Float,
@@ -141,6 +144,25 @@ pub fn write_opus(file_path: &Path, audio: &AudioData) -> Result<(), Error> {
 
     debug!("Writing .opus file {:?}", file_path);
 
+    // opus only supports some sample rates, e.g. not the 44.1kHz of many input devices
+    let resampled;
+    let audio = if audio::OPUS_SAMPLE_RATES.contains(&audio.sample_rate) {
+        audio
+    } else {
+        resampled = AudioData {
+            data: resample::resample(
+                &audio.data,
+                audio.channels as usize,
+                audio.sample_rate,
+                audio::OPUS_SAMPLE_RATE as u32,
+                ResampleQuality::default(),
+            ),
+            channels: audio.channels,
+            sample_rate: audio::OPUS_SAMPLE_RATE as u32,
+        };
+        &resampled
+    };
+
     let (encoded_frames, padding, frame_size) = audio.encode_opus()?;
     let file = File::create(file_path)?;
     let mut writer = PacketWriter::new(file);
@@ -180,6 +202,163 @@ pub fn write_opus(file_path: &Path, audio: &AudioData) -> Result<(), Error> {
     Ok(())
 }
 
+/// Read audio data from a file determined by the file extension.
+///
+/// Returns an error if the file could not be read or decoded.
+///
+/// # Arguments
+///
+/// * `file_path`: The file to read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use varys_audio::file::read_audio;
+/// let audio = read_audio(Path::new("s1i2-response-audio.opus")).unwrap();
+///
+/// println!("{} channels at {}hz", audio.channels, audio.sample_rate);
+/// ```
+pub fn read_audio(file_path: &Path) -> Result<AudioData, Error> {
+    match AudioFileType::from(file_path) {
+        AudioFileType::Wav => read_wav(file_path),
+        AudioFileType::Opus => read_opus(file_path),
+    }
+}
+
+/// Read audio data from a `.wav` file.
+///
+/// Integer samples are scaled to the range `[-1, 1]`. The channel count and sample rate of the
+/// file are kept.
+///
+/// Returns an error if the file could not be read or has more than 255 channels.
+///
+/// # Arguments
+///
+/// * `file_path`: The file to read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use varys_audio::file::{read_wav, write_wav};
+/// # use varys_audio::audio::AudioData;
+/// let audio = AudioData {
+///     data: vec![0_f32, 0.5_f32, -0.5_f32],
+///     channels: 1,
+///     sample_rate: 48000,
+/// };
+/// write_wav(Path::new("audio.wav"), &audio).unwrap();
+///
+/// let read = read_wav(Path::new("audio.wav")).unwrap();
+/// assert_eq!(read.data, audio.data);
+/// assert_eq!(read.sample_rate, audio.sample_rate);
+/// ```
+pub fn read_wav(file_path: &Path) -> Result<AudioData, Error> {
+    let mut reader = hound::WavReader::open(file_path)?;
+    let spec = reader.spec();
+
+    debug!("Reading .wav file {:?} with config {:?}", file_path, spec);
+
+    let data = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        SampleFormat::Int => {
+            let scale = 1. / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    Ok(AudioData {
+        data,
+        channels: u8::try_from(spec.channels).map_err(|_| Error::OutOfRange)?,
+        sample_rate: spec.sample_rate,
+    })
+}
+
+/// Read audio data from an Ogg/Opus file, e.g. one written by [`write_opus`].
+///
+/// The audio is decoded at the input sample rate stored in the file if Opus supports it and at
+/// 48000hz otherwise. The channel count of the file is kept and the pre-skip samples at its start
+/// are dropped.
+///
+/// Returns an error if the file could not be read, is not an Opus file or uses more than two
+/// channels.
+///
+/// # Arguments
+///
+/// * `file_path`: The file to read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use varys_audio::file::{read_opus, write_opus};
+/// # use varys_audio::audio::AudioData;
+/// let audio = AudioData {
+///     data: vec![0_f32; 48000],
+///     channels: 1,
+///     sample_rate: 48000,
+/// };
+/// write_opus(Path::new("audio.opus"), &audio).unwrap();
+///
+/// let read = read_opus(Path::new("audio.opus")).unwrap();
+/// assert_eq!(read.channels, audio.channels);
+/// assert_eq!(read.sample_rate, audio.sample_rate);
+/// ```
+pub fn read_opus(file_path: &Path) -> Result<AudioData, Error> {
+    debug!("Reading .opus file {:?}", file_path);
+
+    let mut reader = PacketReader::new(File::open(file_path)?);
+    let header = reader
+        .read_packet()?
+        .ok_or(Error::InvalidOpusFile("the file is empty"))?
+        .data;
+    // see `opus_id_header` for the structure of the identification header
+    if header.len() < 19 || !header.starts_with(b"OpusHead") {
+        return Err(Error::InvalidOpusFile("missing identification header"));
+    }
+    let channels = header[9];
+    let pre_skip = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let input_sample_rate = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+    let sample_rate = i32::try_from(input_sample_rate)
+        .ok()
+        .and_then(|sample_rate| SampleRate::try_from(sample_rate).ok())
+        .unwrap_or(SampleRate::Hz48000);
+
+    // the comment header does not contain anything needed for decoding
+    reader
+        .read_packet()?
+        .ok_or(Error::InvalidOpusFile("missing comment header"))?;
+
+    let mut decoder = Decoder::new(sample_rate, Channels::try_from(i32::from(channels))?)?;
+    // a packet contains at most 120ms of audio
+    let mut buffer = vec![0.; sample_rate as usize * 120 / 1000 * channels as usize];
+    let mut data = Vec::new();
+
+    while let Some(packet) = reader.read_packet()? {
+        let samples = decoder.decode_float(
+            Some(packet.data.as_slice().try_into()?),
+            MutSignals::try_from(&mut buffer)?,
+            false,
+        )?;
+        data.extend_from_slice(&buffer[..samples * channels as usize]);
+    }
+    trace!("Decoded {} samples", data.len());
+
+    // the pre-skip is given at 48000hz (see https://datatracker.ietf.org/doc/html/rfc7845#section-4.2)
+    let skip = pre_skip * sample_rate as usize / audio::OPUS_SAMPLE_RATE * channels as usize;
+    data.drain(..skip.min(data.len()));
+
+    Ok(AudioData {
+        data,
+        channels,
+        sample_rate: sample_rate as u32,
+    })
+}
+
 fn opus_id_header(audio: &AudioData, padding: u16) -> Result<Vec<u8>, Error> {
     // the identification header is structured as follows:
     //
@@ -207,7 +386,7 @@ fn opus_id_header(audio: &AudioData, padding: u16) -> Result<Vec<u8>, Error> {
     header.extend(b"OpusHead");
     header.push(1); // opus version number
     header.push(audio.channels);
-    header.extend(&padding.to_le_bytes()); // pre-skip (see https://datatracker.ietf.org/doc/html/rfc7845#section-4.2)
+    header.extend(&opus_pre_skip(audio, padding).to_le_bytes()); // pre-skip (see https://datatracker.ietf.org/doc/html/rfc7845#section-4.2)
     header.extend(&audio.sample_rate.to_le_bytes()); // samples per second
     header.extend(&0_u16.to_le_bytes()); // output gain
     header.push(0); // mapping family
@@ -215,6 +394,13 @@ fn opus_id_header(audio: &AudioData, padding: u16) -> Result<Vec<u8>, Error> {
     Ok(header)
 }
 
+/// The number of samples to skip at the start of an Opus stream, which is always given at 48000hz.
+fn opus_pre_skip(audio: &AudioData, padding: u16) -> u16 {
+    let pre_skip = padding as usize * audio::OPUS_SAMPLE_RATE / audio.sample_rate.max(1) as usize;
+
+    u16::try_from(pre_skip).unwrap_or(u16::MAX)
+}
+
 fn opus_comment_header() -> Result<Vec<u8>, Error> {
     // the comment header is structured as follows:
     //
@@ -54,16 +54,17 @@ impl Listener {
             debug!("Using audio device {}", name);
         }
 
-        let device_config: StreamConfig = device
+        let config = device
             .supported_input_configs()?
             .find(|config| {
                 config.sample_format() == SampleFormat::F32
                     && config.max_sample_rate().0 >= Recogniser::SAMPLE_RATE
-                    && config.max_sample_rate().0 >= OPUS_SAMPLE_RATE as u32
             })
-            .ok_or(Error::ConfigurationNotSupported)?
-            .with_sample_rate(SampleRate(OPUS_SAMPLE_RATE as u32))
-            .into();
+            .ok_or(Error::ConfigurationNotSupported)?;
+        // record at the opus sample rate if possible, the audio is resampled otherwise
+        let sample_rate =
+            (OPUS_SAMPLE_RATE as u32).clamp(config.min_sample_rate().0, config.max_sample_rate().0);
+        let device_config: StreamConfig = config.with_sample_rate(SampleRate(sample_rate)).into();
         debug!("Using audio input config {:?}", device_config);
 
         Ok(Listener {
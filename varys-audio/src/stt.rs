@@ -1,20 +1,32 @@
+use std::time::Duration;
+
 use log::{debug, info, trace, warn};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
+use crate::audio::resample::ResampleQuality;
 use crate::audio::AudioData;
 use crate::error::Error;
+use crate::stt::config::RecogniserConfig;
+use crate::stt::segment::{Segment, Token};
 
+pub mod config;
+pub mod segment;
 pub mod transcribe;
 pub mod transcriber;
 
 /// Wraps the whisper API.
 pub struct Recogniser {
     context: WhisperContext,
+    config: RecogniserConfig,
 }
 
 impl Recogniser {
     /// This sample rate is expected by whisper, so all audio data has to be resampled to this.
     pub const SAMPLE_RATE: u32 = 16_000;
+    /// Whisper reports the start and end of segments in units of ten milliseconds.
+    const TIMESTAMP_UNIT_MS: u64 = 10;
+    /// Whisper uses at most this many tokens of a prompt.
+    const MAX_PROMPT_TOKENS: usize = 224;
 
     /// Create a new recogniser that uses the model stored at the given file path.
     ///
@@ -34,16 +46,49 @@ impl Recogniser {
     /// let recogniser = Recogniser::with_model_path(&path).unwrap();
     /// ```
     pub fn with_model_path(model_path: &str) -> Result<Recogniser, Error> {
+        Recogniser::with_config(model_path, RecogniserConfig::default())
+    }
+
+    /// Create a new recogniser that uses the model stored at the given file path and decodes
+    /// speech as configured.
+    ///
+    /// Returns an error if the model could not be loaded or does not have proper `ggml` format.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path`: The path to the whisper model to use. The model must be in `ggml` format.
+    /// * `config`: How to decode speech.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_audio::stt::{Model, MODEL_LARGE, Recogniser};
+    /// # use varys_audio::stt::config::RecogniserConfig;
+    /// # let path = format!("../{}", MODEL_LARGE);
+    /// let config = RecogniserConfig {
+    ///     beam_size: Some(5),
+    ///     ..RecogniserConfig::default()
+    /// };
+    /// let recogniser = Recogniser::with_config(&path, config).unwrap();
+    /// ```
+    pub fn with_config(model_path: &str, config: RecogniserConfig) -> Result<Recogniser, Error> {
         let mut params = WhisperContextParameters::default();
         params.use_gpu(true);
 
         info!("Using model: {model_path}");
+        debug!("Using recogniser config: {config:?}");
 
         Ok(Recogniser {
             context: WhisperContext::new_with_params(model_path, params)?,
+            config,
         })
     }
 
+    /// How this recogniser decodes speech.
+    pub fn config(&self) -> &RecogniserConfig {
+        &self.config
+    }
+
     /// Convert speech in the given audio data to text.
     ///
     /// Forwards any errors that whisper returns.
@@ -70,6 +115,49 @@ impl Recogniser {
     /// let _ = recogniser.recognise(&mut audio);
     /// ```
     pub fn recognise(&self, audio: &mut AudioData) -> Result<String, Error> {
+        let full_text = segment::text(&self.recognise_segments(audio, false, None)?);
+
+        debug!("Recognised: {}", full_text);
+
+        Ok(full_text)
+    }
+
+    /// Convert speech in the given audio data to text, split into segments with the times they
+    /// were spoken at.
+    ///
+    /// Forwards any errors that whisper returns. Like [`Recogniser::recognise`], this first
+    /// preprocesses the audio.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio`: The audio to recognise.
+    /// * `tokens`: Whether to include the tokens of each segment with their probabilities.
+    /// * `query`: The query the audio responds to, which is added to the prompt if the config
+    ///   asks for it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_audio::audio::AudioData;
+    /// # use varys_audio::stt::{Model, MODEL_LARGE, Recogniser};
+    /// # let path = format!("../{}", MODEL_LARGE);
+    /// let mut audio = AudioData {
+    ///     data: vec![0_f32; 32000],
+    ///     channels: 1,
+    ///     sample_rate: 16000,
+    /// };
+    /// let recogniser = Recogniser::with_model_path(&path).unwrap();
+    ///
+    /// for segment in recogniser.recognise_segments(&mut audio, true, None).unwrap() {
+    ///     println!("{segment}");
+    /// }
+    /// ```
+    pub fn recognise_segments(
+        &self,
+        audio: &mut AudioData,
+        tokens: bool,
+        query: Option<&str>,
+    ) -> Result<Vec<Segment>, Error> {
         if audio.duration_s() < 1.0 {
             warn!("Whisper cannot recognise audio shorter than one second");
 
@@ -81,26 +169,49 @@ impl Recogniser {
         Recogniser::preprocess(audio)?;
 
         let mut state = self.context.create_state()?;
-        let mut full_text = String::new();
+        let prompt_tokens = match self.config.prompt(query) {
+            Some(prompt) => {
+                trace!("Prompting with: {prompt}");
+                self.context
+                    .tokenize(&prompt, Recogniser::MAX_PROMPT_TOKENS)?
+            }
+            None => Vec::new(),
+        };
 
-        state.full(self.get_params(), &audio.data)?;
+        state.full(self.get_params(&prompt_tokens), &audio.data)?;
 
         let segment_count = state.full_n_segments()?;
+        let mut segments = Vec::with_capacity(segment_count as usize);
         for i in 0..segment_count {
-            let segment = state.full_get_segment_text(i)?;
-            full_text.push_str(&segment);
-            let timestamps = (state.full_get_segment_t0(i)?, state.full_get_segment_t1(i)?);
-            trace!(
-                "Recognised segment [{} - {}]: {}",
-                timestamps.0,
-                timestamps.1,
-                segment
-            );
+            let mut segment_tokens = Vec::new();
+            if tokens {
+                for j in 0..state.full_n_tokens(i)? {
+                    // skip special tokens like the end of text and timestamps
+                    if state.full_get_token_id(i, j)? >= self.context.token_eot() {
+                        continue;
+                    }
+                    segment_tokens.push(Token {
+                        text: state.full_get_token_text(i, j)?,
+                        probability: state.full_get_token_prob(i, j)?,
+                    });
+                }
+            }
+
+            let segment = Segment {
+                start: Recogniser::timestamp(state.full_get_segment_t0(i)?),
+                end: Recogniser::timestamp(state.full_get_segment_t1(i)?),
+                text: state.full_get_segment_text(i)?,
+                tokens: segment_tokens,
+            };
+            trace!("Recognised segment {segment}");
+            segments.push(segment);
         }
 
-        debug!("Recognised: {}", full_text);
+        Ok(segments)
+    }
 
-        Ok(full_text)
+    fn timestamp(timestamp: i64) -> Duration {
+        Duration::from_millis(timestamp.max(0) as u64 * Recogniser::TIMESTAMP_UNIT_MS)
     }
 
     fn preprocess(audio: &mut AudioData) -> Result<(), Error> {
@@ -108,13 +219,29 @@ impl Recogniser {
 
         audio
             .convert_to_mono()
-            .downsample(Recogniser::SAMPLE_RATE)?;
+            .resample(Recogniser::SAMPLE_RATE, ResampleQuality::default());
 
         Ok(())
     }
 
-    fn get_params(&self) -> FullParams {
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    fn get_params<'a>(&'a self, prompt_tokens: &'a [i32]) -> FullParams<'a, 'a> {
+        let strategy = match self.config.beam_size {
+            Some(beam_size) => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience: -1.0,
+            },
+            None => SamplingStrategy::Greedy { best_of: 1 },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_language(Some(&self.config.language));
+        params.set_temperature(self.config.temperature);
+        params.set_temperature_inc(self.config.temperature_increment);
+        if let Some(threads) = self.config.threads {
+            params.set_n_threads(threads as i32);
+        }
+        if !prompt_tokens.is_empty() {
+            params.set_tokens(prompt_tokens);
+        }
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
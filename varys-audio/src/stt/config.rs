@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// How a [`Recogniser`](super::Recogniser) decodes speech.
+///
+/// The config can be read from a TOML file, where missing values keep their defaults:
+///
+/// ```toml
+/// beam_size = 5
+/// language = "auto"
+/// initial_prompt = "A voice assistant answers a question."
+/// query_prompt = true
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecogniserConfig {
+    /// The number of beams to search or `None` to decode greedily.
+    pub beam_size: Option<u32>,
+    /// The temperature to sample with first.
+    pub temperature: f32,
+    /// How much to increase the temperature each time decoding fails. A value of 0 disables the
+    /// fallback.
+    pub temperature_increment: f32,
+    /// The language of the speech as an ISO 639-1 code (e.g. `en`) or
+    /// [`RecogniserConfig::AUTO_DETECT`] to detect it.
+    pub language: String,
+    /// The number of threads to decode with or `None` to let whisper decide.
+    pub threads: Option<u32>,
+    /// Text to prompt the decoder with, e.g. to introduce vocabulary or a style of writing.
+    pub initial_prompt: Option<String>,
+    /// Whether to add the query a response is recognised for to the prompt, so the decoder
+    /// expects its vocabulary.
+    pub query_prompt: bool,
+}
+
+impl RecogniserConfig {
+    /// The language to set to detect the language of the speech.
+    pub const AUTO_DETECT: &'static str = "auto";
+
+    /// The prompt to decode a response to a query with.
+    ///
+    /// Returns `None` if there is neither an initial prompt nor a query to add to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `query`: The query the response answers, if it is known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_audio::stt::config::RecogniserConfig;
+    /// let config = RecogniserConfig {
+    ///     initial_prompt: Some("Weather report.".to_string()),
+    ///     query_prompt: true,
+    ///     ..RecogniserConfig::default()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     config.prompt(Some("Will it rain in Zurich?")),
+    ///     Some("Weather report. Will it rain in Zurich?".to_string())
+    /// );
+    /// assert_eq!(RecogniserConfig::default().prompt(Some("Will it rain?")), None);
+    /// ```
+    pub fn prompt(&self, query: Option<&str>) -> Option<String> {
+        let query = query.filter(|_| self.query_prompt);
+
+        match (&self.initial_prompt, query) {
+            (Some(prompt), Some(query)) => Some(format!("{prompt} {query}")),
+            (Some(prompt), None) => Some(prompt.clone()),
+            (None, Some(query)) => Some(query.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// The config as JSON, as it is stored with sessions and transcripts.
+    ///
+    /// The default config is stored as `{}`, like the config of sessions and transcripts that
+    /// were stored before the config was, so the same config is always stored the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_audio::stt::config::RecogniserConfig;
+    /// let config = RecogniserConfig {
+    ///     beam_size: Some(5),
+    ///     ..RecogniserConfig::default()
+    /// };
+    ///
+    /// assert_eq!(RecogniserConfig::default().to_json().unwrap(), "{}");
+    /// assert_eq!(
+    ///     serde_json::from_str::<RecogniserConfig>(&config.to_json().unwrap()).unwrap(),
+    ///     config
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        if *self == RecogniserConfig::default() {
+            return Ok("{}".to_string());
+        }
+
+        serde_json::to_string(self)
+    }
+}
+
+impl Default for RecogniserConfig {
+    /// Greedy decoding of English speech without a prompt.
+    fn default() -> Self {
+        RecogniserConfig {
+            beam_size: None,
+            temperature: 0.0,
+            temperature_increment: 0.2,
+            language: "en".to_string(),
+            threads: None,
+            initial_prompt: None,
+            query_prompt: false,
+        }
+    }
+}
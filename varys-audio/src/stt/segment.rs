@@ -0,0 +1,74 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// A part of recognised speech with the time it was spoken at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// When the segment starts, relative to the start of the audio.
+    pub start: Duration,
+    /// When the segment ends, relative to the start of the audio.
+    pub end: Duration,
+    /// The recognised text.
+    pub text: String,
+    /// The tokens the text consists of.
+    ///
+    /// This is empty unless tokens were requested from
+    /// [`Recogniser::recognise_segments`](super::Recogniser::recognise_segments).
+    pub tokens: Vec<Token>,
+}
+
+/// A single token of a [`Segment`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    /// The text of the token, usually a word or part of a word.
+    pub text: String,
+    /// How likely the token is, between 0 and 1.
+    pub probability: f32,
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:.2}s - {:.2}s]: {}",
+            self.start.as_secs_f32(),
+            self.end.as_secs_f32(),
+            self.text
+        )
+    }
+}
+
+/// Join the text of segments to the full text that was recognised.
+///
+/// # Arguments
+///
+/// * `segments`: The segments to join.
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// # use varys_audio::stt::segment::{self, Segment};
+/// let segments = [
+///     Segment {
+///         start: Duration::ZERO,
+///         end: Duration::from_millis(1200),
+///         text: " It is sunny.".to_string(),
+///         tokens: vec![],
+///     },
+///     Segment {
+///         start: Duration::from_millis(1200),
+///         end: Duration::from_millis(2500),
+///         text: " Enjoy your day.".to_string(),
+///         tokens: vec![],
+///     },
+/// ];
+///
+/// assert_eq!(segment::text(&segments), " It is sunny. Enjoy your day.");
+/// ```
+pub fn text(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect()
+}
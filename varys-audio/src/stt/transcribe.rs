@@ -1,3 +1,6 @@
+use crate::stt::segment;
+use crate::stt::segment::Segment;
+
 pub trait Transcribe: Sync + Send {
     /// This method will be called after successfully transcribing.
     ///
@@ -5,6 +8,24 @@ pub trait Transcribe: Sync + Send {
     ///
     /// * `text`: The text that was transcribed.
     fn transcribed(&mut self, text: String);
+
+    /// This method will be called after successfully transcribing, with the segments of the text.
+    ///
+    /// By default, this joins the segments and passes the text to [`Transcribe::transcribed`].
+    ///
+    /// # Arguments
+    ///
+    /// * `segments`: The segments that were transcribed.
+    fn segmented(&mut self, segments: Vec<Segment>) {
+        self.transcribed(segment::text(&segments));
+    }
+
+    /// The query the transcribed audio responds to, if it is known.
+    ///
+    /// This is used to prompt the recogniser with the vocabulary of the query.
+    fn query(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl Transcribe for Option<String> {
@@ -74,9 +74,12 @@ impl<T: Transcribe> Transcriber<T> {
 
             match self.audio_receiver.try_recv() {
                 Ok((mut transcribe, mut audio)) => {
-                    match self.recogniser.recognise(&mut audio) {
-                        Ok(text) => {
-                            transcribe.transcribed(text);
+                    match self
+                        .recogniser
+                        .recognise_segments(&mut audio, false, transcribe.query())
+                    {
+                        Ok(segments) => {
+                            transcribe.segmented(segments);
                         }
                         Err(error) => {
                             error!("Failed to recognise response to: {error}");
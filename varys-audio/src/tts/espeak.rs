@@ -0,0 +1,86 @@
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use crate::error::Error;
+use crate::tts::SpeechSynthesizer;
+
+/// The voice eSpeak NG speaks with if no other voice is chosen.
+pub const DEFAULT_VOICE: &str = "en-us";
+
+/// Synthesizes speech with [eSpeak NG](https://github.com/espeak-ng/espeak-ng), which has to be
+/// installed as `espeak-ng`.
+///
+/// Voices are chosen by their language (e.g. `en-us`), name or file as listed by
+/// `espeak-ng --voices`. Variants can be added with a `+`, e.g. `en-us+f3`.
+pub struct Espeak {
+    voice: String,
+}
+
+impl Espeak {
+    /// Create an eSpeak NG synthesizer that speaks with [`DEFAULT_VOICE`].
+    pub fn new() -> Self {
+        Espeak {
+            voice: DEFAULT_VOICE.to_string(),
+        }
+    }
+
+    /// The languages, names and files of all installed voices.
+    fn voices() -> Result<Vec<String>, Error> {
+        let output = Command::new("espeak-ng")
+            .arg("--voices")
+            .output()
+            .map_err(|err| Error::Tts(err.to_string()))?;
+
+        // skip the header, the columns are priority, language, age and gender, name and file
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(1)
+            .flat_map(|line| {
+                line.split_whitespace()
+                    .enumerate()
+                    .filter(|(column, _)| [1, 3, 4].contains(column))
+                    .map(|(_, value)| value.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+}
+
+impl Default for Espeak {
+    fn default() -> Self {
+        Espeak::new()
+    }
+}
+
+impl SpeechSynthesizer for Espeak {
+    fn set_voice(&mut self, id: &str) -> Result<(), Error> {
+        let voice = id.split('+').next().unwrap_or(id);
+        if !Espeak::voices()?.iter().any(|available| available == voice) {
+            return Err(Error::VoiceNotAvailable(id.to_string()));
+        }
+
+        self.voice = id.to_string();
+
+        Ok(())
+    }
+
+    fn say(&self, text: &str) -> Result<Duration, Error> {
+        debug!("Speaking with espeak-ng voice {}", self.voice);
+
+        let start = Instant::now();
+        let status = Command::new("espeak-ng")
+            .arg("-v")
+            .arg(&self.voice)
+            .arg("--")
+            .arg(text)
+            .status()
+            .map_err(|err| Error::Tts(err.to_string()))?;
+        if !status.success() {
+            return Err(Error::Tts(format!("espeak-ng exited with {status}")));
+        }
+
+        Ok(start.elapsed())
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use log::debug;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::file;
+use crate::tts::{playback, SpeechSynthesizer};
+
+/// The piper voice model that is used if no other model is chosen.
+pub const DEFAULT_MODEL: &str = "data/voices/en_US-libritts_r-medium.onnx";
+
+/// The part of a piper model config that is needed to choose a voice.
+#[derive(Deserialize)]
+struct ModelConfig {
+    /// The ids of the speakers of multi-speaker models by name.
+    #[serde(default)]
+    speaker_id_map: HashMap<String, i64>,
+}
+
+/// Synthesizes speech with [piper](https://github.com/rhasspy/piper), which has to be installed.
+///
+/// Each speaker of a multi-speaker model is a voice. Its name is looked up in the
+/// `speaker_id_map` of the model config, optionally with a `p` in front of it as in the LibriTTS
+/// models (e.g. `p3922`). Single-speaker models speak with their only voice by default.
+pub struct Piper {
+    model: PathBuf,
+    speaker: Option<i64>,
+}
+
+impl Piper {
+    /// Create a piper synthesizer that speaks with a voice model.
+    ///
+    /// The model and its config are only read once speaking or choosing a voice.
+    ///
+    /// # Arguments
+    ///
+    /// * `model`: The path to the `.onnx` voice model.
+    pub fn new(model: PathBuf) -> Self {
+        Piper {
+            model,
+            speaker: None,
+        }
+    }
+
+    /// The path of the config of the voice model.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::path::{Path, PathBuf};
+    /// # use varys_audio::tts::piper::Piper;
+    /// let piper = Piper::new(PathBuf::from("data/voices/en_US-libritts_r-medium.onnx"));
+    ///
+    /// assert_eq!(
+    ///     piper.config_path(),
+    ///     Path::new("data/voices/en_US-libritts_r-medium.onnx.json")
+    /// );
+    /// ```
+    pub fn config_path(&self) -> PathBuf {
+        let mut path = self.model.clone().into_os_string();
+        path.push(".json");
+
+        path.into()
+    }
+
+    /// Read the config of the voice model.
+    ///
+    /// Returns [`Error::VoiceNotAvailable`] with `voice` if the model has no config, since none of
+    /// its voices can be chosen then.
+    fn read_config(&self, voice: &str) -> Result<ModelConfig, Error> {
+        let config = fs::read_to_string(self.config_path()).map_err(|error| {
+            if error.kind() == io::ErrorKind::NotFound {
+                debug!("No piper model config at {}", self.config_path().display());
+                Error::VoiceNotAvailable(voice.to_string())
+            } else {
+                error.into()
+            }
+        })?;
+
+        serde_json::from_str(&config).map_err(|error| Error::Tts(error.to_string()))
+    }
+
+    fn synthesize(&self, text: &str, path: &Path) -> Result<(), Error> {
+        debug!("Writing audio to {}", path.display());
+
+        let mut command = Command::new("piper");
+        command
+            .stdin(Stdio::piped())
+            .arg("--model")
+            .arg(&self.model)
+            .arg("--quiet")
+            .arg("--output_file")
+            .arg(path);
+        if let Some(speaker) = self.speaker {
+            command.arg("--speaker").arg(speaker.to_string());
+        }
+
+        let mut piper = command.spawn().map_err(|err| Error::Tts(err.to_string()))?;
+        piper
+            .stdin
+            .as_mut()
+            .ok_or(Error::Tts("No stdin found".to_string()))?
+            .write_all(text.as_bytes())
+            .map_err(|err| Error::Tts(err.to_string()))?;
+        let status = piper.wait().map_err(|err| Error::Tts(err.to_string()))?;
+        if !status.success() {
+            return Err(Error::Tts(format!("piper exited with {status}")));
+        }
+
+        Ok(())
+    }
+}
+
+impl SpeechSynthesizer for Piper {
+    fn set_voice(&mut self, id: &str) -> Result<(), Error> {
+        let config = self.read_config(id)?;
+        let speaker = config
+            .speaker_id_map
+            .get(id)
+            .or_else(|| {
+                id.strip_prefix('p')
+                    .and_then(|name| config.speaker_id_map.get(name))
+            })
+            .ok_or(Error::VoiceNotAvailable(id.to_string()))?;
+
+        self.speaker = Some(*speaker);
+
+        Ok(())
+    }
+
+    fn say(&self, text: &str) -> Result<Duration, Error> {
+        let path = std::env::temp_dir().join(format!("varys-piper-{}.wav", std::process::id()));
+        self.synthesize(text, &path)?;
+        let audio = file::read_wav(&path);
+        let _ = fs::remove_file(&path);
+
+        playback::play(&audio?)
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, StreamConfig};
+use log::{debug, error};
+
+use crate::audio::resample::ResampleQuality;
+use crate::audio::AudioData;
+use crate::error::Error;
+
+/// How long to keep the stream open after the last sample was handed to the device, so it is
+/// played before the stream stops. This is not included in the returned duration.
+const DRAIN_TIME: Duration = Duration::from_millis(200);
+
+/// Play audio on the default output device.
+///
+/// The audio is resampled to the sample rate of the device and its channels are mapped to the
+/// device's channels. This blocks until the audio has been played.
+///
+/// Returns how long it took until the last sample was handed to the device or an error if there
+/// is no output device that plays `f32` samples or the stream could not be played.
+///
+/// # Arguments
+///
+/// * `audio`: The audio to play.
+pub(crate) fn play(audio: &AudioData) -> Result<Duration, Error> {
+    if audio.data.is_empty() || audio.channels == 0 {
+        return Ok(Duration::ZERO);
+    }
+
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or(Error::AudioDeviceNotFound)?;
+    let config = device
+        .supported_output_configs()?
+        .find(|config| config.sample_format() == SampleFormat::F32)
+        .ok_or(Error::ConfigurationNotSupported)?;
+    let sample_rate = audio
+        .sample_rate
+        .clamp(config.min_sample_rate().0, config.max_sample_rate().0);
+    let config: StreamConfig = config
+        .with_sample_rate(cpal::SampleRate(sample_rate))
+        .into();
+    debug!("Playing audio with output config {config:?}");
+
+    let input_channels = audio.channels as usize;
+    let output_channels = config.channels as usize;
+    let mut resampled = AudioData {
+        data: audio.data.clone(),
+        channels: audio.channels,
+        sample_rate: audio.sample_rate,
+    };
+    resampled.resample(sample_rate, ResampleQuality::default());
+    // each output channel plays the input channel with the same index or the last one
+    let mut samples = Vec::with_capacity(resampled.data.len() / input_channels * output_channels);
+    for frame in resampled.data.chunks_exact(input_channels) {
+        for channel in 0..output_channels {
+            samples.push(frame[channel.min(input_channels - 1)]);
+        }
+    }
+    let mut samples = samples.into_iter();
+
+    let (finished_sender, finished) = channel();
+    // only the first callback after the last sample reports when playing finished
+    let mut finished_sender = Some(finished_sender);
+    let stream = device.build_output_stream(
+        &config,
+        move |data: &mut [f32], _| {
+            for sample in data.iter_mut() {
+                *sample = samples.next().unwrap_or(0.0);
+            }
+            if samples.len() == 0 {
+                if let Some(sender) = finished_sender.take() {
+                    let _ = sender.send(Instant::now());
+                }
+            }
+        },
+        move |err| error!("Audio stream error: {}", err),
+        None,
+    )?;
+    let start = Instant::now();
+    stream.play()?;
+
+    // don't wait forever if the stream fails before all audio was played
+    let timeout = Duration::from_secs_f32(resampled.duration_s()) + DRAIN_TIME * 5;
+    let Ok(finished_at) = finished.recv_timeout(timeout) else {
+        return Err(Error::Tts("Playback did not finish in time".to_string()));
+    };
+    std::thread::sleep(DRAIN_TIME);
+    drop(stream);
+
+    Ok(finished_at.saturating_duration_since(start))
+}
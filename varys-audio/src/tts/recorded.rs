@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::debug;
+
+use crate::error::Error;
+use crate::file;
+use crate::tts::{playback, SpeechSynthesizer};
+
+/// The directory with recorded phrases that is used if no other directory is chosen.
+pub const DEFAULT_DIRECTORY: &str = "data/recordings";
+/// The extensions of the audio files that are looked for, in order.
+const EXTENSIONS: [&str; 2] = ["wav", "opus"];
+
+/// Plays recordings of phrases instead of synthesizing them, e.g. to speak with a real voice.
+///
+/// The recordings are stored in a subdirectory for each voice, named after the phrase with
+/// [`recording_name`] and saved as `.wav` or `.opus`:
+///
+/// ```text
+/// data/recordings/
+/// └── alice/
+///     ├── hey-siri-stop.opus
+///     └── hey-siri-whats-the-weather-today.wav
+/// ```
+///
+/// Saying a phrase without a recording returns [`Error::RecordingNotFound`].
+pub struct Recorded {
+    directory: PathBuf,
+    voice: Option<String>,
+}
+
+impl Recorded {
+    /// Create a synthesizer that plays the recordings in a directory.
+    ///
+    /// Until a voice is chosen, recordings are looked up directly in the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory`: The directory with a subdirectory of recordings for each voice.
+    pub fn new(directory: PathBuf) -> Self {
+        Recorded {
+            directory,
+            voice: None,
+        }
+    }
+
+    fn voice_directory(&self) -> PathBuf {
+        match &self.voice {
+            Some(voice) => self.directory.join(voice),
+            None => self.directory.clone(),
+        }
+    }
+}
+
+impl SpeechSynthesizer for Recorded {
+    fn set_voice(&mut self, id: &str) -> Result<(), Error> {
+        if id.is_empty() || !self.directory.join(id).is_dir() {
+            return Err(Error::VoiceNotAvailable(id.to_string()));
+        }
+
+        self.voice = Some(id.to_string());
+
+        Ok(())
+    }
+
+    fn say(&self, text: &str) -> Result<Duration, Error> {
+        let name = recording_name(text);
+        let path = EXTENSIONS
+            .iter()
+            .map(|extension| self.voice_directory().join(format!("{name}.{extension}")))
+            .find(|path| path.exists())
+            .ok_or(Error::RecordingNotFound(text.to_string()))?;
+        debug!("Playing recording {}", path.display());
+
+        playback::play(&file::read_audio(&path)?)
+    }
+}
+
+/// The file name (without an extension) of the recording of a phrase.
+///
+/// The phrase is lowercased, apostrophes are removed and all other characters that are not
+/// letters or digits separate words, which are joined with dashes.
+///
+/// # Arguments
+///
+/// * `text`: The phrase.
+///
+/// # Examples
+///
+/// ```
+/// # use varys_audio::tts::recorded::recording_name;
+/// assert_eq!(
+///     recording_name("Hey Siri. What's the weather today?"),
+///     "hey-siri-whats-the-weather-today"
+/// );
+/// ```
+pub fn recording_name(text: &str) -> String {
+    text.to_lowercase()
+        .replace(['\'', '’'], "")
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
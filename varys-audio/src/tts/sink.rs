@@ -0,0 +1,67 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::tts::SpeechSynthesizer;
+
+/// Does not say anything, for running without a speaker, e.g. in tests or while replaying
+/// captures.
+///
+/// Every voice is available. If a file is given, each phrase is appended to it as a line with the
+/// voice and the phrase separated by a tab.
+pub struct Sink {
+    file: Option<PathBuf>,
+    voice: String,
+}
+
+impl Sink {
+    /// Create a synthesizer that does not say anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `file`: The file to append the phrases that would have been said to, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::fs;
+    /// # use varys_audio::tts::sink::Sink;
+    /// # use varys_audio::tts::SpeechSynthesizer;
+    /// let path = std::env::temp_dir().join("varys-sink-example.txt");
+    /// # let _ = fs::remove_file(&path);
+    /// let mut sink = Sink::new(Some(path.clone()));
+    ///
+    /// sink.set_voice("Zoe").unwrap();
+    /// sink.say("Hey Siri. Any missed calls?").unwrap();
+    ///
+    /// assert_eq!(
+    ///     fs::read_to_string(&path).unwrap(),
+    ///     "Zoe\tHey Siri. Any missed calls?\n"
+    /// );
+    /// ```
+    pub fn new(file: Option<PathBuf>) -> Self {
+        Sink {
+            file,
+            voice: String::new(),
+        }
+    }
+}
+
+impl SpeechSynthesizer for Sink {
+    fn set_voice(&mut self, id: &str) -> Result<(), Error> {
+        self.voice = id.to_string();
+
+        Ok(())
+    }
+
+    fn say(&self, text: &str) -> Result<Duration, Error> {
+        if let Some(path) = &self.file {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}\t{}", self.voice, text)?;
+        }
+
+        Ok(Duration::ZERO)
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::mpsc::{channel, TryRecvError};
+use std::time::{Duration, Instant};
+
+use cocoa_foundation::{
+    base::id,
+    foundation::{NSDefaultRunLoopMode, NSRunLoop},
+};
+use log::debug;
+use objc::{class, msg_send, sel, sel_impl};
+use tts::{Features, Tts, Voice};
+
+use crate::error::Error;
+use crate::tts::SpeechSynthesizer;
+
+/// Synthesizes speech with the voices installed on macOS.
+pub struct System {
+    tts: Tts,
+    available_voices: Vec<Voice>,
+}
+
+impl System {
+    /// Load the speech synthesizer of the system and all available voices.
+    ///
+    /// Returns an error if the system does not support voices and utterance callbacks.
+    pub fn new() -> Result<Self, Error> {
+        let tts = Tts::default()?;
+
+        let Features {
+            utterance_callbacks,
+            voice,
+            ..
+        } = tts.supported_features();
+        for (available, name) in [
+            (utterance_callbacks, "utterance callbacks"),
+            (voice, "voices"),
+        ] {
+            if !available {
+                return Err(Error::UnsupportedFeature(name.to_string()));
+            }
+        }
+
+        let available_voices = tts.voices()?;
+        let system = System {
+            tts,
+            available_voices,
+        };
+
+        debug!(
+            "Available voices: {}",
+            system
+                .available_voices
+                .iter()
+                .map(|voice| voice.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Ok(system)
+    }
+}
+
+impl SpeechSynthesizer for System {
+    fn set_voice(&mut self, id: &str) -> Result<(), Error> {
+        let voice = self
+            .available_voices
+            .iter()
+            .find(|v| v.id() == id || v.name() == id)
+            .ok_or(Error::VoiceNotAvailable(id.to_string()))?;
+
+        self.tts.set_voice(voice)?;
+
+        Ok(())
+    }
+
+    fn say(&self, text: &str) -> Result<Duration, Error> {
+        let start = Instant::now();
+        let (sender, receiver) = channel();
+        self.tts.on_utterance_end(Some(Box::new(move |_| {
+            let _ = sender.send(());
+        })))?;
+
+        self.tts.clone().speak(text, true)?;
+
+        unsafe {
+            let run_loop: id = NSRunLoop::currentRunLoop();
+            let date: id = msg_send![class!(NSDate), distantFuture];
+            while receiver.try_recv() == Err(TryRecvError::Empty) {
+                let _: () = msg_send![run_loop, runMode:NSDefaultRunLoopMode beforeDate:date];
+            }
+        }
+
+        Ok(start.elapsed())
+    }
+}
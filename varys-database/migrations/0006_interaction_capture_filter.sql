@@ -0,0 +1 @@
+alter table interaction add column capture_filter text;
\ No newline at end of file
@@ -0,0 +1,2 @@
+alter table interaction add column capture_pre_roll integer;
+alter table interaction add column capture_post_roll integer;
@@ -0,0 +1 @@
+alter table interaction add column capture_anonymised boolean not null default false;
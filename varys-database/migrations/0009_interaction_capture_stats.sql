@@ -0,0 +1,8 @@
+alter table interaction add column capture_packets integer;
+alter table interaction add column capture_bytes bigint;
+alter table interaction add column capture_packets_in integer;
+alter table interaction add column capture_packets_out integer;
+alter table interaction add column capture_buffer_dropped integer;
+alter table interaction add column capture_interface_dropped integer;
+alter table interaction add column capture_first_packet timestamptz;
+alter table interaction add column capture_last_packet timestamptz;
@@ -0,0 +1 @@
+alter table interaction add column assistant_ip text;
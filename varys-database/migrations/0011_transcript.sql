@@ -0,0 +1,9 @@
+create table transcript (
+    id serial primary key,
+    interaction_id int not null,
+    model text not null,
+    text text not null,
+    created timestamptz not null,
+
+    constraint fk_interaction foreign key (interaction_id) references interaction(id)
+);
@@ -0,0 +1,14 @@
+create table segment (
+    id serial primary key,
+    interaction_id int not null,
+    transcript_id int,
+    position int not null,
+    start_offset int not null,
+    end_offset int not null,
+    text text not null,
+    tokens text[] not null default '{}',
+    token_probabilities real[] not null default '{}',
+
+    constraint fk_interaction foreign key (interaction_id) references interaction(id),
+    constraint fk_transcript foreign key (transcript_id) references transcript(id)
+);
@@ -0,0 +1,5 @@
+alter table interactor_config add column recogniser text not null default '{}';
+alter table interactor_config drop constraint interactor_config_interface_voice_sensitivity_model_key;
+alter table interactor_config add unique (interface, voice, sensitivity, model, recogniser);
+
+alter table transcript add column recogniser text not null default '{}';
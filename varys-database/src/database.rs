@@ -9,7 +9,9 @@ use crate::error::Error;
 
 pub mod interaction;
 pub mod interactor_config;
+pub mod segment;
 pub mod session;
+pub mod transcript;
 
 /// Connect to the database as specified in the environment variable `DATABASE_URL`.
 ///
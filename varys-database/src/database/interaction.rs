@@ -51,8 +51,51 @@ pub struct Interaction {
     ///
     /// Stored inside the session `data_dir`.
     pub capture_file: Option<String>,
+    /// The BPF filter that was applied while capturing traffic.
+    ///
+    /// If this is `None`, all traffic on the interface was captured.
+    pub capture_filter: Option<String>,
+    /// How many milliseconds of traffic before the start of the interaction were kept in the
+    /// capture.
+    ///
+    /// If this is `None`, the capture was started with the interaction.
+    pub capture_pre_roll: Option<i32>,
+    /// How many milliseconds of traffic after the end of the interaction were kept in the capture.
+    ///
+    /// If this is `None`, the capture was stopped with the interaction.
+    pub capture_post_roll: Option<i32>,
+    /// Whether the addresses in the capture were pseudonymised and its payloads truncated.
+    ///
+    /// If this is `true`, `assistant_mac` is the pseudonym of the assistant in the capture.
+    pub capture_anonymised: bool,
+    /// The number of packets stored in the capture.
+    ///
+    /// If this is `None`, no statistics were recorded for the capture.
+    pub capture_packets: Option<i32>,
+    /// The total length of the captured packets in bytes.
+    pub capture_bytes: Option<i64>,
+    /// The number of captured packets received by the assistant.
+    pub capture_packets_in: Option<i32>,
+    /// The number of captured packets sent by the assistant.
+    pub capture_packets_out: Option<i32>,
+    /// The number of packets dropped because they were not processed quickly enough.
+    pub capture_buffer_dropped: Option<i32>,
+    /// The number of packets dropped by the network interface.
+    pub capture_interface_dropped: Option<i32>,
+    /// When the first captured packet was received.
+    ///
+    /// If this is `None` while `capture_packets` is set, the capture is empty.
+    pub capture_first_packet: Option<DateTime<Utc>>,
+    /// When the last captured packet was received.
+    pub capture_last_packet: Option<DateTime<Utc>>,
     /// The MAC address of the assistant.
     pub assistant_mac: String,
+    /// The IP address of the assistant.
+    ///
+    /// This is used to tell the direction of packets in captures whose link type does not carry
+    /// MAC addresses, e.g. from a tun device. If `capture_anonymised` is `true`, this is the
+    /// pseudonym of the assistant in the capture.
+    pub assistant_ip: Option<String>,
     /// When this interaction was started.
     pub started: DateTime<Utc>,
     /// When this interaction was ended.
@@ -102,7 +145,20 @@ impl Interaction {
             response_duration: None,
             response_file: None,
             capture_file: None,
+            capture_filter: None,
+            capture_pre_roll: None,
+            capture_post_roll: None,
+            capture_anonymised: false,
+            capture_packets: None,
+            capture_bytes: None,
+            capture_packets_in: None,
+            capture_packets_out: None,
+            capture_buffer_dropped: None,
+            capture_interface_dropped: None,
+            capture_first_packet: None,
+            capture_last_packet: None,
             assistant_mac,
+            assistant_ip: None,
             started,
             ended: None,
         })
@@ -160,7 +216,7 @@ impl Interaction {
     /// * `connection`: The connection to use.
     pub async fn update(&mut self, connection: &DatabaseConnection) -> Result<&mut Self, Error> {
         let query = sqlx::query!(
-            "UPDATE interaction SET (session_id, query, query_category, query_duration, query_file, response, response_duration, response_file, capture_file, assistant_mac, started, ended) = ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) WHERE id = $13",
+            "UPDATE interaction SET (session_id, query, query_category, query_duration, query_file, response, response_duration, response_file, capture_file, capture_filter, capture_pre_roll, capture_post_roll, capture_anonymised, capture_packets, capture_bytes, capture_packets_in, capture_packets_out, capture_buffer_dropped, capture_interface_dropped, capture_first_packet, capture_last_packet, assistant_mac, assistant_ip, started, ended) = ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25) WHERE id = $26",
             self.session_id,
             self.query,
             self.query_category,
@@ -170,7 +226,20 @@ impl Interaction {
             self.response_duration,
             self.response_file,
             self.capture_file,
+            self.capture_filter,
+            self.capture_pre_roll,
+            self.capture_post_roll,
+            self.capture_anonymised,
+            self.capture_packets,
+            self.capture_bytes,
+            self.capture_packets_in,
+            self.capture_packets_out,
+            self.capture_buffer_dropped,
+            self.capture_interface_dropped,
+            self.capture_first_packet,
+            self.capture_last_packet,
             self.assistant_mac,
+            self.assistant_ip,
             self.started,
             self.ended,
             self.id
@@ -199,6 +268,20 @@ impl Interaction {
     pub fn is_complete(&self) -> bool {
         self.ended.is_some()
     }
+
+    /// Whether packets were dropped while capturing the traffic of this interaction.
+    pub fn has_capture_drops(&self) -> bool {
+        self.capture_buffer_dropped
+            .is_some_and(|dropped| dropped > 0)
+            || self
+                .capture_interface_dropped
+                .is_some_and(|dropped| dropped > 0)
+    }
+
+    /// Whether the capture of this interaction is known to contain no packets.
+    pub fn has_empty_capture(&self) -> bool {
+        self.capture_packets == Some(0)
+    }
 }
 
 impl Display for Interaction {
@@ -13,13 +13,18 @@ pub struct InteractorConfig {
     pub voice: String,
     pub sensitivity: String,
     pub model: String,
+    /// How the recogniser decodes speech with `model`, as JSON.
+    ///
+    /// Configs stored before this was recorded have `{}`, i.e. the default config.
+    pub recogniser: String,
 }
 
 impl InteractorConfig {
     /// Get an interactor config from the database or create it if it doesn't exist yet.
     ///
-    /// Every combination of interface, voice, sensitivity and model is uniquely represented in the
-    /// database, so we cannot just create a new config if the same one already exists.
+    /// Every combination of interface, voice, sensitivity, model and recogniser config is uniquely
+    /// represented in the database, so we cannot just create a new config if the same one already
+    /// exists.
     ///
     /// # Arguments
     ///
@@ -27,11 +32,12 @@ impl InteractorConfig {
     pub async fn get_or_create(&self, connection: &DatabaseConnection) -> Result<i32, Error> {
         // first, try to find an existing config with the same values ...
         let query = sqlx::query!(
-            "SELECT id FROM interactor_config WHERE interface = $1 AND voice = $2 AND sensitivity = $3 AND model = $4",
+            "SELECT id FROM interactor_config WHERE interface = $1 AND voice = $2 AND sensitivity = $3 AND model = $4 AND recogniser = $5",
             self.interface,
             self.voice,
             self.sensitivity,
             self.model,
+            self.recogniser,
         );
 
         database::log_query(&query);
@@ -41,11 +47,12 @@ impl InteractorConfig {
 
         // ... otherwise, create a new one
         let query = sqlx::query!(
-                "INSERT INTO interactor_config (interface, voice, sensitivity, model) VALUES ($1, $2, $3, $4) RETURNING id",
+                "INSERT INTO interactor_config (interface, voice, sensitivity, model, recogniser) VALUES ($1, $2, $3, $4, $5) RETURNING id",
                 self.interface,
                 self.voice,
                 self.sensitivity,
                 self.model,
+                self.recogniser,
             );
 
         database::log_query(&query);
@@ -68,6 +75,7 @@ impl InteractorConfig {
                 voice: result.voice,
                 sensitivity: result.sensitivity,
                 model: result.model,
+                recogniser: result.recogniser,
             }))
         } else {
             Ok(None)
@@ -0,0 +1,144 @@
+use std::fmt::{Display, Formatter};
+
+use sqlx::FromRow;
+
+use crate::connection::DatabaseConnection;
+use crate::database;
+use crate::database::interaction::Interaction;
+use crate::database::transcript::Transcript;
+use crate::error::Error;
+
+/// The representation of a segment of a transcribed response in the database.
+///
+/// Each segment belongs to an [`Interaction`] and tells when within the recorded response a part
+/// of it was said. Segments of the response recognised during the interaction have no
+/// [`Transcript`], segments of later transcriptions belong to one.
+#[derive(FromRow, Debug)]
+pub struct Segment {
+    /// Segment ids are sequenced.
+    pub id: i32,
+    /// The id of the interaction whose response was transcribed.
+    pub interaction_id: i32,
+    /// The id of the transcript this segment is part of.
+    ///
+    /// If this is `None`, the segment is part of the interaction's `response`.
+    pub transcript_id: Option<i32>,
+    /// The index of the segment within its transcription, starting at 0.
+    pub position: i32,
+    /// When the segment starts in milliseconds after the start of the response file.
+    pub start_offset: i32,
+    /// When the segment ends in milliseconds after the start of the response file.
+    pub end_offset: i32,
+    /// The transcribed text of the segment.
+    pub text: String,
+    /// The tokens of the text.
+    ///
+    /// This is empty if no tokens were stored for the segment.
+    pub tokens: Vec<String>,
+    /// The probability of each token in `tokens`.
+    pub token_probabilities: Vec<f32>,
+}
+
+impl Segment {
+    /// Create a new segment in the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: The connection to use.
+    /// * `interaction`: The interaction whose response was transcribed.
+    /// * `transcript`: The transcript the segment is part of or `None` if it is part of the
+    ///   interaction's `response`.
+    /// * `position`: The index of the segment within its transcription.
+    /// * `offsets`: When the segment starts and ends in milliseconds after the start of the
+    ///   response file.
+    /// * `text`: The transcribed text of the segment.
+    /// * `tokens`: The tokens of the text with their probabilities, may be empty.
+    pub async fn create(
+        connection: &DatabaseConnection,
+        interaction: &Interaction,
+        transcript: Option<&Transcript>,
+        position: i32,
+        (start_offset, end_offset): (i32, i32),
+        text: &str,
+        tokens: &[(String, f32)],
+    ) -> Result<Self, Error> {
+        let transcript_id = transcript.map(|transcript| transcript.id);
+        let (tokens, token_probabilities): (Vec<_>, Vec<_>) = tokens.iter().cloned().unzip();
+        let query = sqlx::query!(
+            "INSERT INTO segment (interaction_id, transcript_id, position, start_offset, end_offset, text, tokens, token_probabilities) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+            interaction.id,
+            transcript_id,
+            position,
+            start_offset,
+            end_offset,
+            text,
+            &tokens,
+            &token_probabilities
+        );
+
+        database::log_query(&query);
+        let id = query.fetch_one(&connection.pool).await?.id;
+
+        Ok(Segment {
+            id,
+            interaction_id: interaction.id,
+            transcript_id,
+            position,
+            start_offset,
+            end_offset,
+            text: text.to_string(),
+            tokens,
+            token_probabilities,
+        })
+    }
+
+    /// Get the segments of the response recognised during an interaction, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: The connection to use.
+    /// * `interaction_id`: The id of the interaction.
+    pub async fn get_by_interaction(
+        connection: &DatabaseConnection,
+        interaction_id: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let query = sqlx::query_as!(
+            Self,
+            "SELECT * FROM segment WHERE segment.interaction_id = $1 AND segment.transcript_id IS NULL ORDER BY position",
+            interaction_id
+        );
+
+        database::log_query(&query);
+        Ok(query.fetch_all(&connection.pool).await?)
+    }
+
+    /// Get the segments of a transcript, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: The connection to use.
+    /// * `transcript_id`: The id of the transcript.
+    pub async fn get_by_transcript(
+        connection: &DatabaseConnection,
+        transcript_id: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let query = sqlx::query_as!(
+            Self,
+            "SELECT * FROM segment WHERE segment.transcript_id = $1 ORDER BY position",
+            transcript_id
+        );
+
+        database::log_query(&query);
+        Ok(query.fetch_all(&connection.pool).await?)
+    }
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}ms - {}ms]: {}",
+            self.start_offset, self.end_offset, self.text
+        )
+    }
+}
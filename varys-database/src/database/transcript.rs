@@ -0,0 +1,100 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use crate::connection::DatabaseConnection;
+use crate::database;
+use crate::database::interaction::Interaction;
+use crate::error::Error;
+
+/// The representation of a transcript of a response in the database.
+///
+/// Responses can be transcribed again with other speech recognition models after an interaction
+/// has ended. Each transcript belongs to an [`Interaction`], whose own `response` stays unchanged.
+#[derive(FromRow, Debug)]
+pub struct Transcript {
+    /// Transcript ids are sequenced.
+    pub id: i32,
+    /// The id of the interaction whose response was transcribed.
+    pub interaction_id: i32,
+    /// The name of the model that was used to transcribe the response.
+    pub model: String,
+    /// How the recogniser decoded speech with `model`, as JSON.
+    pub recogniser: String,
+    /// The transcribed response.
+    pub text: String,
+    /// When this transcript was created.
+    pub created: DateTime<Utc>,
+}
+
+impl Transcript {
+    /// Create a new transcript in the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: The connection to use.
+    /// * `interaction`: The interaction whose response was transcribed.
+    /// * `model`: The name of the model that was used.
+    /// * `recogniser`: The config of the recogniser that was used, as JSON.
+    /// * `text`: The transcribed response.
+    pub async fn create(
+        connection: &DatabaseConnection,
+        interaction: &Interaction,
+        model: &str,
+        recogniser: &str,
+        text: &str,
+    ) -> Result<Self, Error> {
+        let created = Utc::now();
+        let query = sqlx::query!(
+            "INSERT INTO transcript (interaction_id, model, text, created, recogniser) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            interaction.id,
+            model,
+            text,
+            created,
+            recogniser
+        );
+
+        database::log_query(&query);
+        let id = query.fetch_one(&connection.pool).await?.id;
+
+        Ok(Transcript {
+            id,
+            interaction_id: interaction.id,
+            model: model.to_string(),
+            recogniser: recogniser.to_string(),
+            text: text.to_string(),
+            created,
+        })
+    }
+
+    /// Get all transcripts of an interaction from the database, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: The connection to use.
+    /// * `interaction_id`: The id of the interaction.
+    pub async fn get_by_interaction(
+        connection: &DatabaseConnection,
+        interaction_id: i32,
+    ) -> Result<Vec<Self>, Error> {
+        let query = sqlx::query_as!(
+            Self,
+            "SELECT * FROM transcript WHERE transcript.interaction_id = $1 ORDER BY created",
+            interaction_id
+        );
+
+        database::log_query(&query);
+        Ok(query.fetch_all(&connection.pool).await?)
+    }
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Transcript {} of interaction {} ({})",
+            self.id, self.interaction_id, self.model
+        )
+    }
+}
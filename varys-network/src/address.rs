@@ -1,13 +1,33 @@
 use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
 use std::str::FromStr;
 
 use pnet::util::MacAddr;
 
 use crate::error::Error;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub struct MacAddress(pub u8, pub u8, pub u8, pub u8, pub u8, pub u8);
 
+impl MacAddress {
+    /// A BPF capture filter that only keeps frames sent from or to this address.
+    ///
+    /// See [`crate::sniff::Sniffer::filter`] for how to apply it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str::FromStr;
+    /// # use varys_network::address::MacAddress;
+    /// let address = MacAddress::from_str("00:1a:2b:3c:4d:5e").unwrap();
+    ///
+    /// assert_eq!(address.capture_filter(), "ether host 00:1a:2b:3c:4d:5e");
+    /// ```
+    pub fn capture_filter(&self) -> String {
+        format!("ether host {self}")
+    }
+}
+
 impl From<MacAddress> for MacAddr {
     fn from(value: MacAddress) -> Self {
         MacAddr::new(value.0, value.1, value.2, value.3, value.4, value.5)
@@ -37,3 +57,64 @@ impl Display for MacAddress {
         )
     }
 }
+
+/// The addresses of a device, used to tell which packets it sent and received.
+///
+/// The IP address is only needed for captures whose link type does not carry MAC addresses.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DeviceAddress {
+    pub mac: MacAddress,
+    pub ip: Option<IpAddr>,
+}
+
+impl DeviceAddress {
+    /// Create the addresses of a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `mac`: The MAC address of the device.
+    /// * `ip`: The IP address of the device, if known.
+    pub fn new(mac: MacAddress, ip: Option<IpAddr>) -> Self {
+        DeviceAddress { mac, ip }
+    }
+
+    /// Parse the addresses of a device, e.g. as stored with an interaction.
+    ///
+    /// Returns an error if either address is invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `mac`: The MAC address of the device.
+    /// * `ip`: The IP address of the device, if known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_network::address::DeviceAddress;
+    /// let address = DeviceAddress::parse("00:1a:2b:3c:4d:5e", Some("192.168.1.20")).unwrap();
+    ///
+    /// assert_eq!(address.to_string(), "00:1a:2b:3c:4d:5e (192.168.1.20)");
+    /// assert!(DeviceAddress::parse("00:1a:2b:3c:4d:5e", Some("192.168.1")).is_err());
+    /// ```
+    pub fn parse(mac: &str, ip: Option<&str>) -> Result<Self, Error> {
+        Ok(DeviceAddress {
+            mac: MacAddress::from_str(mac)?,
+            ip: ip.map(IpAddr::from_str).transpose()?,
+        })
+    }
+}
+
+impl From<MacAddress> for DeviceAddress {
+    fn from(mac: MacAddress) -> Self {
+        DeviceAddress { mac, ip: None }
+    }
+}
+
+impl Display for DeviceAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.ip {
+            Some(ip) => write!(f, "{} ({ip})", self.mac),
+            None => write!(f, "{}", self.mac),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::str::FromStr;
+
+use log::{debug, info};
+use pcap::Linktype;
+use pnet::packet::arp::MutableArpPacket;
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::{self, MutableIpv4Packet};
+use pnet::packet::ipv6::MutableIpv6Packet;
+
+use crate::address::MacAddress;
+use crate::error::Error;
+use crate::pcapng::Annotations;
+use crate::protocol;
+use crate::rewrite::{self, Rewrite, Rewriter};
+
+const TCP_CHECKSUM_OFFSET: usize = 16;
+const UDP_CHECKSUM_OFFSET: usize = 6;
+
+/// How much of each packet is kept when anonymising a capture.
+///
+/// Only addresses in the headers are pseudonymised, payloads that are kept may still contain real
+/// addresses, e.g. in DNS answers.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Truncation {
+    /// Keep the whole packet.
+    Keep,
+    /// Only keep the Ethernet, IP and transport headers and drop the application payload.
+    #[default]
+    Headers,
+    /// Keep at most this many bytes of each packet.
+    Snaplen(usize),
+}
+
+/// Replaces MAC and IP addresses with pseudonyms and truncates payloads.
+///
+/// Pseudonyms are assigned sequentially in the order addresses are first seen, so the same
+/// anonymiser has to be used for all captures of a session to keep them consistent. Broadcast,
+/// multicast, loopback and unspecified addresses are kept as they are.
+///
+/// The length of each packet in the pcap header is not changed, so traffic traces stay the same.
+/// TCP and UDP checksums are cleared since they can no longer be verified after the addresses were
+/// replaced.
+///
+/// # Examples
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use varys_network::address::MacAddress;
+/// # use varys_network::anonymise::{Anonymiser, Truncation};
+/// let assistant = MacAddress::from_str("00:1a:2b:3c:4d:5e").unwrap();
+/// let mut anonymiser = Anonymiser::new(Truncation::Headers);
+///
+/// let pseudonym = anonymiser.mac(assistant);
+/// assert_eq!(pseudonym.to_string(), "02:00:00:00:00:01");
+/// assert_eq!(anonymiser.mac(assistant), pseudonym);
+/// ```
+#[derive(Debug, Default)]
+pub struct Anonymiser {
+    pub truncation: Truncation,
+    macs: HashMap<MacAddress, MacAddress>,
+    ipv4_addresses: HashMap<Ipv4Addr, Ipv4Addr>,
+    ipv6_addresses: HashMap<Ipv6Addr, Ipv6Addr>,
+}
+
+impl Anonymiser {
+    pub fn new(truncation: Truncation) -> Self {
+        Anonymiser {
+            truncation,
+            ..Default::default()
+        }
+    }
+
+    /// Get the pseudonym of a MAC address, assigning a new one if it was not seen before.
+    ///
+    /// Pseudonyms are locally administered unicast addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to get the pseudonym of.
+    pub fn mac(&mut self, address: MacAddress) -> MacAddress {
+        if address.0 & 0x01 != 0 {
+            // broadcast and multicast
+            return address;
+        }
+
+        let next = self.macs.len() as u32 + 1;
+        *self.macs.entry(address).or_insert_with(|| {
+            let [a, b, c, d] = next.to_be_bytes();
+            MacAddress(0x02, 0x00, a, b, c, d)
+        })
+    }
+
+    /// Get the pseudonym of an IP address, assigning a new one if it was not seen before.
+    ///
+    /// IPv4 pseudonyms are taken from `10.0.0.0/8` and IPv6 pseudonyms from `fd00::/8`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to get the pseudonym of.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::net::IpAddr;
+    /// # use varys_network::anonymise::Anonymiser;
+    /// let mut anonymiser = Anonymiser::default();
+    /// let server: IpAddr = "17.253.53.207".parse().unwrap();
+    /// let broadcast: IpAddr = "255.255.255.255".parse().unwrap();
+    ///
+    /// assert_eq!(anonymiser.ip(server), "10.0.0.1".parse::<IpAddr>().unwrap());
+    /// assert_eq!(anonymiser.ip(broadcast), broadcast);
+    /// ```
+    pub fn ip(&mut self, address: IpAddr) -> IpAddr {
+        match address {
+            IpAddr::V4(address) => self.ipv4(address).into(),
+            IpAddr::V6(address) => self.ipv6(address).into(),
+        }
+    }
+
+    fn ipv4(&mut self, address: Ipv4Addr) -> Ipv4Addr {
+        if address.is_broadcast()
+            || address.is_multicast()
+            || address.is_loopback()
+            || address.is_unspecified()
+        {
+            return address;
+        }
+
+        let next = self.ipv4_addresses.len() as u32 + 1;
+        *self
+            .ipv4_addresses
+            .entry(address)
+            .or_insert_with(|| Ipv4Addr::from(0x0a000000 | (next & 0x00ffffff)))
+    }
+
+    fn ipv6(&mut self, address: Ipv6Addr) -> Ipv6Addr {
+        if address.is_multicast() || address.is_loopback() || address.is_unspecified() {
+            return address;
+        }
+
+        let next = self.ipv6_addresses.len() as u128 + 1;
+        *self
+            .ipv6_addresses
+            .entry(address)
+            .or_insert_with(|| Ipv6Addr::from((0xfd00 << 112) | next))
+    }
+
+    /// Replace the MAC and IP addresses in a BPF capture filter with their pseudonyms.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: The capture filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_network::anonymise::Anonymiser;
+    /// let mut anonymiser = Anonymiser::default();
+    ///
+    /// assert_eq!(
+    ///     anonymiser.anonymise_filter("ether host 00:1a:2b:3c:4d:5e"),
+    ///     "ether host 02:00:00:00:00:01"
+    /// );
+    /// ```
+    pub fn anonymise_filter(&mut self, filter: &str) -> String {
+        filter
+            .split(' ')
+            .map(|token| {
+                if let Ok(address) = MacAddress::from_str(token) {
+                    self.mac(address).to_string()
+                } else if let Ok(address) = IpAddr::from_str(token) {
+                    self.ip(address).to_string()
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Anonymise a captured packet.
+    ///
+    /// Returns the new pcap header and data of the packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The captured Ethernet frame.
+    pub fn anonymise_packet(&mut self, packet: &pcap::Packet) -> (pcap::PacketHeader, Vec<u8>) {
+        let mut data = packet.data.to_vec();
+        self.anonymise_frame(&mut data);
+
+        let mut header = *packet.header;
+        header.caplen = data.len() as u32;
+
+        (header, data)
+    }
+
+    /// Anonymise an Ethernet frame in place.
+    ///
+    /// Frames other than IP and ARP packets and frames that cannot be decoded are always truncated
+    /// to their Ethernet header.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame`: The Ethernet frame to anonymise.
+    pub fn anonymise_frame(&mut self, frame: &mut Vec<u8>) {
+        let Some(mut ethernet) = MutableEthernetPacket::new(frame) else {
+            frame.clear();
+            return;
+        };
+        let source = self.mac(ethernet.get_source().into());
+        let destination = self.mac(ethernet.get_destination().into());
+        ethernet.set_source(source.into());
+        ethernet.set_destination(destination.into());
+        let ether_type = ethernet.get_ethertype();
+
+        let header_len = EthernetPacket::minimum_packet_size();
+        let headers_len = match ether_type {
+            EtherTypes::Ipv4 | EtherTypes::Ipv6 => self
+                .anonymise_ip(&mut frame[header_len..])
+                .map(|len| header_len + len),
+            EtherTypes::Arp => self
+                .anonymise_arp(&mut frame[header_len..])
+                .then_some(frame.len()),
+            _ => None,
+        };
+
+        let keep = match (self.truncation, headers_len) {
+            // the addresses in frames that could not be decoded were not replaced
+            (_, None) => header_len,
+            (Truncation::Keep, _) => frame.len(),
+            (Truncation::Headers, Some(headers_len)) => headers_len,
+            (Truncation::Snaplen(snaplen), _) => snaplen,
+        };
+        frame.truncate(keep);
+    }
+
+    /// Anonymise the addresses of an IP packet and clear its transport checksum.
+    ///
+    /// Returns the length of the IP and transport headers or `None` if the packet could not be
+    /// decoded.
+    fn anonymise_ip(&mut self, data: &mut [u8]) -> Option<usize> {
+        let decoded = protocol::decode_ip(data).ok()?;
+        let source = self.ip(decoded.ip.source);
+        let destination = self.ip(decoded.ip.destination);
+
+        match (source, destination) {
+            (IpAddr::V4(source), IpAddr::V4(destination)) => {
+                let mut ipv4 = MutableIpv4Packet::new(data)?;
+                ipv4.set_source(source);
+                ipv4.set_destination(destination);
+                let checksum = ipv4::checksum(&ipv4.to_immutable());
+                ipv4.set_checksum(checksum);
+            }
+            (IpAddr::V6(source), IpAddr::V6(destination)) => {
+                let mut ipv6 = MutableIpv6Packet::new(data)?;
+                ipv6.set_source(source);
+                ipv6.set_destination(destination);
+            }
+            _ => return None,
+        }
+
+        let checksum_offset = match decoded.ip.protocol {
+            IpNextHeaderProtocols::Tcp => Some(TCP_CHECKSUM_OFFSET),
+            IpNextHeaderProtocols::Udp => Some(UDP_CHECKSUM_OFFSET),
+            _ => None,
+        };
+        if let Some(checksum) = checksum_offset.and_then(|offset| {
+            data.get_mut(decoded.transport_offset + offset..)?
+                .get_mut(..2)
+        }) {
+            checksum.fill(0);
+        }
+
+        Some(decoded.payload_offset)
+    }
+
+    /// Anonymise the addresses of an ARP packet.
+    ///
+    /// Returns whether the packet could be decoded.
+    fn anonymise_arp(&mut self, data: &mut [u8]) -> bool {
+        let Some(mut arp) = MutableArpPacket::new(data) else {
+            return false;
+        };
+
+        let sender_hw = self.mac(arp.get_sender_hw_addr().into());
+        let target_hw = self.mac(arp.get_target_hw_addr().into());
+        let sender_proto = self.ipv4(arp.get_sender_proto_addr());
+        let target_proto = self.ipv4(arp.get_target_proto_addr());
+        arp.set_sender_hw_addr(sender_hw.into());
+        arp.set_target_hw_addr(target_hw.into());
+        arp.set_sender_proto_addr(sender_proto);
+        arp.set_target_proto_addr(target_proto);
+
+        true
+    }
+
+    /// Anonymise all packets of a pcap file, writing them to a new file.
+    ///
+    /// `from` and `to` may be the same path, in which case the file is replaced. If `from` is
+    /// compressed with zstd, `to` is compressed as well. The annotations and interfaces of a pcapng
+    /// file are kept, with addresses in their fields pseudonymised like those in the packets.
+    ///
+    /// Returns the number of packets written or an error if the file could not be read or written
+    /// or does not contain Ethernet frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The pcap file to anonymise.
+    /// * `to`: Where to write the anonymised pcap file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use varys_network::anonymise::{Anonymiser, Truncation};
+    /// let mut anonymiser = Anonymiser::new(Truncation::Snaplen(96));
+    ///
+    /// anonymiser
+    ///     .anonymise_file(Path::new("capture.pcap"), Path::new("anonymised.pcap"))
+    ///     .unwrap();
+    /// ```
+    pub fn anonymise_file(&mut self, from: &Path, to: &Path) -> Result<usize, Error> {
+        info!("Anonymising {from:?} to {to:?}...");
+
+        let written = rewrite::rewrite_file(from, to, self)?;
+
+        debug!("Anonymised {written} packets");
+
+        Ok(written)
+    }
+}
+
+impl Rewriter for Anonymiser {
+    /// Pseudonymise the values of annotation fields that are MAC or IP addresses.
+    fn prepare(
+        &mut self,
+        linktype: Linktype,
+        mut annotations: Annotations,
+    ) -> Result<Annotations, Error> {
+        if linktype != Linktype::ETHERNET {
+            return Err(Error::UnsupportedLinkType(linktype.0));
+        }
+
+        for (_, value) in &mut annotations.fields {
+            if let Ok(address) = MacAddress::from_str(value) {
+                *value = self.mac(address).to_string();
+            } else if let Ok(address) = IpAddr::from_str(value) {
+                *value = self.ip(address).to_string();
+            }
+        }
+
+        Ok(annotations)
+    }
+
+    fn rewrite(&mut self, packet: &pcap::Packet) -> Rewrite {
+        let (header, data) = self.anonymise_packet(packet);
+
+        Rewrite::Replace(header, data)
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::address::DeviceAddress;
+use crate::packet::{CapturedPacket, PacketDirection};
+
+/// Packets that were sent or received by a device in quick succession, e.g. the upload of a spoken
+/// query or the download of the response audio.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Burst {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// The number of packets sent by the device.
+    pub sent_packets: usize,
+    /// The number of bytes sent by the device.
+    pub sent_bytes: usize,
+    /// The number of packets received by the device.
+    pub received_packets: usize,
+    /// The number of bytes received by the device.
+    pub received_bytes: usize,
+}
+
+impl Burst {
+    fn new(start_time: DateTime<Utc>) -> Self {
+        Burst {
+            start_time,
+            end_time: start_time,
+            sent_packets: 0,
+            sent_bytes: 0,
+            received_packets: 0,
+            received_bytes: 0,
+        }
+    }
+
+    fn add(&mut self, timestamp: DateTime<Utc>, direction: PacketDirection, len: usize) {
+        self.end_time = self.end_time.max(timestamp);
+        match direction {
+            PacketDirection::Out => {
+                self.sent_packets += 1;
+                self.sent_bytes += len;
+            }
+            PacketDirection::In => {
+                self.received_packets += 1;
+                self.received_bytes += len;
+            }
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+
+    /// The direction most bytes of the burst were transferred in, i.e. whether it is an upload or a
+    /// download.
+    pub fn direction(&self) -> PacketDirection {
+        if self.sent_bytes > self.received_bytes {
+            PacketDirection::Out
+        } else {
+            PacketDirection::In
+        }
+    }
+}
+
+impl Display for Burst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Burst at {} ({:.2} seconds, sent {} packets with {} bytes, received {} packets with {} bytes)",
+            self.start_time.format("%d.%m.%Y %H:%M:%S"),
+            self.duration().num_milliseconds() as f32 / 1000.,
+            self.sent_packets,
+            self.sent_bytes,
+            self.received_packets,
+            self.received_bytes
+        )
+    }
+}
+
+/// Group packets into bursts, starting a new burst whenever no packet was sent or received for
+/// longer than `gap`.
+///
+/// Packets that were neither sent nor received by the device are skipped. The packets do not need
+/// to be ordered by time.
+///
+/// # Arguments
+///
+/// * `packets`: The captured packets.
+/// * `relative_to`: The address of the device to get the direction relative to.
+/// * `gap`: How long the traffic must pause to end a burst.
+///
+/// # Examples
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use chrono::{Duration, TimeZone, Utc};
+/// # use varys_network::address::{DeviceAddress, MacAddress};
+/// # use varys_network::burst;
+/// # use varys_network::packet::PacketHeader;
+/// let assistant = MacAddress::from_str("f0:18:98:12:34:56").unwrap();
+/// let router = MacAddress::from_str("00:00:5e:00:53:01").unwrap();
+/// let start = Utc.timestamp_opt(0, 0).unwrap();
+/// let packet = |milliseconds, len, outgoing| PacketHeader {
+///     timestamp: start + Duration::milliseconds(milliseconds),
+///     len,
+///     source: Some(if outgoing { assistant } else { router }),
+///     destination: Some(if outgoing { router } else { assistant }),
+///     source_ip: None,
+///     destination_ip: None,
+/// };
+/// let packets = [
+///     packet(0, 1000, true),
+///     packet(100, 60, false),
+///     packet(2000, 1500, false),
+///     packet(2100, 1500, false),
+/// ];
+///
+/// let bursts = burst::bursts(
+///     &packets,
+///     &DeviceAddress::from(assistant),
+///     Duration::milliseconds(500),
+/// );
+/// assert_eq!(bursts.len(), 2);
+/// assert_eq!((bursts[0].sent_bytes, bursts[0].received_bytes), (1000, 60));
+/// assert_eq!(bursts[1].received_packets, 2);
+/// assert_eq!(bursts[1].duration(), Duration::milliseconds(100));
+/// ```
+pub fn bursts<P: CapturedPacket>(
+    packets: &[P],
+    relative_to: &DeviceAddress,
+    gap: Duration,
+) -> Vec<Burst> {
+    let mut packets = packets
+        .iter()
+        .filter_map(|packet| {
+            packet
+                .direction(relative_to)
+                .map(|direction| (packet.timestamp(), direction, packet.length()))
+        })
+        .collect::<Vec<_>>();
+    packets.sort_by_key(|(timestamp, _, _)| *timestamp);
+
+    let mut bursts: Vec<Burst> = Vec::new();
+    for (timestamp, direction, len) in packets {
+        let burst = match bursts.last_mut() {
+            Some(burst) if timestamp - burst.end_time <= gap => burst,
+            _ => {
+                bursts.push(Burst::new(timestamp));
+                bursts.last_mut().expect("a burst was just added")
+            }
+        };
+        burst.add(timestamp, direction, len);
+    }
+
+    bursts
+}
@@ -0,0 +1,158 @@
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::{debug, info};
+use pcap::{Capture, Offline};
+
+use crate::error::Error;
+
+/// The extension added to the path of compressed captures.
+pub const EXTENSION: &str = "zst";
+/// The zstd compression level used if none is specified.
+pub const DEFAULT_LEVEL: i32 = 3;
+/// The magic number at the start of every zstd frame.
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Distinguishes the decompressed copies of captures read at the same time.
+static DECOMPRESSED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether a file is compressed with zstd.
+///
+/// This checks the contents of the file instead of its extension, so renamed files are detected
+/// correctly.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file.
+pub fn is_compressed<P: AsRef<Path>>(path: P) -> Result<bool, Error> {
+    let mut magic = [0; 4];
+    let read = File::open(path)?.read(&mut magic)?;
+
+    Ok(read == MAGIC.len() && magic == MAGIC)
+}
+
+/// The path of the compressed version of a file, i.e. the path with `.zst` appended.
+///
+/// # Arguments
+///
+/// * `path`: The path to the uncompressed file.
+///
+/// # Examples
+///
+/// ```
+/// # use std::path::{Path, PathBuf};
+/// # use varys_network::compress;
+/// assert_eq!(
+///     compress::compressed_path(Path::new("capture.pcap")),
+///     PathBuf::from("capture.pcap.zst")
+/// );
+/// ```
+pub fn compressed_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut path = OsString::from(path.as_ref());
+    path.push(".");
+    path.push(EXTENSION);
+
+    PathBuf::from(path)
+}
+
+/// Compress a file with zstd, replacing it with a file at its [`compressed_path`].
+///
+/// The original file is only removed once the compressed file was written completely.
+///
+/// Returns the path to the compressed file or an error if the file could not be read or written.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file to compress.
+/// * `level`: The zstd compression level, see [`DEFAULT_LEVEL`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::compress;
+/// let compressed = compress::compress_file("capture.pcap", compress::DEFAULT_LEVEL).unwrap();
+/// ```
+pub fn compress_file<P: AsRef<Path>>(path: P, level: i32) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    let compressed = compressed_path(path);
+
+    info!("Compressing {path:?}...");
+
+    compress_to(path, &compressed, level)?;
+    fs::remove_file(path)?;
+
+    Ok(compressed)
+}
+
+/// Compress a file with zstd, writing it to `to`.
+///
+/// The compressed data is written to a temporary file first, so `to` is never left incomplete.
+pub(crate) fn compress_to(from: &Path, to: &Path, level: i32) -> Result<(), Error> {
+    let temporary_path = to.with_extension("compressing");
+    let mut source = File::open(from)?;
+    let mut encoder = zstd::Encoder::new(File::create(&temporary_path)?, level)?;
+    let uncompressed = io::copy(&mut source, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&temporary_path, to)?;
+
+    debug!(
+        "Compressed {uncompressed} bytes to {} bytes",
+        fs::metadata(to)?.len()
+    );
+
+    Ok(())
+}
+
+/// A decompressed copy of a capture that is removed when it is dropped.
+pub(crate) struct Decompressed {
+    path: PathBuf,
+}
+
+impl Decompressed {
+    fn new(from: &Path) -> Result<Self, Error> {
+        let path = std::env::temp_dir().join(format!(
+            "varys-{}-{}-{}.pcap",
+            std::process::id(),
+            DECOMPRESSED_COUNT.fetch_add(1, Ordering::Relaxed),
+            from.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        ));
+        let decompressed = Decompressed { path };
+
+        debug!("Decompressing {from:?} to {:?}...", decompressed.path);
+
+        let mut decoder = zstd::Decoder::new(File::open(from)?)?;
+        io::copy(&mut decoder, &mut File::create(&decompressed.path)?)?;
+
+        Ok(decompressed)
+    }
+}
+
+impl Drop for Decompressed {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Open a capture for reading, decompressing it first if it is compressed.
+///
+/// The decompressed copy is kept until the returned guard is dropped, so keep it alive as long as
+/// the capture is read.
+pub(crate) fn open_capture<P: AsRef<Path>>(
+    path: P,
+) -> Result<(Capture<Offline>, Option<Decompressed>), Error> {
+    let path = path.as_ref();
+    if !is_compressed(path)? {
+        return Ok((Capture::from_file(path)?, None));
+    }
+
+    let decompressed = Decompressed::new(path)?;
+
+    Ok((Capture::from_file(&decompressed.path)?, Some(decompressed)))
+}
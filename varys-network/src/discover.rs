@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::info;
+
+use crate::address::MacAddress;
+use crate::error::Error;
+use crate::packet::{CapturedPacket, PacketHeader};
+use crate::sniff::{ContinuousInstance, Sniffer};
+
+mod oui;
+
+/// How long a discovery keeps packets in its capture buffer. Packets are collected as they arrive,
+/// so the buffer does not need to hold the whole discovery.
+const DISCOVERY_BUFFER_WINDOW: Duration = Duration::from_secs(1);
+/// How much more likely a device is the assistant if its vendor makes voice assistants.
+const VENDOR_WEIGHT: f64 = 1.5;
+
+/// A vendor of voice assistants.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Vendor {
+    Apple,
+    Amazon,
+    Google,
+}
+
+impl Vendor {
+    /// Look up the vendor of a device by the organisationally unique identifier of its address.
+    ///
+    /// Returns `None` if the vendor is not in the bundled table or the address is locally
+    /// administered, e.g. a private address chosen at random by the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The MAC address of the device.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str::FromStr;
+    /// # use varys_network::address::MacAddress;
+    /// # use varys_network::discover::Vendor;
+    /// let homepod = MacAddress::from_str("f0:18:98:12:34:56").unwrap();
+    /// let private = MacAddress::from_str("f2:18:98:12:34:56").unwrap();
+    ///
+    /// assert_eq!(Vendor::of(&homepod), Some(Vendor::Apple));
+    /// assert_eq!(Vendor::of(&private), None);
+    /// ```
+    pub fn of(address: &MacAddress) -> Option<Vendor> {
+        let prefix = [address.0, address.1, address.2];
+
+        oui::OUIS
+            .iter()
+            .find(|(oui, _)| *oui == prefix)
+            .map(|(_, vendor)| *vendor)
+    }
+}
+
+impl Display for Vendor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The time during which a probe, e.g. the wake word, was spoken.
+#[derive(Copy, Clone, Debug)]
+pub struct Probe {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// A device that might be the voice assistant.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub address: MacAddress,
+    pub vendor: Option<Vendor>,
+    /// How many probes the device sent or received packets after.
+    pub probe_hits: usize,
+    /// The number of bytes sent or received while probing.
+    pub active_bytes: u64,
+    /// The number of bytes sent or received while not probing.
+    pub idle_bytes: u64,
+    /// How likely the device is the voice assistant, higher is more likely.
+    pub score: f64,
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}): score {:.3}, {} probe hits, {} bytes while probing, {} bytes otherwise",
+            self.address,
+            self.vendor
+                .map_or("unknown vendor".to_string(), |vendor| vendor.to_string()),
+            self.score,
+            self.probe_hits,
+            self.active_bytes,
+            self.idle_bytes
+        )
+    }
+}
+
+/// Rank the devices in a capture by how well their traffic correlates with the probes.
+///
+/// A voice assistant sends a burst of traffic after each time it hears its wake word, so the
+/// device with the highest share of its traffic inside the probe windows is most likely the
+/// assistant. Devices from a vendor of voice assistants are preferred. Broadcast and multicast
+/// addresses are ignored.
+///
+/// Returns the candidates sorted by descending score.
+///
+/// # Arguments
+///
+/// * `packets`: The captured packets.
+/// * `probes`: When the probes were spoken.
+/// * `response_window`: How long after the end of a probe traffic is still counted as a response.
+///
+/// # Examples
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use chrono::{Duration, TimeZone, Utc};
+/// # use varys_network::address::MacAddress;
+/// # use varys_network::discover::{self, Probe};
+/// # use varys_network::packet::PacketHeader;
+/// let assistant = MacAddress::from_str("f0:18:98:12:34:56").unwrap();
+/// let laptop = MacAddress::from_str("3a:00:00:00:00:01").unwrap();
+/// let router = MacAddress::from_str("00:00:5e:00:53:01").unwrap();
+/// let start = Utc.timestamp_opt(0, 0).unwrap();
+/// let packet = |seconds, source| PacketHeader {
+///     timestamp: start + Duration::seconds(seconds),
+///     len: 1000,
+///     source: Some(source),
+///     destination: Some(router),
+///     source_ip: None,
+///     destination_ip: None,
+/// };
+/// // the laptop sends traffic all the time, the assistant only after the probe
+/// let packets = [
+///     packet(0, laptop),
+///     packet(5, laptop),
+///     packet(10, laptop),
+///     packet(11, assistant),
+///     packet(12, assistant),
+///     packet(15, laptop),
+/// ];
+/// let probes = [Probe {
+///     start: start + Duration::seconds(10),
+///     end: start + Duration::seconds(11),
+/// }];
+///
+/// let candidates = discover::rank_candidates(&packets, &probes, Duration::seconds(2));
+/// assert_eq!(candidates[0].address, assistant);
+/// ```
+pub fn rank_candidates<P: CapturedPacket>(
+    packets: &[P],
+    probes: &[Probe],
+    response_window: chrono::Duration,
+) -> Vec<Candidate> {
+    let windows = probes
+        .iter()
+        .map(|probe| (probe.start, probe.end + response_window))
+        .collect::<Vec<_>>();
+    let (Some(first), Some(last)) = (
+        packets.iter().map(|packet| packet.timestamp()).min(),
+        packets.iter().map(|packet| packet.timestamp()).max(),
+    ) else {
+        return Vec::new();
+    };
+    let active_seconds = windows
+        .iter()
+        .map(|(start, end)| seconds(*end - *start))
+        .sum::<f64>()
+        .max(1.);
+    let idle_seconds = (seconds(last - first) - active_seconds).max(1.);
+
+    let mut traffic: HashMap<MacAddress, (Vec<bool>, u64, u64)> = HashMap::new();
+    for packet in packets {
+        let timestamp = packet.timestamp();
+        let window = windows
+            .iter()
+            .position(|(start, end)| *start <= timestamp && timestamp <= *end);
+
+        for address in [packet.source(), packet.destination()]
+            .into_iter()
+            .flatten()
+        {
+            if address.0 & 0x01 != 0 {
+                // broadcast and multicast
+                continue;
+            }
+
+            let (hits, active_bytes, idle_bytes) = traffic
+                .entry(address)
+                .or_insert_with(|| (vec![false; windows.len()], 0, 0));
+            match window {
+                Some(window) => {
+                    hits[window] = true;
+                    *active_bytes += packet.length() as u64;
+                }
+                None => *idle_bytes += packet.length() as u64,
+            }
+        }
+    }
+
+    let mut candidates = traffic
+        .into_iter()
+        .map(|(address, (hits, active_bytes, idle_bytes))| {
+            let probe_hits = hits.iter().filter(|hit| **hit).count();
+            let coverage = probe_hits as f64 / probes.len().max(1) as f64;
+            let active_rate = active_bytes as f64 / active_seconds;
+            let idle_rate = idle_bytes as f64 / idle_seconds;
+            let correlation = active_rate / (active_rate + idle_rate).max(f64::MIN_POSITIVE);
+            let vendor = Vendor::of(&address);
+            let weight = if vendor.is_some() { VENDOR_WEIGHT } else { 1. };
+
+            Candidate {
+                address,
+                vendor,
+                probe_hits,
+                active_bytes,
+                idle_bytes,
+                score: coverage * correlation * weight,
+            }
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    candidates
+}
+
+fn seconds(duration: chrono::Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.
+}
+
+/// A running discovery of the voice assistant's MAC address.
+///
+/// Start it, speak a number of probes while recording when they were spoken with
+/// [`Discovery::probe`] and get the ranked candidates with [`Discovery::finish`].
+pub struct Discovery {
+    instance: ContinuousInstance,
+    packets: Arc<Mutex<Vec<PacketHeader>>>,
+    probes: Vec<Probe>,
+}
+
+impl Discovery {
+    /// Start capturing the traffic of all devices.
+    ///
+    /// The sniffer should not filter traffic by address, otherwise the assistant might not be
+    /// seen at all. Returns the same errors as [`Sniffer::start`].
+    ///
+    /// # Arguments
+    ///
+    /// * `sniffer`: The sniffer to capture with.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use chrono::{Duration, Utc};
+    /// # use varys_network::discover::Discovery;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::Sniffer;
+    /// let sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// let mut discovery = Discovery::start(&sniffer).unwrap();
+    ///
+    /// let start = Utc::now();
+    /// // speak the wake word...
+    /// discovery.probe(start, Utc::now());
+    ///
+    /// let candidates = discovery.finish(Duration::seconds(5)).unwrap();
+    /// println!("The assistant is most likely {}", candidates[0]);
+    /// ```
+    pub fn start(sniffer: &Sniffer) -> Result<Discovery, Error> {
+        info!("Starting discovery...");
+
+        let instance = sniffer.start_continuous(DISCOVERY_BUFFER_WINDOW)?;
+        let packets = Arc::new(Mutex::new(Vec::new()));
+        let subscriber_packets = packets.clone();
+        instance.instance().subscribe_with(move |packet| {
+            subscriber_packets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(PacketHeader {
+                    timestamp: packet.timestamp,
+                    len: packet.len,
+                    source: packet.source(),
+                    destination: packet.destination(),
+                    source_ip: packet.source_ip(),
+                    destination_ip: packet.destination_ip(),
+                })
+        });
+
+        Ok(Discovery {
+            instance,
+            packets,
+            probes: Vec::new(),
+        })
+    }
+
+    /// Record when a probe was spoken.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: When the probe started.
+    /// * `end`: When the probe ended.
+    pub fn probe(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) {
+        self.probes.push(Probe { start, end });
+    }
+
+    /// Stop capturing and rank the devices seen by how well their traffic correlates with the
+    /// probes.
+    ///
+    /// Returns the candidates sorted by descending score or an error if the sniffer could not be
+    /// stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `response_window`: How long after the end of a probe traffic is still counted as a
+    ///   response.
+    pub fn finish(self, response_window: chrono::Duration) -> Result<Vec<Candidate>, Error> {
+        info!("{}", self.instance.stop()?);
+
+        let packets = self.packets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        Ok(rank_candidates(&packets, &self.probes, response_window))
+    }
+}
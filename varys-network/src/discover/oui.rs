@@ -0,0 +1,66 @@
+use crate::discover::Vendor;
+
+/// Organisationally unique identifiers of the vendors of supported voice assistants.
+///
+/// This is only a subset of the identifiers registered by each vendor, see
+/// <https://standards-oui.ieee.org> for the full registry.
+pub(crate) const OUIS: &[([u8; 3], Vendor)] = &[
+    ([0x00, 0x03, 0x93], Vendor::Apple),
+    ([0x00, 0x0a, 0x95], Vendor::Apple),
+    ([0x00, 0x17, 0xf2], Vendor::Apple),
+    ([0x00, 0x1b, 0x63], Vendor::Apple),
+    ([0x00, 0x1e, 0xc2], Vendor::Apple),
+    ([0x00, 0x25, 0x00], Vendor::Apple),
+    ([0x28, 0xcf, 0xe9], Vendor::Apple),
+    ([0x3c, 0x07, 0x54], Vendor::Apple),
+    ([0x40, 0xa6, 0xd9], Vendor::Apple),
+    ([0x68, 0xa8, 0x6d], Vendor::Apple),
+    ([0x7c, 0xd1, 0xc3], Vendor::Apple),
+    ([0x88, 0x66, 0x5a], Vendor::Apple),
+    ([0x8c, 0x85, 0x90], Vendor::Apple),
+    ([0x98, 0x01, 0xa7], Vendor::Apple),
+    ([0xa4, 0x5e, 0x60], Vendor::Apple),
+    ([0xac, 0xbc, 0x32], Vendor::Apple),
+    ([0xd0, 0x03, 0x4b], Vendor::Apple),
+    ([0xdc, 0xa9, 0x04], Vendor::Apple),
+    ([0xf0, 0x18, 0x98], Vendor::Apple),
+    ([0xf0, 0x99, 0xbf], Vendor::Apple),
+    ([0x00, 0xfc, 0x8b], Vendor::Amazon),
+    ([0x0c, 0x47, 0xc9], Vendor::Amazon),
+    ([0x18, 0x74, 0x2e], Vendor::Amazon),
+    ([0x34, 0xd2, 0x70], Vendor::Amazon),
+    ([0x38, 0xf7, 0x3d], Vendor::Amazon),
+    ([0x40, 0xb4, 0xcd], Vendor::Amazon),
+    ([0x44, 0x65, 0x0d], Vendor::Amazon),
+    ([0x4c, 0xef, 0xc0], Vendor::Amazon),
+    ([0x50, 0xdc, 0xe7], Vendor::Amazon),
+    ([0x68, 0x37, 0xe9], Vendor::Amazon),
+    ([0x68, 0x54, 0xfd], Vendor::Amazon),
+    ([0x6c, 0x56, 0x97], Vendor::Amazon),
+    ([0x74, 0xc2, 0x46], Vendor::Amazon),
+    ([0x84, 0xd6, 0xd0], Vendor::Amazon),
+    ([0x88, 0x71, 0xe5], Vendor::Amazon),
+    ([0xa0, 0x02, 0xdc], Vendor::Amazon),
+    ([0xac, 0x63, 0xbe], Vendor::Amazon),
+    ([0xb4, 0x7c, 0x9c], Vendor::Amazon),
+    ([0xf0, 0x27, 0x2d], Vendor::Amazon),
+    ([0xf0, 0x81, 0x73], Vendor::Amazon),
+    ([0xfc, 0x65, 0xde], Vendor::Amazon),
+    ([0xfc, 0xa1, 0x83], Vendor::Amazon),
+    ([0x00, 0x1a, 0x11], Vendor::Google),
+    ([0x1c, 0xf2, 0x9a], Vendor::Google),
+    ([0x20, 0xdf, 0xb9], Vendor::Google),
+    ([0x30, 0xfd, 0x38], Vendor::Google),
+    ([0x3c, 0x5a, 0xb4], Vendor::Google),
+    ([0x44, 0x07, 0x0b], Vendor::Google),
+    ([0x48, 0xd6, 0xd5], Vendor::Google),
+    ([0x54, 0x60, 0x09], Vendor::Google),
+    ([0x7c, 0x2e, 0xbd], Vendor::Google),
+    ([0x94, 0xeb, 0x2c], Vendor::Google),
+    ([0xa4, 0x77, 0x33], Vendor::Google),
+    ([0xd8, 0x6c, 0x63], Vendor::Google),
+    ([0xe4, 0xf0, 0x42], Vendor::Google),
+    ([0xf4, 0xf5, 0xd8], Vendor::Google),
+    ([0xf4, 0xf5, 0xe8], Vendor::Google),
+    ([0xf8, 0x8f, 0xca], Vendor::Google),
+];
@@ -6,14 +6,28 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     InvalidMacAddress(#[from] pnet::datalink::ParseMacAddrErr),
+    #[error(transparent)]
+    InvalidIpAddress(#[from] std::net::AddrParseError),
     #[error("No default network device was found")]
     DefaultDeviceNotFound,
     #[error("Could not find device {0}")]
     NetworkDeviceNotFound(String),
+    #[error("No network device to capture on was given")]
+    NoDeviceGiven,
+    #[error("Cannot merge captures with link types {0} and {1}")]
+    MixedLinkTypes(i32, i32),
     #[error("Tried to stop sniffer that was not running")]
     CannotStop,
     #[error("Did not receive sniffer stats")]
     NoStatsReceived,
+    #[error("The capture buffer is unavailable because the sniffer thread panicked")]
+    CaptureBufferUnavailable,
+    #[error("Malformed packet: {0}")]
+    MalformedPacket(&'static str),
+    #[error("Invalid pcapng file: {0}")]
+    InvalidPcapng(&'static str),
+    #[error("Unsupported link type {0}")]
+    UnsupportedLinkType(i32),
     #[error("Pcap error: {0}")]
     Pcap(String),
 }
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use pnet::packet::ip::IpNextHeaderProtocol;
+
+use crate::packet::{CapturedPacket, Packet, PacketDirection};
+use crate::protocol::DecodedPacket;
+
+/// Identifies a bidirectional connection independent of the direction of a packet.
+///
+/// The two endpoints are stored in a canonical order, so a packet and its reply map to the same
+/// key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FlowKey {
+    pub protocol: IpNextHeaderProtocol,
+    pub lower: SocketAddr,
+    pub upper: SocketAddr,
+}
+
+impl FlowKey {
+    /// Create the key of a connection between two endpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol`: The transport protocol of the connection.
+    /// * `source`: One endpoint of the connection.
+    /// * `destination`: The other endpoint of the connection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pnet::packet::ip::IpNextHeaderProtocols;
+    /// # use varys_network::flow::FlowKey;
+    /// let client = "192.168.1.2:51000".parse().unwrap();
+    /// let server = "17.0.0.1:443".parse().unwrap();
+    ///
+    /// assert_eq!(
+    ///     FlowKey::new(IpNextHeaderProtocols::Tcp, client, server),
+    ///     FlowKey::new(IpNextHeaderProtocols::Tcp, server, client)
+    /// );
+    /// ```
+    pub fn new(
+        protocol: IpNextHeaderProtocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) -> Self {
+        let (lower, upper) = if source <= destination {
+            (source, destination)
+        } else {
+            (destination, source)
+        };
+
+        FlowKey {
+            protocol,
+            lower,
+            upper,
+        }
+    }
+
+    /// The key of the connection a decoded packet belongs to.
+    ///
+    /// Returns `None` if the packet is not a TCP or UDP packet.
+    pub fn of(packet: &DecodedPacket) -> Option<Self> {
+        let (source, destination) = endpoints(packet)?;
+
+        Some(FlowKey::new(packet.ip?.protocol, source, destination))
+    }
+}
+
+/// Packet and byte counts for one direction of a flow.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FlowCounts {
+    pub packets: usize,
+    /// The sum of the packet lengths, read from the packet headers.
+    pub bytes: usize,
+}
+
+/// A bidirectional connection between two endpoints, together with all its packets.
+///
+/// Directions are relative to the initiator, the endpoint that sent the first captured packet.
+pub struct Flow<P = Packet> {
+    pub protocol: IpNextHeaderProtocol,
+    /// The endpoint that sent the first captured packet of this flow.
+    pub initiator: SocketAddr,
+    /// The other endpoint of this flow.
+    pub responder: SocketAddr,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// Packets sent by the initiator.
+    pub outgoing: FlowCounts,
+    /// Packets sent by the responder.
+    pub incoming: FlowCounts,
+    /// The packets of this flow in the order they were added.
+    pub packets: Vec<P>,
+}
+
+impl<P> Flow<P> {
+    pub fn key(&self) -> FlowKey {
+        FlowKey::new(self.protocol, self.initiator, self.responder)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end_time - self.start_time
+    }
+
+    /// The total number of bytes exchanged in both directions.
+    pub fn total_bytes(&self) -> usize {
+        self.outgoing.bytes + self.incoming.bytes
+    }
+
+    /// The direction of a packet sent from `source`, as seen from the initiator.
+    ///
+    /// Returns `None` if `source` is not an endpoint of this flow.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The endpoint that sent the packet.
+    pub fn direction(&self, source: &SocketAddr) -> Option<PacketDirection> {
+        if *source == self.initiator {
+            Some(PacketDirection::Out)
+        } else if *source == self.responder {
+            Some(PacketDirection::In)
+        } else {
+            None
+        }
+    }
+}
+
+impl<P: CapturedPacket> Flow<P> {
+    fn new(
+        protocol: IpNextHeaderProtocol,
+        initiator: SocketAddr,
+        responder: SocketAddr,
+        packet: &P,
+    ) -> Self {
+        Flow {
+            protocol,
+            initiator,
+            responder,
+            start_time: packet.timestamp(),
+            end_time: packet.timestamp(),
+            outgoing: FlowCounts::default(),
+            incoming: FlowCounts::default(),
+            packets: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, source: &SocketAddr, packet: P) {
+        let counts = match self.direction(source) {
+            Some(PacketDirection::In) => &mut self.incoming,
+            _ => &mut self.outgoing,
+        };
+        counts.packets += 1;
+        counts.bytes += packet.length();
+
+        self.start_time = self.start_time.min(packet.timestamp());
+        self.end_time = self.end_time.max(packet.timestamp());
+        self.packets.push(packet);
+    }
+}
+
+impl<P> Display for Flow<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} <-> {} ({:.2} seconds, {} packets / {} bytes out, {} packets / {} bytes in)",
+            self.protocol,
+            self.initiator,
+            self.responder,
+            self.duration().num_milliseconds() as f32 / 1000.,
+            self.outgoing.packets,
+            self.outgoing.bytes,
+            self.incoming.packets,
+            self.incoming.bytes
+        )
+    }
+}
+
+/// Splits captured packets into bidirectional TCP and UDP flows keyed by their 5-tuple.
+///
+/// # Examples
+///
+/// ```
+/// # use chrono::Utc;
+/// # use varys_network::flow::FlowTable;
+/// # use varys_network::link::LinkType;
+/// # use varys_network::packet::Packet;
+/// fn udp(source: [u8; 4], destination: [u8; 4], source_port: u16, port: u16) -> Packet {
+///     let mut data = vec![0; 42];
+///     data[12..14].copy_from_slice(&[0x08, 0x00]); // IPv4
+///     data[14] = 0x45;
+///     data[16..18].copy_from_slice(&28_u16.to_be_bytes());
+///     data[23] = 17; // UDP
+///     data[26..30].copy_from_slice(&source);
+///     data[30..34].copy_from_slice(&destination);
+///     data[34..36].copy_from_slice(&source_port.to_be_bytes());
+///     data[36..38].copy_from_slice(&port.to_be_bytes());
+///     data[38..40].copy_from_slice(&8_u16.to_be_bytes());
+///     Packet {
+///         timestamp: Utc::now(),
+///         len: data.len(),
+///         data,
+///         link_type: LinkType::Ethernet,
+///     }
+/// }
+///
+/// let (client, server) = ([192, 168, 1, 2], [17, 0, 0, 1]);
+/// let mut table = FlowTable::default();
+/// table.add(udp(client, server, 50000, 443));
+/// table.add(udp(server, client, 443, 50000));
+/// table.add(udp(client, server, 50001, 443));
+///
+/// let flows = table.into_flows();
+/// assert_eq!(flows.len(), 2);
+/// assert_eq!(flows[0].outgoing.packets, 1);
+/// assert_eq!(flows[0].incoming.packets, 1);
+/// assert_eq!(flows[0].initiator, "192.168.1.2:50000".parse().unwrap());
+/// ```
+#[derive(Default)]
+pub struct FlowTable {
+    flows: Vec<Flow>,
+    index: HashMap<FlowKey, usize>,
+    /// The number of packets that were not added because they are not TCP or UDP packets.
+    pub skipped: usize,
+}
+
+impl FlowTable {
+    /// Add a packet to the flow it belongs to, creating a new flow if necessary.
+    ///
+    /// Returns `false` if the packet was skipped because it could not be decoded or is not a TCP
+    /// or UDP packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The packet to add.
+    pub fn add(&mut self, packet: Packet) -> bool {
+        let Some((key, source, destination)) = packet.decode().ok().and_then(|decoded| {
+            let (source, destination) = endpoints(&decoded)?;
+
+            Some((FlowKey::of(&decoded)?, source, destination))
+        }) else {
+            self.skipped += 1;
+            return false;
+        };
+
+        let index = *self.index.entry(key).or_insert_with(|| {
+            self.flows
+                .push(Flow::new(key.protocol, source, destination, &packet));
+            self.flows.len() - 1
+        });
+        self.flows[index].add(&source, packet);
+
+        true
+    }
+
+    /// The flows in the order of their first packet.
+    pub fn flows(&self) -> &[Flow] {
+        &self.flows
+    }
+
+    pub fn into_flows(self) -> Vec<Flow> {
+        self.flows
+    }
+}
+
+impl FromIterator<Packet> for FlowTable {
+    fn from_iter<T: IntoIterator<Item = Packet>>(packets: T) -> Self {
+        let mut table = FlowTable::default();
+        packets.into_iter().for_each(|packet| {
+            table.add(packet);
+        });
+
+        table
+    }
+}
+
+fn endpoints(packet: &DecodedPacket) -> Option<(SocketAddr, SocketAddr)> {
+    let ip = packet.ip?;
+    let transport = packet.transport.as_ref()?;
+
+    Some((
+        SocketAddr::new(ip.source, transport.source_port()),
+        SocketAddr::new(ip.destination, transport.destination_port()),
+    ))
+}
@@ -0,0 +1,341 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::Path;
+
+use log::trace;
+
+use crate::error::Error;
+use crate::flow::Flow;
+use crate::hostname::dns::DnsData;
+use crate::hostname::quic::CryptoFrame;
+use crate::packet;
+use crate::packet::Packet;
+use crate::protocol::{QuicHeader, QuicPacketType, TransportHeader};
+
+pub mod dns;
+pub mod quic;
+pub mod tls;
+
+const DNS_PORT: u16 = 53;
+/// How many incomplete QUIC handshakes are kept at most, the oldest one is dropped first.
+const MAX_PENDING_HANDSHAKES: usize = 256;
+
+/// Maps IP addresses to the hostnames they were contacted as.
+///
+/// Hostnames are collected from the server name indication of TLS and QUIC handshakes and from
+/// A, AAAA and CNAME answers of DNS responses. If a hostname is an alias, the addresses it resolves
+/// to are labelled with both the alias and its canonical name.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::hostname;
+/// let hostnames = hostname::read_hostnames("capture.pcap").unwrap();
+///
+/// for (address, names) in hostnames.iter() {
+///     println!("{address}: {names:?}");
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Hostnames {
+    names: HashMap<IpAddr, BTreeSet<String>>,
+    /// Maps canonical names to their aliases.
+    aliases: HashMap<String, BTreeSet<String>>,
+    /// CRYPTO frames of QUIC handshakes whose ClientHello was not complete yet, by destination
+    /// connection id.
+    pending_handshakes: HashMap<Vec<u8>, Vec<CryptoFrame>>,
+    /// The connection ids of the pending handshakes, oldest first.
+    pending_order: VecDeque<Vec<u8>>,
+}
+
+impl Hostnames {
+    /// Collect hostnames from a packet.
+    ///
+    /// Packets that cannot be decoded or carry no hostnames are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The packet to collect hostnames from.
+    pub fn add_packet(&mut self, packet: &Packet) {
+        let Ok(decoded) = packet.decode() else {
+            return;
+        };
+        let (Some(ip), Some(transport)) = (decoded.ip, &decoded.transport) else {
+            return;
+        };
+        let payload = decoded.payload(&packet.data);
+
+        match transport {
+            TransportHeader::Tcp(tcp) if tcp.source_port == DNS_PORT => {
+                // DNS messages over TCP are prefixed with their length
+                self.add_dns_response(payload.get(2..).unwrap_or_default());
+            }
+            TransportHeader::Tcp(_) => {
+                if let Some(name) = tls::server_name_from_record(payload) {
+                    self.insert(ip.destination, name);
+                }
+            }
+            TransportHeader::Udp(udp) if udp.source_port == DNS_PORT => {
+                self.add_dns_response(payload);
+            }
+            TransportHeader::Udp(udp) => {
+                if let Some(QuicHeader::Long {
+                    packet_type: QuicPacketType::Initial,
+                    ..
+                }) = udp.quic
+                {
+                    self.add_quic_initial(ip.destination, payload);
+                }
+            }
+        }
+    }
+
+    /// Label an address with a hostname.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to label.
+    /// * `hostname`: The hostname of the address.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::net::IpAddr;
+    /// # use varys_network::hostname::Hostnames;
+    /// let address: IpAddr = "17.253.53.207".parse().unwrap();
+    /// let mut hostnames = Hostnames::default();
+    /// hostnames.insert(address, "guzzoni.apple.com".to_string());
+    ///
+    /// assert!(hostnames.matches(&address, "apple.com"));
+    /// assert!(!hostnames.matches(&address, "ple.com"));
+    /// ```
+    pub fn insert(&mut self, address: IpAddr, hostname: String) {
+        let mut names = self.aliases_of(&hostname);
+        names.insert(hostname);
+
+        trace!("Labelling {address} with {names:?}");
+
+        self.names.entry(address).or_default().extend(names);
+    }
+
+    /// The hostnames an address was contacted as or `None` if it is unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to get the hostnames of.
+    pub fn get(&self, address: &IpAddr) -> Option<&BTreeSet<String>> {
+        self.names.get(address)
+    }
+
+    /// Iterate over all labelled addresses and their hostnames.
+    pub fn iter(&self) -> impl Iterator<Item = (&IpAddr, &BTreeSet<String>)> {
+        self.names.iter()
+    }
+
+    /// Whether an address was contacted as the given hostname or any of its subdomains.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to check.
+    /// * `hostname`: The hostname to look for, e.g. `apple.com`.
+    pub fn matches(&self, address: &IpAddr, hostname: &str) -> bool {
+        self.get(address).is_some_and(|names| {
+            names.iter().any(|name| {
+                name == hostname
+                    || name
+                        .strip_suffix(hostname)
+                        .is_some_and(|subdomain| subdomain.ends_with('.'))
+            })
+        })
+    }
+
+    /// A human-readable label for the server side of a flow.
+    ///
+    /// Returns `None` if no hostname is known for either endpoint of the flow.
+    ///
+    /// # Arguments
+    ///
+    /// * `flow`: The flow to label.
+    pub fn label<P>(&self, flow: &Flow<P>) -> Option<String> {
+        self.get(&flow.responder.ip())
+            .or_else(|| self.get(&flow.initiator.ip()))
+            .map(|names| names.iter().cloned().collect::<Vec<_>>().join(", "))
+    }
+
+    fn add_dns_response(&mut self, message: &[u8]) {
+        for answer in dns::answers(message).unwrap_or_default() {
+            match answer.data {
+                DnsData::Address(address) => self.insert(address, answer.name),
+                DnsData::Cname(canonical_name) => {
+                    // addresses that were already resolved also get the new alias
+                    self.names
+                        .values_mut()
+                        .filter(|names| names.contains(&canonical_name))
+                        .for_each(|names| {
+                            names.insert(answer.name.clone());
+                        });
+                    self.aliases
+                        .entry(canonical_name)
+                        .or_default()
+                        .insert(answer.name);
+                }
+            }
+        }
+    }
+
+    fn add_quic_initial(&mut self, destination: IpAddr, datagram: &[u8]) {
+        let Some(initial) = quic::decrypt_client_initial(datagram) else {
+            return;
+        };
+
+        let id = initial.destination_connection_id;
+        if !self.pending_handshakes.contains_key(&id) {
+            if self.pending_order.len() >= MAX_PENDING_HANDSHAKES {
+                if let Some(oldest) = self.pending_order.pop_front() {
+                    trace!("Dropping pending QUIC handshake {oldest:02x?}");
+                    self.pending_handshakes.remove(&oldest);
+                }
+            }
+            self.pending_order.push_back(id.clone());
+        }
+        let frames = self.pending_handshakes.entry(id.clone()).or_default();
+        frames.extend(initial.crypto);
+
+        let handshake = quic::assemble_crypto(frames);
+        let name = tls::server_name(&handshake);
+        // a complete ClientHello without a server name will not get one later
+        if name.is_some() || is_complete_handshake(&handshake) {
+            self.pending_handshakes.remove(&id);
+            self.pending_order.retain(|pending| *pending != id);
+        }
+        if let Some(name) = name {
+            self.insert(destination, name);
+        }
+    }
+
+    fn aliases_of(&self, hostname: &str) -> BTreeSet<String> {
+        let mut aliases = BTreeSet::new();
+        let mut unvisited = vec![hostname];
+
+        while let Some(name) = unvisited.pop() {
+            for alias in self.aliases.get(name).into_iter().flatten() {
+                if aliases.insert(alias.clone()) {
+                    unvisited.push(alias);
+                }
+            }
+        }
+
+        aliases
+    }
+}
+
+impl<'a> FromIterator<&'a Packet> for Hostnames {
+    fn from_iter<T: IntoIterator<Item = &'a Packet>>(packets: T) -> Self {
+        let mut hostnames = Hostnames::default();
+        packets
+            .into_iter()
+            .for_each(|packet| hostnames.add_packet(packet));
+
+        hostnames
+    }
+}
+
+/// Collect the hostnames of all addresses contacted in a pcap file.
+///
+/// # Arguments
+///
+/// * `path`: The path to the pcap file.
+pub fn read_hostnames<P: AsRef<Path>>(path: P) -> Result<Hostnames, Error> {
+    let mut hostnames = Hostnames::default();
+
+    for packet in packet::read_packets(path)? {
+        hostnames.add_packet(&packet?);
+    }
+
+    Ok(hostnames)
+}
+
+/// Whether a handshake message is complete according to its length.
+fn is_complete_handshake(handshake: &[u8]) -> bool {
+    let mut reader = Reader::new(handshake);
+
+    reader
+        .u8()
+        .and_then(|_| reader.u24())
+        .is_some_and(|length| handshake.len() >= 4 + length as usize)
+}
+
+/// Reads big-endian values from a byte slice without panicking if it is too short.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader::at(data, 0)
+    }
+
+    fn at(data: &'a [u8], position: usize) -> Self {
+        Reader { data, position }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.checked_add(len)?)?;
+        self.position += len;
+
+        Some(bytes)
+    }
+
+    /// Read all remaining bytes.
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = self.data.get(self.position..).unwrap_or_default();
+        self.position = self.data.len();
+
+        rest
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u24(&mut self) -> Option<u32> {
+        let bytes = self.bytes(3)?;
+
+        Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    /// Read a QUIC variable-length integer.
+    ///
+    /// See <https://datatracker.ietf.org/doc/html/rfc9000#section-16> for details.
+    fn varint(&mut self) -> Option<u64> {
+        let len = 1 << (*self.data.get(self.position)? >> 6);
+        let bytes = self.bytes(len)?;
+
+        Some(
+            bytes[1..]
+                .iter()
+                .fold(u64::from(bytes[0] & 0x3f), |value, &byte| {
+                    (value << 8) | u64::from(byte)
+                }),
+        )
+    }
+}
@@ -0,0 +1,127 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::hostname::Reader;
+
+const RESPONSE_FLAG: u16 = 0x8000;
+const TYPE_A: u16 = 1;
+const TYPE_CNAME: u16 = 5;
+const TYPE_AAAA: u16 = 28;
+/// The maximum number of compression pointers to follow in a single name.
+const MAX_POINTERS: usize = 32;
+
+/// A resource record from the answer section of a DNS response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DnsAnswer {
+    /// The name the record belongs to.
+    pub name: String,
+    pub data: DnsData,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DnsData {
+    /// The address of an A or AAAA record.
+    Address(IpAddr),
+    /// The canonical name of a CNAME record.
+    Cname(String),
+}
+
+/// Parse the A, AAAA and CNAME records in the answer section of a DNS response.
+///
+/// Other record types are skipped. Returns `None` if the message is not a DNS response or is
+/// malformed.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc1035#section-4> for details.
+///
+/// # Arguments
+///
+/// * `message`: The DNS message, e.g. the payload of a UDP packet from port 53.
+///
+/// # Examples
+///
+/// ```
+/// # use varys_network::hostname::dns::{self, DnsAnswer, DnsData};
+/// let mut message = vec![0, 1, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+/// // question: apple.com A IN
+/// message.extend(b"\x05apple\x03com\x00");
+/// message.extend([0, 1, 0, 1]);
+/// // answer: pointer to the name in the question, A IN, ttl, 4 bytes of data
+/// message.extend([0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+/// message.extend([17, 253, 144, 10]);
+///
+/// assert_eq!(
+///     dns::answers(&message),
+///     Some(vec![DnsAnswer {
+///         name: "apple.com".to_string(),
+///         data: DnsData::Address("17.253.144.10".parse().unwrap()),
+///     }])
+/// );
+/// ```
+pub fn answers(message: &[u8]) -> Option<Vec<DnsAnswer>> {
+    let mut reader = Reader::new(message);
+    reader.u16()?; // id
+    if reader.u16()? & RESPONSE_FLAG == 0 {
+        return None;
+    }
+    let question_count = reader.u16()?;
+    let answer_count = reader.u16()?;
+    reader.skip(4)?; // authority and additional record counts
+
+    for _ in 0..question_count {
+        read_name(message, &mut reader)?;
+        reader.skip(4)?; // type and class
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..answer_count {
+        let name = read_name(message, &mut reader)?;
+        let record_type = reader.u16()?;
+        reader.skip(6)?; // class and ttl
+        let data_len = reader.u16()? as usize;
+        let data_start = reader.position;
+        let data = reader.bytes(data_len)?;
+
+        let data = match record_type {
+            TYPE_A => DnsData::Address(Ipv4Addr::from(<[u8; 4]>::try_from(data).ok()?).into()),
+            TYPE_AAAA => DnsData::Address(Ipv6Addr::from(<[u8; 16]>::try_from(data).ok()?).into()),
+            TYPE_CNAME => DnsData::Cname(read_name(message, &mut Reader::at(message, data_start))?),
+            _ => continue,
+        };
+        answers.push(DnsAnswer { name, data });
+    }
+
+    Some(answers)
+}
+
+/// Read a possibly compressed domain name, leaving the reader after its end.
+fn read_name(message: &[u8], reader: &mut Reader) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut current = Reader::at(message, reader.position);
+    let mut pointers = 0;
+
+    loop {
+        let len = current.u8()?;
+        match len & 0xc0 {
+            0x00 if len == 0 => break,
+            0x00 => labels.push(String::from_utf8_lossy(current.bytes(len as usize)?).into_owned()),
+            0xc0 => {
+                let offset = (((len & 0x3f) as usize) << 8) | current.u8()? as usize;
+                if pointers == 0 {
+                    // the name continues elsewhere, but it ends here in the original message
+                    reader.position = current.position;
+                }
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                current.position = offset;
+            }
+            _ => return None,
+        }
+    }
+
+    if pointers == 0 {
+        reader.position = current.position;
+    }
+
+    Some(labels.join("."))
+}
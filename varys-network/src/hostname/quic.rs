@@ -0,0 +1,247 @@
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes128Gcm, Nonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::hostname::Reader;
+
+const VERSION_1: u32 = 0x00000001;
+/// The salt used to derive the keys of Initial packets in QUIC version 1.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9001#section-5.2>.
+const INITIAL_SALT_V1: [u8; 20] = [
+    0x38, 0x76, 0x2c, 0xf7, 0xf5, 0x59, 0x34, 0xb3, 0x4d, 0x17, 0x9a, 0xe6, 0xa4, 0xc8, 0x0c, 0xad,
+    0xcc, 0xbb, 0x7f, 0x0a,
+];
+const SAMPLE_OFFSET: usize = 4;
+const SAMPLE_LEN: usize = 16;
+
+const FRAME_PADDING: u64 = 0x00;
+const FRAME_PING: u64 = 0x01;
+const FRAME_ACK: u64 = 0x02;
+const FRAME_ACK_ECN: u64 = 0x03;
+const FRAME_CRYPTO: u64 = 0x06;
+
+/// A CRYPTO frame carrying part of the TLS handshake.
+#[derive(Clone, Debug)]
+pub struct CryptoFrame {
+    /// The offset of the data in the handshake stream.
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// The decrypted content of an Initial packet sent by a client.
+#[derive(Clone, Debug)]
+pub struct ClientInitial {
+    /// The destination connection id chosen by the client, which the Initial keys are derived from.
+    pub destination_connection_id: Vec<u8>,
+    pub crypto: Vec<CryptoFrame>,
+}
+
+/// Decrypt the first QUIC version 1 Initial packet of a UDP datagram sent by a client.
+///
+/// Initial packets are protected with keys derived from the destination connection id, so anyone
+/// observing the handshake can decrypt them. Returns `None` if the datagram does not start with an
+/// Initial packet or it could not be decrypted.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9001#section-5> for details.
+///
+/// # Arguments
+///
+/// * `datagram`: The UDP payload.
+pub fn decrypt_client_initial(datagram: &[u8]) -> Option<ClientInitial> {
+    let mut reader = Reader::new(datagram);
+    let first = reader.u8()?;
+    if first & 0x80 == 0 || (first & 0x30) >> 4 != 0 || reader.u32()? != VERSION_1 {
+        return None;
+    }
+    let destination_len = reader.u8()? as usize;
+    let destination_connection_id = reader.bytes(destination_len)?.to_vec();
+    let source_len = reader.u8()? as usize;
+    reader.skip(source_len)?;
+    let token_len = usize::try_from(reader.varint()?).ok()?;
+    reader.skip(token_len)?;
+    let length = usize::try_from(reader.varint()?).ok()?;
+    let packet_number_offset = reader.position;
+
+    let keys = InitialKeys::client(&destination_connection_id)?;
+
+    // remove header protection
+    let sample = datagram.get(
+        packet_number_offset + SAMPLE_OFFSET..packet_number_offset + SAMPLE_OFFSET + SAMPLE_LEN,
+    )?;
+    let mut mask = GenericArray::clone_from_slice(sample);
+    keys.header_protection.encrypt_block(&mut mask);
+    let first = first ^ (mask[0] & 0x0f);
+    let packet_number_len = (first & 0x03) as usize + 1;
+    let mut header = datagram
+        .get(..packet_number_offset + packet_number_len)?
+        .to_vec();
+    header[0] = first;
+    let mut packet_number = 0_u64;
+    for i in 0..packet_number_len {
+        header[packet_number_offset + i] ^= mask[1 + i];
+        packet_number = (packet_number << 8) | header[packet_number_offset + i] as u64;
+    }
+
+    // decrypt the payload
+    let mut nonce = keys.iv;
+    nonce[4..]
+        .iter_mut()
+        .zip(packet_number.to_be_bytes())
+        .for_each(|(byte, packet_number_byte)| *byte ^= packet_number_byte);
+    let payload = keys
+        .packet
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: datagram
+                    .get(packet_number_offset + packet_number_len..packet_number_offset + length)?,
+                aad: &header,
+            },
+        )
+        .ok()?;
+
+    Some(ClientInitial {
+        destination_connection_id,
+        crypto: crypto_frames(&payload),
+    })
+}
+
+/// Concatenate the contiguous handshake data at the start of a stream of CRYPTO frames.
+///
+/// Frames may arrive out of order or overlap, data after the first gap is ignored.
+///
+/// # Arguments
+///
+/// * `frames`: The CRYPTO frames of a handshake.
+///
+/// # Examples
+///
+/// ```
+/// # use varys_network::hostname::quic::{self, CryptoFrame};
+/// let frames = [
+///     CryptoFrame { offset: 2, data: vec![3, 4] },
+///     CryptoFrame { offset: 0, data: vec![1, 2, 3] },
+///     CryptoFrame { offset: 8, data: vec![9] },
+/// ];
+///
+/// assert_eq!(quic::assemble_crypto(&frames), vec![1, 2, 3, 4]);
+/// ```
+pub fn assemble_crypto(frames: &[CryptoFrame]) -> Vec<u8> {
+    let mut frames = frames.iter().collect::<Vec<_>>();
+    frames.sort_by_key(|frame| frame.offset);
+
+    let mut data = Vec::new();
+    for frame in frames {
+        let Ok(offset) = usize::try_from(frame.offset) else {
+            break;
+        };
+        if offset > data.len() {
+            break;
+        }
+        data.extend(frame.data.iter().skip(data.len() - offset));
+    }
+
+    data
+}
+
+/// Parse the CRYPTO frames of a decrypted packet payload, stopping at the first unsupported
+/// frame type.
+fn crypto_frames(payload: &[u8]) -> Vec<CryptoFrame> {
+    let mut reader = Reader::new(payload);
+    let mut frames = Vec::new();
+
+    while let Some(frame_type) = reader.varint() {
+        match frame_type {
+            FRAME_PADDING | FRAME_PING => {}
+            FRAME_ACK | FRAME_ACK_ECN => {
+                if skip_ack(&mut reader, frame_type == FRAME_ACK_ECN).is_none() {
+                    break;
+                }
+            }
+            FRAME_CRYPTO => {
+                let Some(frame) = crypto_frame(&mut reader) else {
+                    break;
+                };
+                frames.push(frame);
+            }
+            _ => break,
+        }
+    }
+
+    frames
+}
+
+fn crypto_frame(reader: &mut Reader) -> Option<CryptoFrame> {
+    let offset = reader.varint()?;
+    let len = usize::try_from(reader.varint()?).ok()?;
+
+    Some(CryptoFrame {
+        offset,
+        data: reader.bytes(len)?.to_vec(),
+    })
+}
+
+fn skip_ack(reader: &mut Reader, ecn: bool) -> Option<()> {
+    reader.varint()?; // largest acknowledged
+    reader.varint()?; // delay
+    let range_count = reader.varint()?;
+    reader.varint()?; // first range
+    for _ in 0..range_count {
+        reader.varint()?; // gap
+        reader.varint()?; // range length
+    }
+    if ecn {
+        for _ in 0..3 {
+            reader.varint()?;
+        }
+    }
+
+    Some(())
+}
+
+struct InitialKeys {
+    packet: Aes128Gcm,
+    iv: [u8; 12],
+    header_protection: Aes128,
+}
+
+impl InitialKeys {
+    /// Derive the keys protecting Initial packets sent by the client.
+    ///
+    /// See <https://datatracker.ietf.org/doc/html/rfc9001#section-5.2> for details.
+    fn client(destination_connection_id: &[u8]) -> Option<Self> {
+        let (_, initial) =
+            Hkdf::<Sha256>::extract(Some(&INITIAL_SALT_V1), destination_connection_id);
+        let client_secret = expand_label(&initial, "client in", 32)?;
+        let client = Hkdf::<Sha256>::from_prk(&client_secret).ok()?;
+
+        Some(InitialKeys {
+            packet: Aes128Gcm::new_from_slice(&expand_label(&client, "quic key", 16)?).ok()?,
+            iv: expand_label(&client, "quic iv", 12)?.try_into().ok()?,
+            header_protection: Aes128::new_from_slice(&expand_label(&client, "quic hp", 16)?)
+                .ok()?,
+        })
+    }
+}
+
+/// The TLS 1.3 `HKDF-Expand-Label` function with an empty context.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc8446#section-7.1>.
+fn expand_label(hkdf: &Hkdf<Sha256>, label: &str, len: u16) -> Option<Vec<u8>> {
+    let label = format!("tls13 {label}");
+    let mut info = Vec::with_capacity(4 + label.len());
+    info.extend(len.to_be_bytes());
+    info.push(u8::try_from(label.len()).ok()?);
+    info.extend(label.as_bytes());
+    info.push(0); // context length
+
+    let mut output = vec![0; len as usize];
+    hkdf.expand(&info, &mut output).ok()?;
+
+    Some(output)
+}
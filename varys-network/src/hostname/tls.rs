@@ -0,0 +1,115 @@
+use crate::hostname::Reader;
+
+const HANDSHAKE_RECORD: u8 = 0x16;
+const CLIENT_HELLO: u8 = 0x01;
+const SERVER_NAME_EXTENSION: u16 = 0x0000;
+const HOST_NAME: u8 = 0x00;
+
+/// Extract the server name indication from a TLS record carrying a ClientHello.
+///
+/// Returns `None` if the data is not a handshake record, does not contain a ClientHello or the
+/// ClientHello has no server name.
+///
+/// # Arguments
+///
+/// * `record`: The TCP payload starting with a TLS record header.
+///
+/// # Examples
+///
+/// ```
+/// # use varys_network::hostname::tls;
+/// assert_eq!(tls::server_name_from_record(&[0x17, 0x03, 0x03, 0x00, 0x00]), None);
+/// ```
+pub fn server_name_from_record(record: &[u8]) -> Option<String> {
+    let mut reader = Reader::new(record);
+    if reader.u8()? != HANDSHAKE_RECORD {
+        return None;
+    }
+    reader.skip(4)?; // version and length
+
+    server_name(reader.rest())
+}
+
+/// Extract the server name indication from a ClientHello handshake message.
+///
+/// The message may be truncated, the server name is returned as long as its extension is
+/// complete. Returns `None` if the data does not start with a ClientHello or it has no server
+/// name.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc8446#section-4.1.2> and
+/// <https://datatracker.ietf.org/doc/html/rfc6066#section-3> for details.
+///
+/// # Arguments
+///
+/// * `handshake`: The handshake message without a record header.
+///
+/// # Examples
+///
+/// ```
+/// # use varys_network::hostname::tls;
+/// let name = b"guzzoni.apple.com";
+/// let mut extension = vec![0, 0]; // server name
+/// extension.extend((name.len() as u16 + 5).to_be_bytes());
+/// extension.extend((name.len() as u16 + 3).to_be_bytes());
+/// extension.push(0); // host name
+/// extension.extend((name.len() as u16).to_be_bytes());
+/// extension.extend(name);
+///
+/// let mut hello = vec![0x03, 0x03];
+/// hello.extend([0; 32]); // random
+/// hello.push(0); // session id
+/// hello.extend([0, 2, 0x13, 0x01]); // cipher suites
+/// hello.extend([1, 0]); // compression methods
+/// hello.extend((extension.len() as u16).to_be_bytes());
+/// hello.extend(extension);
+///
+/// let mut handshake = vec![0x01, 0];
+/// handshake.extend((hello.len() as u16).to_be_bytes());
+/// handshake.extend(hello);
+///
+/// assert_eq!(tls::server_name(&handshake), Some("guzzoni.apple.com".to_string()));
+/// ```
+pub fn server_name(handshake: &[u8]) -> Option<String> {
+    let mut reader = Reader::new(handshake);
+    if reader.u8()? != CLIENT_HELLO {
+        return None;
+    }
+    reader.u24()?; // length
+    reader.skip(2 + 32)?; // version and random
+    let session_id_len = reader.u8()? as usize;
+    reader.skip(session_id_len)?;
+    let cipher_suites_len = reader.u16()? as usize;
+    reader.skip(cipher_suites_len)?;
+    let compression_methods_len = reader.u8()? as usize;
+    reader.skip(compression_methods_len)?;
+    reader.u16()?; // extensions length
+
+    while !reader.is_empty() {
+        let extension_type = reader.u16()?;
+        let extension_len = reader.u16()? as usize;
+        let extension = reader.bytes(extension_len)?;
+
+        if extension_type == SERVER_NAME_EXTENSION {
+            return host_name(extension);
+        }
+    }
+
+    None
+}
+
+fn host_name(extension: &[u8]) -> Option<String> {
+    let mut reader = Reader::new(extension);
+    reader.u16()?; // list length
+
+    while !reader.is_empty() {
+        let name_type = reader.u8()?;
+        let name_len = reader.u16()? as usize;
+        let name = reader.bytes(name_len)?;
+
+        if name_type == HOST_NAME {
+            return String::from_utf8(name.to_vec()).ok();
+        }
+    }
+
+    None
+}
@@ -1,4 +1,20 @@
 pub mod address;
+pub mod anonymise;
+pub mod burst;
+pub mod compress;
+pub mod discover;
 pub mod error;
+pub mod flow;
+pub mod hostname;
+pub mod link;
+pub mod merge;
 pub mod packet;
+pub mod pcapng;
+pub mod protocol;
+pub(crate) mod rewrite;
+pub mod ring;
 pub mod sniff;
+pub mod subscribe;
+pub mod trim;
+pub mod tzsp;
+pub mod verify;
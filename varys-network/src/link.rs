@@ -0,0 +1,266 @@
+use pcap::Linktype;
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+
+use crate::address::MacAddress;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const VLAN_TAG_LEN: usize = 4;
+const SLL_HEADER_LEN: usize = 16;
+const SLL2_HEADER_LEN: usize = 20;
+const NULL_HEADER_LEN: usize = 4;
+const LLC_SNAP_HEADER: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+const LLC_SNAP_HEADER_LEN: usize = 8;
+/// The ARP hardware type of Ethernet, used in Linux cooked capture headers.
+const ARPHRD_ETHER: u16 = 1;
+
+/// The data link layer of a capture, which determines how the frames are decoded.
+///
+/// See <https://www.tcpdump.org/linktypes.html> for details.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum LinkType {
+    /// Ethernet frames, optionally with 802.1Q VLAN tags.
+    #[default]
+    Ethernet,
+    /// Linux cooked capture headers, e.g. from capturing on the `any` device.
+    LinuxSll,
+    /// Version 2 of the Linux cooked capture headers.
+    LinuxSll2,
+    /// IP packets without any link layer header, e.g. from a tun device.
+    RawIp,
+    /// The BSD loopback header, e.g. from a `utun` device on macOS.
+    Null,
+    /// 802.11 frames, e.g. from capturing in Wi-Fi monitor mode.
+    Ieee80211,
+    /// 802.11 frames with a radiotap header.
+    Radiotap,
+    /// Any other link type, whose frames cannot be decoded.
+    Unsupported(i32),
+}
+
+impl LinkType {
+    /// Whether frames of this link type can carry MAC addresses.
+    ///
+    /// If they cannot, the direction of packets is derived from IP addresses instead.
+    pub fn has_mac_addresses(&self) -> bool {
+        matches!(
+            self,
+            LinkType::Ethernet
+                | LinkType::LinuxSll
+                | LinkType::LinuxSll2
+                | LinkType::Ieee80211
+                | LinkType::Radiotap
+        )
+    }
+
+    /// Decode the link layer header of a frame.
+    ///
+    /// Returns `None` if the frame is too short or the link type is unsupported.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The captured frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pnet::packet::ethernet::EtherTypes;
+    /// # use varys_network::link::LinkType;
+    /// let mut frame = vec![0; 20];
+    /// frame[0] = 0x45; // IPv4
+    ///
+    /// let header = LinkType::RawIp.decode(&frame).unwrap();
+    /// assert_eq!(header.source, None);
+    /// assert_eq!(header.ether_type, Some(EtherTypes::Ipv4));
+    /// assert_eq!(header.payload_offset, 0);
+    /// ```
+    pub fn decode(&self, data: &[u8]) -> Option<LinkHeader> {
+        match self {
+            LinkType::Ethernet => decode_ethernet(data),
+            LinkType::LinuxSll => decode_sll(data),
+            LinkType::LinuxSll2 => decode_sll2(data),
+            LinkType::RawIp => Some(LinkHeader::ip(data, 0)),
+            LinkType::Null => {
+                data.get(..NULL_HEADER_LEN)?;
+                Some(LinkHeader::ip(data, NULL_HEADER_LEN))
+            }
+            LinkType::Ieee80211 => decode_ieee80211(data, 0),
+            LinkType::Radiotap => {
+                let len = u16::from_le_bytes(data.get(2..4)?.try_into().ok()?) as usize;
+                decode_ieee80211(data, len)
+            }
+            LinkType::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<Linktype> for LinkType {
+    fn from(linktype: Linktype) -> Self {
+        match linktype.0 {
+            1 => LinkType::Ethernet,
+            113 => LinkType::LinuxSll,
+            276 => LinkType::LinuxSll2,
+            // the raw IP link type has different values on different platforms
+            12 | 14 | 101 | 228 | 229 => LinkType::RawIp,
+            0 | 108 => LinkType::Null,
+            105 => LinkType::Ieee80211,
+            127 => LinkType::Radiotap,
+            other => LinkType::Unsupported(other),
+        }
+    }
+}
+
+/// The decoded link layer header of a frame.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LinkHeader {
+    /// The MAC address of the sender or `None` if the link type does not carry it.
+    pub source: Option<MacAddress>,
+    /// The MAC address of the receiver or `None` if the link type does not carry it.
+    pub destination: Option<MacAddress>,
+    /// The protocol of the payload or `None` if it is unknown, e.g. for encrypted 802.11 frames.
+    pub ether_type: Option<EtherType>,
+    /// The offset of the payload from the start of the frame.
+    pub payload_offset: usize,
+}
+
+impl LinkHeader {
+    /// The header of a frame that starts with an IP packet at `offset`.
+    fn ip(data: &[u8], offset: usize) -> Self {
+        let ether_type = match data.get(offset).map(|byte| byte >> 4) {
+            Some(4) => Some(EtherTypes::Ipv4),
+            Some(6) => Some(EtherTypes::Ipv6),
+            _ => None,
+        };
+
+        LinkHeader {
+            source: None,
+            destination: None,
+            ether_type,
+            payload_offset: offset,
+        }
+    }
+}
+
+fn decode_ethernet(data: &[u8]) -> Option<LinkHeader> {
+    let mut ether_type = read_u16(data, 12)?;
+    let mut payload_offset = ETHERNET_HEADER_LEN;
+    while EtherType(ether_type) == EtherTypes::Vlan {
+        ether_type = read_u16(data, payload_offset + 2)?;
+        payload_offset += VLAN_TAG_LEN;
+    }
+
+    Some(LinkHeader {
+        source: Some(read_mac(data, 6)?),
+        destination: Some(read_mac(data, 0)?),
+        ether_type: Some(EtherType(ether_type)),
+        payload_offset,
+    })
+}
+
+/// Decode a Linux cooked capture header.
+///
+/// Only the address of the sender is captured, so the destination is always unknown.
+///
+/// See <https://www.tcpdump.org/linktypes/LINKTYPE_LINUX_SLL.html> for details.
+fn decode_sll(data: &[u8]) -> Option<LinkHeader> {
+    data.get(..SLL_HEADER_LEN)?;
+    let source = sll_address(read_u16(data, 2)?, read_u16(data, 4)?, &data[6..14]);
+
+    Some(LinkHeader {
+        source,
+        destination: None,
+        ether_type: Some(EtherType(read_u16(data, 14)?)),
+        payload_offset: SLL_HEADER_LEN,
+    })
+}
+
+/// Decode a Linux cooked capture version 2 header.
+///
+/// See <https://www.tcpdump.org/linktypes/LINKTYPE_LINUX_SLL2.html> for details.
+fn decode_sll2(data: &[u8]) -> Option<LinkHeader> {
+    data.get(..SLL2_HEADER_LEN)?;
+    let source = sll_address(read_u16(data, 8)?, data[11] as u16, &data[12..20]);
+
+    Some(LinkHeader {
+        source,
+        destination: None,
+        ether_type: Some(EtherType(read_u16(data, 0)?)),
+        payload_offset: SLL2_HEADER_LEN,
+    })
+}
+
+fn sll_address(hardware_type: u16, address_len: u16, address: &[u8]) -> Option<MacAddress> {
+    (hardware_type == ARPHRD_ETHER && address_len == 6)
+        .then(|| read_mac(address, 0))
+        .flatten()
+}
+
+/// Decode an 802.11 header starting at `offset`, e.g. after a radiotap header.
+///
+/// The source and destination are the addresses of the original sender and final receiver, so
+/// they match the addresses the devices use on a wired network.
+///
+/// See <https://en.wikipedia.org/wiki/802.11_frame_types> for details.
+fn decode_ieee80211(data: &[u8], offset: usize) -> Option<LinkHeader> {
+    let frame = data.get(offset..)?;
+    let frame_control = *frame.first()?;
+    let flags = *frame.get(1)?;
+    let frame_type = (frame_control >> 2) & 0x03;
+    let subtype = frame_control >> 4;
+    let to_ds = flags & 0x01 != 0;
+    let from_ds = flags & 0x02 != 0;
+    let protected = flags & 0x40 != 0;
+
+    let address = |index: usize| read_mac(frame, 4 + index * 6);
+
+    if frame_type != 2 {
+        // management and control frames are exchanged with the access point directly
+        return Some(LinkHeader {
+            source: address(1),
+            destination: address(0),
+            ether_type: None,
+            payload_offset: data.len(),
+        });
+    }
+
+    let (source, destination) = match (to_ds, from_ds) {
+        (false, false) => (address(1), address(0)),
+        (true, false) => (address(1), address(2)),
+        (false, true) => (address(2), address(0)),
+        (true, true) => (read_mac(frame, 24), address(2)),
+    };
+    let mut header_len = 24;
+    if to_ds && from_ds {
+        header_len += 6;
+    }
+    if subtype & 0x08 != 0 {
+        // QoS control
+        header_len += 2;
+    }
+    let ether_type = match frame.get(header_len..header_len + LLC_SNAP_HEADER_LEN) {
+        Some(llc) if !protected && llc[..6] == LLC_SNAP_HEADER => {
+            Some(EtherType(u16::from_be_bytes([llc[6], llc[7]])))
+        }
+        _ => None,
+    };
+
+    Some(LinkHeader {
+        source,
+        destination,
+        ether_type,
+        payload_offset: offset + header_len + LLC_SNAP_HEADER_LEN,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_mac(data: &[u8], offset: usize) -> Option<MacAddress> {
+    let address = data.get(offset..offset + 6)?;
+
+    Some(MacAddress(
+        address[0], address[1], address[2], address[3], address[4], address[5],
+    ))
+}
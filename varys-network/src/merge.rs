@@ -0,0 +1,198 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::link::LinkType;
+use crate::packet;
+
+/// How long packets are held back to wait for packets captured earlier on another interface.
+pub const MERGE_DELAY: Duration = Duration::from_millis(250);
+/// How far apart the same frame may be seen on two interfaces to count as a duplicate.
+pub const DUPLICATE_WINDOW: Duration = Duration::from_millis(100);
+
+/// A packet released by a [`Merger`] together with the interface that captured it.
+pub struct MergedPacket {
+    /// The index of the interface that saw the packet first.
+    pub interface: usize,
+    pub header: pcap::PacketHeader,
+    pub data: Vec<u8>,
+}
+
+impl MergedPacket {
+    /// Borrow the packet in the form libpcap uses.
+    pub fn packet(&self) -> pcap::Packet<'_> {
+        pcap::Packet {
+            header: &self.header,
+            data: &self.data,
+        }
+    }
+}
+
+/// A packet waiting in a [`Merger`], ordered by its timestamp and then by arrival.
+struct Pending {
+    timestamp: DateTime<Utc>,
+    sequence: u64,
+    packet: MergedPacket,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.timestamp, self.sequence) == (other.timestamp, other.sequence)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.timestamp, self.sequence).cmp(&(other.timestamp, other.sequence))
+    }
+}
+
+/// Merges the packets captured on several interfaces into a single stream ordered by timestamp.
+///
+/// Frames that are seen on more than one interface, e.g. on a mirrored port and on Wi-Fi, are only
+/// released once. Frames are compared by their network layer, so the link layer headers of the
+/// interfaces may differ, e.g. by a VLAN tag. Frames repeated on the same interface, e.g. TCP
+/// retransmissions, are always kept.
+pub struct Merger {
+    link_type: LinkType,
+    delay: chrono::Duration,
+    window: chrono::Duration,
+    pending: BinaryHeap<Reverse<Pending>>,
+    /// The timestamp, hash and interface of recently released packets.
+    released: VecDeque<(DateTime<Utc>, u64, usize)>,
+    sequence: u64,
+    /// How many duplicate frames were dropped so far.
+    pub duplicates: u32,
+}
+
+impl Merger {
+    /// Create a merger for packets of the given link type using [`MERGE_DELAY`] and
+    /// [`DUPLICATE_WINDOW`].
+    ///
+    /// # Arguments
+    ///
+    /// * `link_type`: The link type of all interfaces.
+    pub fn new(link_type: LinkType) -> Self {
+        Merger::with_delay(link_type, MERGE_DELAY, DUPLICATE_WINDOW)
+    }
+
+    /// Create a merger with a custom delay and duplicate window.
+    ///
+    /// # Arguments
+    ///
+    /// * `link_type`: The link type of all interfaces.
+    /// * `delay`: How long packets are held back. This must be longer than the time between a
+    ///   frame being captured on different interfaces to order and deduplicate it correctly.
+    /// * `window`: How far apart the same frame may be seen to count as a duplicate.
+    pub fn with_delay(link_type: LinkType, delay: Duration, window: Duration) -> Self {
+        Merger {
+            link_type,
+            delay: chrono::Duration::from_std(delay).unwrap_or_default(),
+            window: chrono::Duration::from_std(window).unwrap_or_default(),
+            pending: BinaryHeap::new(),
+            released: VecDeque::new(),
+            sequence: 0,
+            duplicates: 0,
+        }
+    }
+
+    /// Add a packet that was captured on an interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface`: The index of the interface that captured the packet.
+    /// * `packet`: The captured packet.
+    pub fn push(&mut self, interface: usize, packet: &pcap::Packet) {
+        self.sequence += 1;
+        self.pending.push(Reverse(Pending {
+            timestamp: packet::packet_timestamp(packet.header),
+            sequence: self.sequence,
+            packet: MergedPacket {
+                interface,
+                header: *packet.header,
+                data: packet.data.to_vec(),
+            },
+        }));
+    }
+
+    /// Release the next packet that was captured at least the delay before `now`.
+    ///
+    /// Returns `None` if no packet is ready yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: The current time.
+    pub fn pop(&mut self, now: DateTime<Utc>) -> Option<MergedPacket> {
+        self.pop_until(now - self.delay)
+    }
+
+    /// Release the next packet regardless of the delay, e.g. once capturing stopped.
+    pub fn pop_any(&mut self) -> Option<MergedPacket> {
+        self.pop_until(DateTime::<Utc>::MAX_UTC)
+    }
+
+    fn pop_until(&mut self, until: DateTime<Utc>) -> Option<MergedPacket> {
+        while self
+            .pending
+            .peek()
+            .is_some_and(|Reverse(pending)| pending.timestamp <= until)
+        {
+            let Reverse(pending) = self.pending.pop()?;
+            if !self.is_duplicate(&pending) {
+                return Some(pending.packet);
+            }
+
+            self.duplicates += 1;
+        }
+
+        None
+    }
+
+    /// Whether the same frame was already released from another interface, remembering it if not.
+    fn is_duplicate(&mut self, pending: &Pending) -> bool {
+        while self
+            .released
+            .front()
+            .is_some_and(|(timestamp, _, _)| pending.timestamp - *timestamp > self.window)
+        {
+            self.released.pop_front();
+        }
+
+        let data = &pending.packet.data;
+        let payload = self
+            .link_type
+            .decode(data)
+            .and_then(|header| data.get(header.payload_offset..))
+            .filter(|payload| !payload.is_empty())
+            .unwrap_or(data);
+        let mut hasher = DefaultHasher::new();
+        payload.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let interface = pending.packet.interface;
+        if self
+            .released
+            .iter()
+            .any(|(_, released, other)| *released == hash && *other != interface)
+        {
+            return true;
+        }
+
+        self.released
+            .push_back((pending.timestamp, hash, interface));
+
+        false
+    }
+}
@@ -1,15 +1,21 @@
 use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::net::IpAddr;
 use std::path::Path;
 use std::time;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use log::trace;
-use pcap::Capture;
-use pnet::packet::ethernet::EthernetPacket;
+use pcap::{Capture, Offline};
 
-use crate::address::MacAddress;
+use crate::address::{DeviceAddress, MacAddress};
+use crate::compress;
+use crate::compress::Decompressed;
 use crate::error::Error;
+use crate::link::LinkType;
+use crate::protocol;
+use crate::protocol::DecodedPacket;
 
 #[derive(Copy, Clone, Debug)]
 pub enum PacketDirection {
@@ -35,13 +41,84 @@ impl From<PacketDirection> for f32 {
     }
 }
 
+/// Information shared by all representations of a captured packet.
+///
+/// This is implemented by [`Packet`], which holds the full captured data, and by the lightweight
+/// [`PacketHeader`], which does not copy the payload.
+pub trait CapturedPacket {
+    /// When the packet was captured.
+    fn timestamp(&self) -> DateTime<Utc>;
+
+    /// The length of the packet, read from the packet header.
+    fn length(&self) -> usize;
+
+    /// The MAC source address of the packet or `None` if its link type does not carry it.
+    fn source(&self) -> Option<MacAddress>;
+
+    /// The MAC destination address of the packet or `None` if its link type does not carry it.
+    fn destination(&self) -> Option<MacAddress>;
+
+    /// The IP source address of the packet or `None` if it is not an IP packet.
+    fn source_ip(&self) -> Option<IpAddr>;
+
+    /// The IP destination address of the packet or `None` if it is not an IP packet.
+    fn destination_ip(&self) -> Option<IpAddr>;
+
+    /// The direction of the packet as seen from the device with the given address.
+    ///
+    /// The direction is derived from the MAC addresses of the packet. If it does not carry both,
+    /// e.g. because it was captured on a tun device or the Linux `any` device, the IP address of
+    /// the device is used instead.
+    ///
+    /// Returns `None` if the packet was neither sent nor received by that device.
+    ///
+    /// # Arguments
+    ///
+    /// * `relative_to`: The address of the device to get the direction relative to.
+    fn direction(&self, relative_to: &DeviceAddress) -> Option<PacketDirection> {
+        let (source, destination) = (self.source(), self.destination());
+        if source == Some(relative_to.mac) {
+            return Some(PacketDirection::Out);
+        } else if destination == Some(relative_to.mac) {
+            return Some(PacketDirection::In);
+        } else if source.is_some() && destination.is_some() {
+            return None;
+        }
+
+        let ip = relative_to.ip?;
+        if self.source_ip() == Some(ip) {
+            Some(PacketDirection::Out)
+        } else if self.destination_ip() == Some(ip) {
+            Some(PacketDirection::In)
+        } else {
+            None
+        }
+    }
+}
+
+/// Conversion from a packet read from a capture with a known link type.
+///
+/// This is implemented by all packet representations that [`CaptureReader`] can yield.
+pub trait FromCapture {
+    /// Convert a pcap packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The packet as read by pcap.
+    /// * `link_type`: The link type of the capture the packet was read from.
+    fn from_capture(packet: pcap::Packet, link_type: LinkType) -> Self;
+}
+
 /// A sniffer packet contains all packet information for one captured pcap packet.
+#[derive(Clone)]
 pub struct Packet {
     pub timestamp: DateTime<Utc>,
     /// The length of the packet, read from the packet header.
     ///
     /// In rare cases, this might be more than the amount of data captured.
     pub len: usize,
+    /// The link layer of the captured data.
+    pub link_type: LinkType,
     pub data: Vec<u8>,
 }
 
@@ -53,16 +130,63 @@ impl Packet {
         self.data.len()
     }
 
-    pub fn direction(&self, relative_to: &MacAddress) -> Option<PacketDirection> {
-        EthernetPacket::new(&self.data).and_then(|packet| {
-            if MacAddress::from(packet.get_source()) == *relative_to {
-                Some(PacketDirection::Out)
-            } else if MacAddress::from(packet.get_destination()) == *relative_to {
-                Some(PacketDirection::In)
-            } else {
-                None
-            }
-        })
+    /// Decode the captured data up to the transport layer.
+    ///
+    /// Returns an error if the headers are malformed or were not captured completely.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chrono::Utc;
+    /// # use varys_network::link::LinkType;
+    /// # use varys_network::packet::Packet;
+    /// let mut data = vec![0; 54];
+    /// data[12..14].copy_from_slice(&[0x08, 0x00]); // IPv4
+    /// data[14] = 0x45; // version 4, header length 20 bytes
+    /// data[16..18].copy_from_slice(&40_u16.to_be_bytes()); // total length
+    /// data[23] = 6; // TCP
+    /// data[46] = 0x50; // data offset 20 bytes
+    /// data[47] = 0x10; // ACK
+    /// let packet = Packet {
+    ///     timestamp: Utc::now(),
+    ///     len: data.len(),
+    ///     link_type: LinkType::Ethernet,
+    ///     data,
+    /// };
+    ///
+    /// let decoded = packet.decode().unwrap();
+    /// assert_eq!(decoded.payload_len, 0);
+    /// assert!(decoded.is_ack_only());
+    /// assert!(decoded.is_noise());
+    /// ```
+    pub fn decode(&self) -> Result<DecodedPacket, Error> {
+        DecodedPacket::decode(&self.data, self.link_type)
+    }
+}
+
+impl CapturedPacket for Packet {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn length(&self) -> usize {
+        self.len
+    }
+
+    fn source(&self) -> Option<MacAddress> {
+        self.link_type.decode(&self.data)?.source
+    }
+
+    fn destination(&self) -> Option<MacAddress> {
+        self.link_type.decode(&self.data)?.destination
+    }
+
+    fn source_ip(&self) -> Option<IpAddr> {
+        protocol::ip_addresses(&self.data, self.link_type).map(|(source, _)| source)
+    }
+
+    fn destination_ip(&self) -> Option<IpAddr> {
+        protocol::ip_addresses(&self.data, self.link_type).map(|(_, destination)| destination)
     }
 }
 
@@ -82,40 +206,205 @@ impl Display for Packet {
     }
 }
 
-impl From<pcap::Packet<'_>> for Packet {
-    fn from(packet: pcap::Packet) -> Self {
-        let timestamp = packet.header.ts;
-        let s = timestamp.tv_sec as u64;
-        let us = u64::try_from(timestamp.tv_usec as i64).unwrap_or(0); // tv_usec might be negative for dates before 1970, ignore those
-        let timestamp =
-            DateTime::from(time::UNIX_EPOCH + Duration::from_secs(s) + Duration::from_micros(us));
-
+impl FromCapture for Packet {
+    fn from_capture(packet: pcap::Packet, link_type: LinkType) -> Self {
         Packet {
-            timestamp,
+            timestamp: packet_timestamp(packet.header),
             len: packet.header.len as usize,
+            link_type,
             data: packet.data.into(),
         }
     }
 }
 
-/// Load all packets from a pcap file.
+/// The header information of one captured pcap packet.
+///
+/// Unlike [`Packet`], this does not hold a copy of the captured data. Only the MAC and IP
+/// addresses are read from it, which makes it cheap to keep many of them in memory.
+#[derive(Copy, Clone, Debug)]
+pub struct PacketHeader {
+    pub timestamp: DateTime<Utc>,
+    /// The length of the packet, read from the packet header.
+    pub len: usize,
+    /// The MAC source address or `None` if the link type does not carry it.
+    pub source: Option<MacAddress>,
+    /// The MAC destination address or `None` if the link type does not carry it.
+    pub destination: Option<MacAddress>,
+    /// The IP source address or `None` if the packet is not an IP packet.
+    pub source_ip: Option<IpAddr>,
+    /// The IP destination address or `None` if the packet is not an IP packet.
+    pub destination_ip: Option<IpAddr>,
+}
+
+impl CapturedPacket for PacketHeader {
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn length(&self) -> usize {
+        self.len
+    }
+
+    fn source(&self) -> Option<MacAddress> {
+        self.source
+    }
+
+    fn destination(&self) -> Option<MacAddress> {
+        self.destination
+    }
+
+    fn source_ip(&self) -> Option<IpAddr> {
+        self.source_ip
+    }
+
+    fn destination_ip(&self) -> Option<IpAddr> {
+        self.destination_ip
+    }
+}
+
+impl FromCapture for PacketHeader {
+    fn from_capture(packet: pcap::Packet, link_type: LinkType) -> Self {
+        let link = link_type.decode(packet.data);
+        let ip = protocol::ip_addresses(packet.data, link_type);
+
+        PacketHeader {
+            timestamp: packet_timestamp(packet.header),
+            len: packet.header.len as usize,
+            source: link.and_then(|link| link.source),
+            destination: link.and_then(|link| link.destination),
+            source_ip: ip.map(|(source, _)| source),
+            destination_ip: ip.map(|(_, destination)| destination),
+        }
+    }
+}
+
+/// Reads packets from a pcap file one at a time instead of loading the whole file into memory.
+///
+/// Depending on `T`, the reader yields full [`Packet`]s or payload-less [`PacketHeader`]s. Use
+/// [`read_packets`] or [`read_headers`] to create one.
+pub struct CaptureReader<T> {
+    capture: Capture<Offline>,
+    link_type: LinkType,
+    packet_type: PhantomData<T>,
+    /// The decompressed copy of the capture, removed once the reader is dropped.
+    _decompressed: Option<Decompressed>,
+}
+
+impl<T> CaptureReader<T> {
+    /// Open a pcap file for reading.
+    ///
+    /// Files compressed with zstd are decompressed to a temporary file first, see
+    /// [`crate::compress`].
+    ///
+    /// Returns an error if the file could not be opened or is not a valid capture.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to the pcap file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        trace!("Reading packets from {}...", path.as_ref().display());
+
+        let (capture, decompressed) = compress::open_capture(path)?;
+        let link_type = capture.get_datalink().into();
+
+        Ok(CaptureReader {
+            capture,
+            link_type,
+            packet_type: PhantomData,
+            _decompressed: decompressed,
+        })
+    }
+
+    /// The link type of the capture, which all packets read from it share.
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+}
+
+impl<T: FromCapture> Iterator for CaptureReader<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.capture.next_packet() {
+            Ok(pcap_packet) => Some(Ok(T::from_capture(pcap_packet, self.link_type))),
+            Err(pcap::Error::NoMorePackets) => None,
+            Err(error) => Some(Err(Error::from(error))),
+        }
+    }
+}
+
+/// Load all packets from a pcap file, which may be compressed with zstd.
+///
+/// Each packet carries the link type of the capture, so it is decoded correctly regardless of
+/// whether it was captured on Ethernet, the Linux `any` device, a tun device or in monitor mode.
+///
+/// This keeps the data of every packet in memory. To process large captures, prefer iterating over
+/// [`read_packets`] or [`read_headers`].
 ///
 /// # Arguments
 ///
 /// * `path`: The path to the pcap file.
 pub fn load_packets<P: AsRef<Path>>(path: P) -> Result<Vec<Packet>, Error> {
-    trace!("Loading packets from {}...", path.as_ref().display());
+    read_packets(path)?.collect()
+}
+
+/// Iterate over all packets in a pcap file.
+///
+/// # Arguments
+///
+/// * `path`: The path to the pcap file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::packet;
+/// let total_captured: usize = packet::read_packets("capture.pcap")
+///     .unwrap()
+///     .filter_map(Result::ok)
+///     .map(|packet| packet.captured_len())
+///     .sum();
+/// ```
+pub fn read_packets<P: AsRef<Path>>(path: P) -> Result<CaptureReader<Packet>, Error> {
+    CaptureReader::open(path)
+}
+
+/// Iterate over the headers of all packets in a pcap file without copying their payloads.
+///
+/// # Arguments
+///
+/// * `path`: The path to the pcap file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::packet;
+/// let headers = packet::read_headers("capture.pcap")
+///     .unwrap()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// ```
+pub fn read_headers<P: AsRef<Path>>(path: P) -> Result<CaptureReader<PacketHeader>, Error> {
+    CaptureReader::open(path)
+}
 
-    let mut capture = Capture::from_file(path)?;
-    let mut packets = Vec::new();
+/// Create the libpcap header of a complete frame that was captured at `timestamp`.
+pub(crate) fn packet_header(timestamp: DateTime<Utc>, len: usize) -> pcap::PacketHeader {
+    let len = u32::try_from(len).unwrap_or(u32::MAX);
+    // SAFETY: the header only consists of integers, for which all zero bytes are a valid value
+    let mut header: pcap::PacketHeader = unsafe { std::mem::zeroed() };
+    // the types of the timestamp fields depend on the platform
+    header.ts.tv_sec = timestamp.timestamp() as _;
+    header.ts.tv_usec = timestamp.timestamp_subsec_micros() as _;
+    header.caplen = len;
+    header.len = len;
 
-    loop {
-        match capture.next_packet() {
-            Ok(pcap_packet) => packets.push(Packet::from(pcap_packet)),
-            Err(pcap::Error::NoMorePackets) => break,
-            Err(error) => return Err(Error::from(error)),
-        }
-    }
+    header
+}
+
+pub(crate) fn packet_timestamp(header: &pcap::PacketHeader) -> DateTime<Utc> {
+    let timestamp = header.ts;
+    let s = timestamp.tv_sec as u64;
+    let us = u64::try_from(timestamp.tv_usec).unwrap_or(0); // tv_usec might be negative for dates before 1970, ignore those
 
-    Ok(packets)
+    DateTime::from(time::UNIX_EPOCH + Duration::from_secs(s) + Duration::from_micros(us))
 }
@@ -0,0 +1,641 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use pcap::Linktype;
+
+use crate::compress;
+use crate::error::Error;
+use crate::packet;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d_0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const OPTION_END: u16 = 0;
+const OPTION_COMMENT: u16 = 1;
+const OPTION_INTERFACE_NAME: u16 = 2;
+const OPTION_USER_APPLICATION: u16 = 4;
+const OPTION_TIMESTAMP_RESOLUTION: u16 = 9;
+/// Timestamps are written in microseconds, the default resolution of pcapng.
+const DEFAULT_TIMESTAMP_RESOLUTION: u8 = 6;
+/// Blocks larger than this are rejected instead of allocating memory for them.
+const MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+/// A comment attached to the first packet captured at or after a point in time, e.g. when the
+/// query started.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mark {
+    pub timestamp: DateTime<Utc>,
+    pub comment: String,
+}
+
+impl Mark {
+    pub fn new(timestamp: DateTime<Utc>, comment: &str) -> Self {
+        Mark {
+            timestamp,
+            comment: comment.to_string(),
+        }
+    }
+}
+
+/// Metadata stored in a pcapng file alongside its packets.
+///
+/// The fields are written as `key: value` comments of the section header, so they are shown by
+/// tools like Wireshark. When reading a file, the timestamp of each mark is the timestamp of the
+/// packet it was attached to.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Annotations {
+    /// The name and version of the application that wrote the file.
+    pub application: Option<String>,
+    /// Key-value pairs describing the capture, e.g. the interaction it belongs to, in the order
+    /// they were added.
+    pub fields: Vec<(String, String)>,
+    /// The comments to attach to packets, which are written in the order of their timestamps.
+    ///
+    /// Marks after the last packet cannot be stored and are dropped.
+    pub marks: Vec<Mark>,
+    /// The names of the interfaces the packets were captured on.
+    ///
+    /// If this is empty, a single interface without a name is written. Interfaces without a name
+    /// are read as empty strings.
+    pub interfaces: Vec<String>,
+}
+
+impl Annotations {
+    /// Add a field.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The name of the field, which should not contain `: `.
+    /// * `value`: The value of the field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_network::pcapng::Annotations;
+    /// let annotations = Annotations::default()
+    ///     .with("interaction", 42)
+    ///     .with("query", "What time is it?");
+    ///
+    /// assert_eq!(annotations.field("interaction"), Some("42"));
+    /// assert_eq!(annotations.field("category"), None);
+    /// ```
+    pub fn with<T: ToString>(mut self, key: &str, value: T) -> Self {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Get the value of the first field with the given key.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether there is nothing to store.
+    pub fn is_empty(&self) -> bool {
+        self.application.is_none()
+            && self.fields.is_empty()
+            && self.marks.is_empty()
+            && self.interfaces.is_empty()
+    }
+}
+
+/// Writes packets to a pcapng file with a single interface.
+///
+/// Marks are attached as comments to the first packet written at or after their timestamp, so
+/// packets should be written in the order they were captured.
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+    marks: VecDeque<Mark>,
+    interfaces: usize,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Start a pcapng file by writing its section header and interface description.
+    ///
+    /// Returns an error if the headers could not be written.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer`: Where to write the file to.
+    /// * `linktype`: The link type of the packets that will be written.
+    /// * `annotations`: The metadata to store in the section header and the marks to attach to
+    ///   packets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chrono::Utc;
+    /// # use pcap::Linktype;
+    /// # use varys_network::pcapng::{self, Annotations, Mark, PcapngWriter};
+    /// let annotations = Annotations {
+    ///     application: Some("varys".to_string()),
+    ///     marks: vec![Mark::new(Utc::now(), "query started")],
+    ///     ..Annotations::default()
+    /// }
+    /// .with("interaction", 42);
+    ///
+    /// let writer = PcapngWriter::new(Vec::new(), Linktype::ETHERNET, &annotations).unwrap();
+    /// let file = writer.finish().unwrap();
+    ///
+    /// let read = pcapng::read_annotations_from(file.as_slice()).unwrap();
+    /// assert_eq!(read.application.as_deref(), Some("varys"));
+    /// assert_eq!(read.field("interaction"), Some("42"));
+    /// // there was no packet to attach the mark to
+    /// assert!(read.marks.is_empty());
+    /// ```
+    pub fn new(
+        mut writer: W,
+        linktype: Linktype,
+        annotations: &Annotations,
+    ) -> Result<Self, Error> {
+        let mut options = Vec::new();
+        if let Some(application) = &annotations.application {
+            write_option(
+                &mut options,
+                OPTION_USER_APPLICATION,
+                application.as_bytes(),
+            );
+        }
+        for (key, value) in &annotations.fields {
+            write_option(
+                &mut options,
+                OPTION_COMMENT,
+                format!("{key}: {value}").as_bytes(),
+            );
+        }
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1_u16.to_le_bytes());
+        body.extend_from_slice(&0_u16.to_le_bytes());
+        // the length of the section is unknown
+        body.extend_from_slice(&(-1_i64).to_le_bytes());
+        end_options(&mut body, options);
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &body)?;
+
+        let unnamed = [String::new()];
+        let interfaces = match annotations.interfaces.as_slice() {
+            [] => &unnamed,
+            interfaces => interfaces,
+        };
+        for name in interfaces {
+            let mut body = Vec::new();
+            body.extend_from_slice(&u16::try_from(linktype.0).unwrap_or_default().to_le_bytes());
+            body.extend_from_slice(&0_u16.to_le_bytes());
+            // no snapshot length limit
+            body.extend_from_slice(&0_u32.to_le_bytes());
+            if !name.is_empty() {
+                let mut options = Vec::new();
+                write_option(&mut options, OPTION_INTERFACE_NAME, name.as_bytes());
+                end_options(&mut body, options);
+            }
+            write_block(&mut writer, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+        }
+
+        let mut marks = annotations.marks.clone();
+        marks.sort_by_key(|mark| mark.timestamp);
+
+        Ok(PcapngWriter {
+            writer,
+            marks: marks.into(),
+            interfaces: interfaces.len(),
+        })
+    }
+
+    /// Write a packet, attaching all marks up to its timestamp as comments.
+    ///
+    /// Returns an error if the packet could not be written.
+    pub fn write(&mut self, packet: &pcap::Packet) -> Result<(), Error> {
+        self.write_on(packet, 0)
+    }
+
+    /// Write a packet that was captured on one of the [`Annotations::interfaces`].
+    ///
+    /// Returns an error if the packet could not be written or the interface does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The packet to write.
+    /// * `interface`: The index of the interface that captured the packet.
+    pub fn write_on(&mut self, packet: &pcap::Packet, interface: usize) -> Result<(), Error> {
+        self.write_received(packet, packet::packet_timestamp(packet.header), interface)
+    }
+
+    /// Write a packet, attaching all marks up to the time it was received as comments.
+    ///
+    /// This differs from [`PcapngWriter::write`] for replayed packets, whose timestamps are the
+    /// recorded ones.
+    pub(crate) fn write_received(
+        &mut self,
+        packet: &pcap::Packet,
+        received: DateTime<Utc>,
+        interface: usize,
+    ) -> Result<(), Error> {
+        if interface >= self.interfaces {
+            return Err(Error::InvalidPcapng(
+                "packet refers to an unknown interface",
+            ));
+        }
+        let timestamp = packet::packet_timestamp(packet.header);
+        let micros = timestamp.timestamp_micros() as u64;
+        let captured_len = packet.data.len();
+
+        let mut body = Vec::with_capacity(20 + captured_len + 4);
+        body.extend_from_slice(&(interface as u32).to_le_bytes());
+        body.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(micros as u32).to_le_bytes());
+        body.extend_from_slice(&(captured_len as u32).to_le_bytes());
+        body.extend_from_slice(&packet.header.len.to_le_bytes());
+        body.extend_from_slice(packet.data);
+        pad(&mut body);
+
+        let mut options = Vec::new();
+        while self
+            .marks
+            .front()
+            .is_some_and(|mark| mark.timestamp <= received)
+        {
+            if let Some(mark) = self.marks.pop_front() {
+                write_option(&mut options, OPTION_COMMENT, mark.comment.as_bytes());
+            }
+        }
+        if !options.is_empty() {
+            end_options(&mut body, options);
+        }
+
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    /// Flush the file and return the underlying writer.
+    ///
+    /// Marks after the last packet cannot be stored and are dropped.
+    pub fn finish(mut self) -> Result<W, Error> {
+        for mark in &self.marks {
+            debug!(
+                "Dropping mark \"{}\" because no packet was captured after it",
+                mark.comment
+            );
+        }
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Create a pcapng file.
+///
+/// See [`PcapngWriter::new`] for details.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file to create.
+/// * `linktype`: The link type of the packets that will be written.
+/// * `annotations`: The metadata to store in the file.
+pub fn create<P: AsRef<Path>>(
+    path: P,
+    linktype: Linktype,
+    annotations: &Annotations,
+) -> Result<PcapngWriter<BufWriter<File>>, Error> {
+    PcapngWriter::new(BufWriter::new(File::create(path)?), linktype, annotations)
+}
+
+/// Convert a pcap file to a pcapng file with the given annotations.
+///
+/// `from` and `to` may be the same path, in which case the file is replaced.
+///
+/// Returns the number of packets written or an error if the file could not be read or written.
+///
+/// # Arguments
+///
+/// * `from`: The pcap file to convert, which may be compressed.
+/// * `to`: Where to write the pcapng file.
+/// * `annotations`: The metadata to store in the file.
+pub fn convert(from: &Path, to: &Path, annotations: &Annotations) -> Result<usize, Error> {
+    convert_interfaces(from, to, annotations, &[])
+}
+
+/// Convert a pcap file to a pcapng file, storing which interface captured each packet.
+///
+/// `interface_ids` holds the index into [`Annotations::interfaces`] of each packet in the order
+/// they were written to `from`. Packets without an index are stored on the first interface.
+pub(crate) fn convert_interfaces(
+    from: &Path,
+    to: &Path,
+    annotations: &Annotations,
+    interface_ids: &[usize],
+) -> Result<usize, Error> {
+    info!("Converting {from:?} to pcapng at {to:?}...");
+
+    let (mut capture, _decompressed) = compress::open_capture(from)?;
+    // write to a temporary file first in case the capture is converted in place
+    let temporary_path = to.with_extension("converting.pcapng");
+    let mut writer = create(&temporary_path, capture.get_datalink(), annotations)?;
+    let mut written = 0;
+
+    loop {
+        match capture.next_packet() {
+            Ok(packet) => {
+                let interface = interface_ids.get(written).copied().unwrap_or_default();
+                writer.write_on(&packet, interface)?
+            }
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(error) => return Err(error.into()),
+        }
+        written += 1;
+    }
+    writer.finish()?;
+    fs::rename(&temporary_path, to)?;
+
+    Ok(written)
+}
+
+/// Whether a file is a pcapng file, as opposed to a pcap file.
+///
+/// Files compressed with zstd are checked after decompression.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file.
+pub fn is_pcapng<P: AsRef<Path>>(path: P) -> Result<bool, Error> {
+    let mut block_type = [0; 4];
+
+    Ok(open(path.as_ref())?.read_exact(&mut block_type).is_ok()
+        && u32::from_le_bytes(block_type) == SECTION_HEADER_BLOCK)
+}
+
+/// Read the annotations of a pcapng file, which may be compressed with zstd.
+///
+/// Returns an error if the file could not be read or is not a valid pcapng file.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::pcapng;
+/// let annotations = pcapng::read_annotations("capture.pcapng").unwrap();
+/// for mark in annotations.marks {
+///     println!("{}: {}", mark.timestamp, mark.comment);
+/// }
+/// ```
+pub fn read_annotations<P: AsRef<Path>>(path: P) -> Result<Annotations, Error> {
+    read_annotations_from(open(path.as_ref())?)
+}
+
+/// Read the annotations of a pcapng file from a reader.
+///
+/// See [`read_annotations`] for details.
+pub fn read_annotations_from<R: Read>(reader: R) -> Result<Annotations, Error> {
+    Ok(read_from(reader)?.0)
+}
+
+/// Read which interface captured each packet of a pcapng file, which may be compressed with zstd.
+///
+/// Returns the index into the [`Annotations::interfaces`] of each packet in the order they are
+/// stored, or an error if the file could not be read or is not a valid pcapng file.
+///
+/// # Arguments
+///
+/// * `path`: The path to the file.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use varys_network::{packet, pcapng};
+/// let path = "capture.pcapng";
+/// let interfaces = pcapng::read_annotations(path).unwrap().interfaces;
+/// let interface_ids = pcapng::read_packet_interfaces(path).unwrap();
+///
+/// for (packet, interface) in packet::read_packets(path).unwrap().zip(interface_ids) {
+///     println!("{} on {}", packet.unwrap(), interfaces[interface]);
+/// }
+/// ```
+pub fn read_packet_interfaces<P: AsRef<Path>>(path: P) -> Result<Vec<usize>, Error> {
+    Ok(read_from(open(path.as_ref())?)?.1)
+}
+
+/// Read the annotations and the interface of each packet of a pcapng file.
+fn read_from<R: Read>(mut reader: R) -> Result<(Annotations, Vec<usize>), Error> {
+    let mut annotations = Annotations::default();
+    let mut interface_ids = Vec::new();
+    let mut in_section = false;
+    let mut big_endian = false;
+    let mut resolutions = Vec::new();
+    // interface indices restart in every section
+    let mut first_interface = 0;
+
+    loop {
+        let mut header = [0; 8];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error.into()),
+        }
+        // the type of a section header reads the same in both byte orders
+        let is_section_header = u32::from_le_bytes([header[0], header[1], header[2], header[3]])
+            == SECTION_HEADER_BLOCK;
+        if is_section_header {
+            // the byte order of a section is only known from its header
+            let mut magic = [0; 4];
+            reader.read_exact(&mut magic)?;
+            big_endian = match u32::from_le_bytes(magic) {
+                BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => return Err(Error::InvalidPcapng("unknown byte order")),
+            };
+            in_section = true;
+            resolutions.clear();
+            first_interface = annotations.interfaces.len();
+        } else if !in_section {
+            return Err(Error::InvalidPcapng(
+                "file does not start with a section header",
+            ));
+        }
+
+        let read = Reader { big_endian };
+        let block_type = read.u32(&header[0..4]);
+        let block_len = read.u32(&header[4..8]) as usize;
+        let header_len = if is_section_header { 12 } else { 8 };
+        if block_len < header_len + 4 || !block_len.is_multiple_of(4) || block_len > MAX_BLOCK_LEN {
+            return Err(Error::InvalidPcapng("invalid block length"));
+        }
+        let mut body = vec![0; block_len - header_len];
+        reader.read_exact(&mut body)?;
+        // the body is followed by the block length again
+        let body = &body[..body.len() - 4];
+
+        match block_type {
+            SECTION_HEADER_BLOCK => {
+                for (code, value) in read.options(body.get(12..).unwrap_or_default()) {
+                    let value = String::from_utf8_lossy(value).to_string();
+                    match code {
+                        OPTION_USER_APPLICATION => annotations.application = Some(value),
+                        OPTION_COMMENT => {
+                            if let Some((key, value)) = value.split_once(": ") {
+                                annotations
+                                    .fields
+                                    .push((key.to_string(), value.to_string()));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            INTERFACE_DESCRIPTION_BLOCK => {
+                let mut resolution = DEFAULT_TIMESTAMP_RESOLUTION;
+                let mut name = String::new();
+                for (code, value) in read.options(body.get(8..).unwrap_or_default()) {
+                    match code {
+                        OPTION_TIMESTAMP_RESOLUTION if value.len() == 1 => resolution = value[0],
+                        OPTION_INTERFACE_NAME => name = String::from_utf8_lossy(value).to_string(),
+                        _ => {}
+                    }
+                }
+                resolutions.push(resolution);
+                annotations.interfaces.push(name);
+            }
+            ENHANCED_PACKET_BLOCK => {
+                let fields = body
+                    .get(..20)
+                    .ok_or(Error::InvalidPcapng("packet block is too short"))?;
+                let interface = read.u32(&fields[0..4]) as usize;
+                let units =
+                    (read.u32(&fields[4..8]) as u64) << 32 | read.u32(&fields[8..12]) as u64;
+                let captured_len = read.u32(&fields[12..16]) as usize;
+                let options_offset = 20 + captured_len.next_multiple_of(4);
+                let resolution = *resolutions.get(interface).ok_or(Error::InvalidPcapng(
+                    "packet refers to an unknown interface",
+                ))?;
+                interface_ids.push(first_interface + interface);
+
+                for (code, value) in read.options(body.get(options_offset..).unwrap_or_default()) {
+                    if code == OPTION_COMMENT {
+                        annotations.marks.push(Mark {
+                            timestamp: timestamp(units, resolution),
+                            comment: String::from_utf8_lossy(value).to_string(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((annotations, interface_ids))
+}
+
+/// Open a file for reading, decompressing it on the fly if it is compressed.
+fn open(path: &Path) -> Result<Box<dyn Read>, Error> {
+    let file = BufReader::new(File::open(path)?);
+
+    Ok(if compress::is_compressed(path)? {
+        Box::new(zstd::Decoder::with_buffer(file)?)
+    } else {
+        Box::new(file)
+    })
+}
+
+/// Convert a pcapng timestamp to a date, given the resolution of its interface.
+fn timestamp(units: u64, resolution: u8) -> DateTime<Utc> {
+    let exponent = (resolution & 0x7f) as u32;
+    let units_per_second: u128 = if resolution & 0x80 == 0 {
+        10_u128.pow(exponent.min(19))
+    } else {
+        1_u128 << exponent.min(64)
+    };
+    let seconds = units as u128 / units_per_second;
+    let nanos = (units as u128 % units_per_second) * 1_000_000_000 / units_per_second;
+
+    DateTime::from_timestamp(seconds as i64, nanos as u32).unwrap_or_else(|| {
+        warn!("Ignoring invalid pcapng timestamp {units}");
+        DateTime::UNIX_EPOCH
+    })
+}
+
+/// Reads numbers and options in the byte order of a section.
+#[derive(Copy, Clone)]
+struct Reader {
+    big_endian: bool,
+}
+
+impl Reader {
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Iterate over the options of a block until the end of options or the block.
+    fn options<'a>(&self, mut options: &'a [u8]) -> impl Iterator<Item = (u16, &'a [u8])> {
+        let read = *self;
+
+        std::iter::from_fn(move || {
+            let code = read.u16(options.get(0..2)?);
+            let len = read.u16(options.get(2..4)?) as usize;
+            if code == OPTION_END {
+                return None;
+            }
+            let value = options.get(4..4 + len)?;
+            options = options
+                .get(4 + len.next_multiple_of(4)..)
+                .unwrap_or_default();
+
+            Some((code, value))
+        })
+    }
+}
+
+fn write_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> Result<(), Error> {
+    let block_len = (body.len() + 12) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&block_len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&block_len.to_le_bytes())?;
+
+    Ok(())
+}
+
+fn write_option(options: &mut Vec<u8>, code: u16, value: &[u8]) {
+    // option values are limited to 65535 bytes
+    let value = &value[..value.len().min(u16::MAX as usize)];
+    options.extend_from_slice(&code.to_le_bytes());
+    options.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    options.extend_from_slice(value);
+    pad(options);
+}
+
+/// Append the options and the end of options marker to a block body.
+fn end_options(body: &mut Vec<u8>, options: Vec<u8>) {
+    if options.is_empty() {
+        return;
+    }
+    body.extend(options);
+    body.extend_from_slice(&OPTION_END.to_le_bytes());
+    body.extend_from_slice(&0_u16.to_le_bytes());
+}
+
+fn pad(data: &mut Vec<u8>) {
+    data.resize(data.len().next_multiple_of(4), 0);
+}
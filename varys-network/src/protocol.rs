@@ -0,0 +1,480 @@
+use std::cmp::min;
+use std::net::IpAddr;
+
+use pnet::packet::ethernet::{EtherType, EtherTypes};
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv6::Ipv6Packet;
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::udp::UdpPacket;
+use pnet::packet::Packet as _;
+
+use crate::address::MacAddress;
+use crate::error::Error;
+use crate::link::LinkType;
+
+const MDNS_PORT: u16 = 5353;
+const QUIC_PORT: u16 = 443;
+
+/// A captured frame decoded up to its transport layer.
+///
+/// Use [`crate::packet::Packet::decode`] to decode a captured packet.
+#[derive(Clone, Debug)]
+pub struct DecodedPacket {
+    /// The MAC source address or `None` if the link type does not carry it.
+    pub source: Option<MacAddress>,
+    /// The MAC destination address or `None` if the link type does not carry it.
+    pub destination: Option<MacAddress>,
+    /// The protocol of the link layer payload.
+    pub ether_type: EtherType,
+    /// The IP header or `None` if this is not an IP packet.
+    pub ip: Option<IpHeader>,
+    /// The transport header or `None` if this is not a TCP or UDP packet.
+    pub transport: Option<TransportHeader>,
+    /// The length of the application payload in bytes, without any of the decoded headers.
+    ///
+    /// This is calculated from the length fields in the headers, so it is correct even if the
+    /// payload itself was not captured completely.
+    pub payload_len: usize,
+    /// The offset of the application payload from the start of the captured frame.
+    pub payload_offset: usize,
+}
+
+impl DecodedPacket {
+    /// Decode a captured frame of the given link type up to its transport layer.
+    ///
+    /// Returns an error if the link type is unsupported, the headers are malformed or they were
+    /// not captured completely.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The captured frame.
+    /// * `link_type`: The link type of the capture the frame was read from.
+    pub fn decode(data: &[u8], link_type: LinkType) -> Result<Self, Error> {
+        let link = link_type.decode(data).ok_or(Error::MalformedPacket(
+            "frame is too short for its link layer header or the link type is unsupported",
+        ))?;
+        let ether_type = link
+            .ether_type
+            .ok_or(Error::MalformedPacket("protocol of the frame is unknown"))?;
+        let header_len = link.payload_offset;
+        let payload = data.get(header_len..).unwrap_or_default();
+        let (ip, transport, payload_offset, payload_len) = match ether_type {
+            EtherTypes::Ipv4 | EtherTypes::Ipv6 => {
+                let decoded = decode_ip(payload)?;
+                (
+                    Some(decoded.ip),
+                    decoded.transport,
+                    header_len + decoded.payload_offset,
+                    decoded.payload_len,
+                )
+            }
+            _ => (None, None, header_len, payload.len()),
+        };
+
+        Ok(DecodedPacket {
+            source: link.source,
+            destination: link.destination,
+            ether_type,
+            ip,
+            transport,
+            payload_len,
+            payload_offset,
+        })
+    }
+
+    /// Get the captured part of the application payload from the frame this packet was decoded
+    /// from.
+    ///
+    /// This might be shorter than [`DecodedPacket::payload_len`] if the payload was truncated
+    /// during capture.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The captured frame this packet was decoded from.
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let end = min(data.len(), self.payload_offset + self.payload_len);
+
+        data.get(self.payload_offset..end).unwrap_or_default()
+    }
+
+    /// Whether this is an ARP packet.
+    pub fn is_arp(&self) -> bool {
+        self.ether_type == EtherTypes::Arp
+    }
+
+    /// Whether this is a multicast DNS packet.
+    pub fn is_mdns(&self) -> bool {
+        matches!(
+            self.transport,
+            Some(TransportHeader::Udp(UdpHeader {
+                source_port: MDNS_PORT,
+                ..
+            })) | Some(TransportHeader::Udp(UdpHeader {
+                destination_port: MDNS_PORT,
+                ..
+            }))
+        )
+    }
+
+    /// Whether this is a TCP packet that only acknowledges received data without carrying any
+    /// payload or control flags.
+    pub fn is_ack_only(&self) -> bool {
+        match &self.transport {
+            Some(TransportHeader::Tcp(tcp)) => {
+                tcp.flags.is_set(TcpFlags::ACK)
+                    && !tcp
+                        .flags
+                        .is_set(TcpFlags::SYN | TcpFlags::FIN | TcpFlags::RST)
+                    && self.payload_len == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether this packet is unrelated to an interaction with a voice assistant.
+    ///
+    /// This includes ARP packets, multicast DNS and TCP packets that only acknowledge data.
+    pub fn is_noise(&self) -> bool {
+        self.is_arp() || self.is_mdns() || self.is_ack_only()
+    }
+}
+
+impl TryFrom<&[u8]> for DecodedPacket {
+    type Error = Error;
+
+    /// Decode an Ethernet frame.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        DecodedPacket::decode(data, LinkType::Ethernet)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IpVersion {
+    V4,
+    V6,
+}
+
+/// The relevant fields of an IPv4 or IPv6 header.
+#[derive(Copy, Clone, Debug)]
+pub struct IpHeader {
+    pub version: IpVersion,
+    pub source: IpAddr,
+    pub destination: IpAddr,
+    /// The protocol of the IP payload. For IPv6, extension headers are skipped.
+    pub protocol: IpNextHeaderProtocol,
+}
+
+/// The header of a transport layer protocol supported by varys.
+#[derive(Clone, Debug)]
+pub enum TransportHeader {
+    Tcp(TcpHeader),
+    Udp(UdpHeader),
+}
+
+impl TransportHeader {
+    pub fn source_port(&self) -> u16 {
+        match self {
+            TransportHeader::Tcp(tcp) => tcp.source_port,
+            TransportHeader::Udp(udp) => udp.source_port,
+        }
+    }
+
+    pub fn destination_port(&self) -> u16 {
+        match self {
+            TransportHeader::Tcp(tcp) => tcp.destination_port,
+            TransportHeader::Udp(udp) => udp.destination_port,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct TcpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    pub sequence: u32,
+    pub acknowledgement: u32,
+    pub flags: Flags,
+}
+
+/// The control flags of a TCP header.
+///
+/// Use the constants in [`TcpFlags`] to test for specific flags.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Flags(pub u8);
+
+impl Flags {
+    /// Whether any of the given flags are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `flags`: The flags to test for, combined with `|`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use pnet::packet::tcp::TcpFlags;
+    /// # use varys_network::protocol::Flags;
+    /// let flags = Flags(TcpFlags::SYN | TcpFlags::ACK);
+    ///
+    /// assert!(flags.is_set(TcpFlags::ACK));
+    /// assert!(flags.is_set(TcpFlags::FIN | TcpFlags::SYN));
+    /// assert!(!flags.is_set(TcpFlags::RST));
+    /// ```
+    pub fn is_set(&self, flags: u8) -> bool {
+        self.0 & flags != 0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UdpHeader {
+    pub source_port: u16,
+    pub destination_port: u16,
+    /// The QUIC header if this datagram was sent to or from the QUIC port and looks like QUIC.
+    pub quic: Option<QuicHeader>,
+}
+
+/// The unencrypted part of a QUIC packet header.
+///
+/// See <https://datatracker.ietf.org/doc/html/rfc9000#section-17> for details.
+#[derive(Clone, Debug)]
+pub enum QuicHeader {
+    /// Long headers are used during connection establishment.
+    Long {
+        packet_type: QuicPacketType,
+        version: u32,
+        destination_connection_id: Vec<u8>,
+        source_connection_id: Vec<u8>,
+    },
+    /// Short headers are used once a connection is established. The length of the destination
+    /// connection id is only known to the endpoints, so it is not decoded.
+    Short,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum QuicPacketType {
+    Initial,
+    ZeroRtt,
+    Handshake,
+    Retry,
+    VersionNegotiation,
+}
+
+impl QuicHeader {
+    /// Try to decode a QUIC header from a UDP payload.
+    ///
+    /// Returns `None` if the payload is not a QUIC packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: The UDP payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use varys_network::protocol::{QuicHeader, QuicPacketType};
+    /// let initial = [0xc3, 0, 0, 0, 1, 2, 0xaa, 0xbb, 1, 0xcc, 0];
+    ///
+    /// if let Some(QuicHeader::Long { packet_type, version, destination_connection_id, .. }) =
+    ///     QuicHeader::decode(&initial)
+    /// {
+    ///     assert_eq!(packet_type, QuicPacketType::Initial);
+    ///     assert_eq!(version, 1);
+    ///     assert_eq!(destination_connection_id, vec![0xaa, 0xbb]);
+    /// } else {
+    ///     panic!("Expected a long header");
+    /// }
+    /// ```
+    pub fn decode(payload: &[u8]) -> Option<QuicHeader> {
+        let first = *payload.first()?;
+
+        if first & 0x80 == 0 {
+            // short headers must have the fixed bit set
+            return (first & 0x40 != 0).then_some(QuicHeader::Short);
+        }
+
+        let version = u32::from_be_bytes(payload.get(1..5)?.try_into().ok()?);
+        let destination_length = *payload.get(5)? as usize;
+        let destination_connection_id = payload.get(6..6 + destination_length)?.to_vec();
+        let source_offset = 6 + destination_length;
+        let source_length = *payload.get(source_offset)? as usize;
+        let source_connection_id = payload
+            .get(source_offset + 1..source_offset + 1 + source_length)?
+            .to_vec();
+        let packet_type = if version == 0 {
+            QuicPacketType::VersionNegotiation
+        } else if first & 0x40 == 0 {
+            return None;
+        } else {
+            match (first & 0x30) >> 4 {
+                0 => QuicPacketType::Initial,
+                1 => QuicPacketType::ZeroRtt,
+                2 => QuicPacketType::Handshake,
+                _ => QuicPacketType::Retry,
+            }
+        };
+
+        Some(QuicHeader::Long {
+            packet_type,
+            version,
+            destination_connection_id,
+            source_connection_id,
+        })
+    }
+}
+
+/// The result of decoding an IP packet.
+pub(crate) struct DecodedIp {
+    pub ip: IpHeader,
+    pub transport: Option<TransportHeader>,
+    /// The offset of the transport header from the start of the IP header.
+    pub transport_offset: usize,
+    /// The offset of the application payload from the start of the IP header.
+    pub payload_offset: usize,
+    pub payload_len: usize,
+}
+
+/// Read the source and destination addresses of an IP packet without decoding the rest of it.
+///
+/// Returns `None` if the frame does not contain an IP packet or its header was not captured.
+pub(crate) fn ip_addresses(data: &[u8], link_type: LinkType) -> Option<(IpAddr, IpAddr)> {
+    let link = link_type.decode(data)?;
+    let ip = data.get(link.payload_offset..)?;
+
+    match link.ether_type? {
+        EtherTypes::Ipv4 => {
+            let ipv4 = Ipv4Packet::new(ip)?;
+            Some((ipv4.get_source().into(), ipv4.get_destination().into()))
+        }
+        EtherTypes::Ipv6 => {
+            let ipv6 = Ipv6Packet::new(ip)?;
+            Some((ipv6.get_source().into(), ipv6.get_destination().into()))
+        }
+        _ => None,
+    }
+}
+
+/// Decode an IPv4 or IPv6 packet and its transport header.
+///
+/// Returns an error if the IP version is unknown or the headers were not captured completely.
+pub(crate) fn decode_ip(data: &[u8]) -> Result<DecodedIp, Error> {
+    let (ip, offset, payload_len) = match data.first().map(|byte| byte >> 4) {
+        Some(4) => decode_ipv4(data)?,
+        Some(6) => decode_ipv6(data)?,
+        _ => return Err(Error::MalformedPacket("unknown IP version")),
+    };
+    let payload = data.get(offset..).unwrap_or_default();
+    let (transport, header_len, payload_len) = match ip.protocol {
+        IpNextHeaderProtocols::Tcp => {
+            let tcp = TcpPacket::new(payload)
+                .ok_or(Error::MalformedPacket("TCP header was not captured"))?;
+            let header_len = tcp.get_data_offset() as usize * 4;
+            let header = TcpHeader {
+                source_port: tcp.get_source(),
+                destination_port: tcp.get_destination(),
+                sequence: tcp.get_sequence(),
+                acknowledgement: tcp.get_acknowledgement(),
+                flags: Flags(tcp.get_flags()),
+            };
+
+            (
+                Some(TransportHeader::Tcp(header)),
+                header_len,
+                payload_len.saturating_sub(header_len),
+            )
+        }
+        IpNextHeaderProtocols::Udp => {
+            let udp = UdpPacket::new(payload)
+                .ok_or(Error::MalformedPacket("UDP header was not captured"))?;
+            let (source_port, destination_port) = (udp.get_source(), udp.get_destination());
+            let quic = if source_port == QUIC_PORT || destination_port == QUIC_PORT {
+                QuicHeader::decode(udp.payload())
+            } else {
+                None
+            };
+            let header = UdpHeader {
+                source_port,
+                destination_port,
+                quic,
+            };
+            let header_len = UdpPacket::minimum_packet_size();
+
+            (
+                Some(TransportHeader::Udp(header)),
+                header_len,
+                (udp.get_length() as usize).saturating_sub(header_len),
+            )
+        }
+        _ => (None, 0, payload_len),
+    };
+
+    Ok(DecodedIp {
+        ip,
+        transport,
+        transport_offset: offset,
+        payload_offset: offset + header_len,
+        payload_len,
+    })
+}
+
+fn decode_ipv4(data: &[u8]) -> Result<(IpHeader, usize, usize), Error> {
+    let ipv4 = Ipv4Packet::new(data).ok_or(Error::MalformedPacket("IPv4 header is too short"))?;
+    let header_len = ipv4.get_header_length() as usize * 4;
+    if header_len < Ipv4Packet::minimum_packet_size() || header_len > data.len() {
+        return Err(Error::MalformedPacket("IPv4 header length is invalid"));
+    }
+    let header = IpHeader {
+        version: IpVersion::V4,
+        source: ipv4.get_source().into(),
+        destination: ipv4.get_destination().into(),
+        protocol: ipv4.get_next_level_protocol(),
+    };
+
+    Ok((
+        header,
+        header_len,
+        (ipv4.get_total_length() as usize).saturating_sub(header_len),
+    ))
+}
+
+fn decode_ipv6(data: &[u8]) -> Result<(IpHeader, usize, usize), Error> {
+    let ipv6 = Ipv6Packet::new(data).ok_or(Error::MalformedPacket("IPv6 header is too short"))?;
+    let mut protocol = ipv6.get_next_header();
+    let mut offset = Ipv6Packet::minimum_packet_size();
+    let mut payload_len = ipv6.get_payload_length() as usize;
+
+    // skip extension headers to find the transport protocol
+    loop {
+        let extension_len = match protocol {
+            IpNextHeaderProtocols::Hopopt
+            | IpNextHeaderProtocols::Ipv6Route
+            | IpNextHeaderProtocols::Ipv6Opts => {
+                (*data.get(offset + 1).ok_or(Error::MalformedPacket(
+                    "IPv6 extension header was not captured",
+                ))? as usize
+                    + 1)
+                    * 8
+            }
+            IpNextHeaderProtocols::Ipv6Frag => 8,
+            _ => break,
+        };
+
+        protocol = IpNextHeaderProtocol(*data.get(offset).ok_or(Error::MalformedPacket(
+            "IPv6 extension header was not captured",
+        ))?);
+        offset += extension_len;
+        payload_len = payload_len.saturating_sub(extension_len);
+    }
+
+    let header = IpHeader {
+        version: IpVersion::V6,
+        source: ipv6.get_source().into(),
+        destination: ipv6.get_destination().into(),
+        protocol,
+    };
+    if offset > data.len() {
+        return Err(Error::MalformedPacket("IPv6 extension headers are invalid"));
+    }
+
+    Ok((header, offset, payload_len))
+}
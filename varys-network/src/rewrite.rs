@@ -0,0 +1,156 @@
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use pcap::{Capture, Linktype, Savefile};
+
+use crate::compress;
+use crate::error::Error;
+use crate::pcapng;
+use crate::pcapng::{Annotations, PcapngWriter};
+
+/// What to do with a packet when rewriting a capture.
+pub(crate) enum Rewrite {
+    /// Store the packet unchanged.
+    Keep,
+    /// Leave the packet out.
+    Drop,
+    /// Store a modified packet instead.
+    Replace(pcap::PacketHeader, Vec<u8>),
+}
+
+/// Decides how each packet of a capture is rewritten.
+pub(crate) trait Rewriter {
+    /// Called with the link type and annotations of the capture before any packet is written.
+    ///
+    /// Returns the annotations to store or an error if the capture cannot be rewritten.
+    fn prepare(
+        &mut self,
+        _linktype: Linktype,
+        annotations: Annotations,
+    ) -> Result<Annotations, Error> {
+        Ok(annotations)
+    }
+
+    /// Decide what to store for a packet.
+    fn rewrite(&mut self, packet: &pcap::Packet) -> Rewrite;
+
+    /// Called if the annotations or a packet of the capture cannot be read, e.g. because the file
+    /// was cut off.
+    ///
+    /// Returns the error to abort rewriting, which is the default, or `Ok` to store what was read
+    /// so far. Rewriting ends after the first packet that cannot be read and the annotations are
+    /// left empty if they cannot be read.
+    fn read_failed(&mut self, error: Error) -> Result<(), Error> {
+        Err(error)
+    }
+}
+
+/// Writes a capture in the format of the capture it is rewritten from.
+enum CaptureWriter {
+    Pcap(Savefile),
+    Pcapng(PcapngWriter<BufWriter<File>>),
+}
+
+impl CaptureWriter {
+    fn write(&mut self, packet: &pcap::Packet, interface: usize) -> Result<(), Error> {
+        match self {
+            CaptureWriter::Pcap(file) => file.write(packet),
+            CaptureWriter::Pcapng(file) => file.write_on(packet, interface)?,
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self {
+            CaptureWriter::Pcap(mut file) => file.flush()?,
+            CaptureWriter::Pcapng(file) => {
+                file.finish()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Rewrite a capture packet by packet, keeping its format and compression.
+///
+/// The annotations and interfaces of a pcapng file are kept as well. `from` and `to` may be the
+/// same path, in which case the file is replaced.
+///
+/// Returns the number of packets written.
+///
+/// # Arguments
+///
+/// * `from`: The capture to rewrite, which may be compressed.
+/// * `to`: Where to write the rewritten capture.
+/// * `rewriter`: Decides what to store for each packet.
+pub(crate) fn rewrite_file<R: Rewriter>(
+    from: &Path,
+    to: &Path,
+    rewriter: &mut R,
+) -> Result<usize, Error> {
+    let (mut capture, decompressed) = compress::open_capture(from)?;
+    let linktype = capture.get_datalink();
+    let is_pcapng = pcapng::is_pcapng(from)?;
+    let (annotations, interface_ids) = if is_pcapng {
+        match pcapng::read_annotations(from)
+            .and_then(|annotations| Ok((annotations, pcapng::read_packet_interfaces(from)?)))
+        {
+            Ok(read) => read,
+            Err(error) => {
+                rewriter.read_failed(error)?;
+                (Annotations::default(), Vec::new())
+            }
+        }
+    } else {
+        (Annotations::default(), Vec::new())
+    };
+    let annotations = rewriter.prepare(linktype, annotations)?;
+
+    // write to a temporary file first in case the capture is rewritten in place
+    let temporary_path = to.with_extension("rewriting");
+    let mut writer = if is_pcapng {
+        CaptureWriter::Pcapng(pcapng::create(&temporary_path, linktype, &annotations)?)
+    } else {
+        CaptureWriter::Pcap(Capture::dead(linktype)?.savefile(&temporary_path)?)
+    };
+    let mut written = 0;
+
+    for index in 0.. {
+        let packet = match capture.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(error) => {
+                rewriter.read_failed(error.into())?;
+                break;
+            }
+        };
+        let interface = interface_ids.get(index).copied().unwrap_or_default();
+
+        match rewriter.rewrite(&packet) {
+            Rewrite::Keep => writer.write(&packet, interface)?,
+            Rewrite::Drop => continue,
+            Rewrite::Replace(header, data) => writer.write(
+                &pcap::Packet {
+                    header: &header,
+                    data: &data,
+                },
+                interface,
+            )?,
+        }
+        written += 1;
+    }
+    writer.finish()?;
+
+    if decompressed.is_some() {
+        compress::compress_to(&temporary_path, to, compress::DEFAULT_LEVEL)?;
+        fs::remove_file(&temporary_path)?;
+    } else {
+        fs::rename(&temporary_path, to)?;
+    }
+
+    Ok(written)
+}
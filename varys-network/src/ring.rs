@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use pcap::{Capture, Linktype};
+
+use crate::address::DeviceAddress;
+use crate::error::Error;
+use crate::packet::{FromCapture, PacketHeader};
+use crate::pcapng;
+use crate::pcapng::Annotations;
+use crate::sniff::{CaptureFormat, SnifferStats};
+
+/// A packet kept in a [`PacketRing`].
+struct RingEntry {
+    /// When the packet was received by the sniffer.
+    received: DateTime<Utc>,
+    /// The index of the interface that captured the packet.
+    interface: usize,
+    header: pcap::PacketHeader,
+    data: Vec<u8>,
+}
+
+/// Keeps the packets received within a rolling time window in memory.
+///
+/// Packets are sliced by the time they were received, which is the capture time for live captures
+/// and the replay time for replayed captures.
+pub struct PacketRing {
+    /// How long packets are kept.
+    pub window: Duration,
+    /// The address of the device whose packets are counted as incoming and outgoing when a slice
+    /// is written.
+    pub relative_to: Option<DeviceAddress>,
+    /// The names of the interfaces the packets are captured on, which are stored in pcapng slices.
+    pub interfaces: Vec<String>,
+    linktype: Linktype,
+    packets: VecDeque<RingEntry>,
+}
+
+impl PacketRing {
+    /// Create an empty ring for packets of the given link type.
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: How long packets are kept after they were received.
+    /// * `linktype`: The link type of the capture the packets come from.
+    pub fn new(window: Duration, linktype: Linktype) -> Self {
+        PacketRing {
+            window,
+            relative_to: None,
+            interfaces: Vec::new(),
+            linktype,
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Add a packet that was just received and drop all packets that are older than the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The received packet.
+    pub fn push(&mut self, packet: &pcap::Packet) {
+        self.push_on(packet, 0);
+    }
+
+    /// Add a packet that was just received on one of the [`PacketRing::interfaces`] like
+    /// [`PacketRing::push`].
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The received packet.
+    /// * `interface`: The index of the interface that captured the packet.
+    pub fn push_on(&mut self, packet: &pcap::Packet, interface: usize) {
+        let received = Utc::now();
+        let window =
+            chrono::Duration::from_std(self.window).unwrap_or(chrono::Duration::max_value());
+
+        while self
+            .packets
+            .front()
+            .is_some_and(|oldest| received - oldest.received > window)
+        {
+            self.packets.pop_front();
+        }
+
+        self.packets.push_back(RingEntry {
+            received,
+            interface,
+            header: *packet.header,
+            data: packet.data.to_vec(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// When the oldest packet still in the ring was received.
+    pub fn oldest(&self) -> Option<DateTime<Utc>> {
+        self.packets.front().map(|entry| entry.received)
+    }
+
+    /// Write all packets received between `from` and `to` (inclusive) to a capture file.
+    ///
+    /// Returns [`SnifferStats`] about the packets written, without any dropped packets, or an error
+    /// if the file could not be written.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The start of the slice.
+    /// * `to`: The end of the slice.
+    /// * `file_path`: The path of the capture file to write.
+    /// * `format`: The format of the capture file.
+    /// * `annotations`: The metadata to store in the file. Marks are attached to the first packet
+    ///   received at or after them. This is ignored for pcap files.
+    pub fn write_slice(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        file_path: &Path,
+        format: CaptureFormat,
+        annotations: &Annotations,
+    ) -> Result<SnifferStats, Error> {
+        if self.oldest().is_some_and(|oldest| oldest > from) {
+            warn!("Packets before {from} were already dropped from the capture buffer");
+        }
+
+        let (mut pcap_file, mut pcapng_file) = match format {
+            CaptureFormat::Pcap => (
+                Some(Capture::dead(self.linktype)?.savefile(file_path)?),
+                None,
+            ),
+            CaptureFormat::Pcapng => {
+                let mut annotations = annotations.clone();
+                if !self.interfaces.is_empty() {
+                    annotations.interfaces = self.interfaces.clone();
+                }
+                (
+                    None,
+                    Some(pcapng::create(file_path, self.linktype, &annotations)?),
+                )
+            }
+        };
+        let mut stats = SnifferStats::default();
+
+        for entry in self
+            .packets
+            .iter()
+            .skip_while(|entry| entry.received < from)
+            .take_while(|entry| entry.received <= to)
+        {
+            let packet = pcap::Packet {
+                header: &entry.header,
+                data: &entry.data,
+            };
+            if let Some(file) = &mut pcap_file {
+                file.write(&packet);
+            }
+            if let Some(file) = &mut pcapng_file {
+                file.write_received(&packet, entry.received, entry.interface)?;
+            }
+            stats.received += 1;
+            stats.count(
+                &PacketHeader::from_capture(packet, self.linktype.into()),
+                self.relative_to.as_ref(),
+            );
+        }
+        if let Some(mut file) = pcap_file {
+            file.flush()?;
+        }
+        if let Some(file) = pcapng_file {
+            file.finish()?;
+        }
+
+        debug!(
+            "Wrote {} packets from {from} to {to} to {file_path:?}",
+            stats.received
+        );
+
+        Ok(stats)
+    }
+}
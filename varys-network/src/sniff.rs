@@ -1,31 +1,219 @@
 use std::fmt::{Display, Formatter};
-use std::path::Path;
-use std::sync::mpsc::{channel, Sender, TryRecvError};
-use std::time::Duration;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 use std::{thread, thread::JoinHandle};
 
-use log::{info, trace};
-pub use pcap::ConnectionStatus;
-use pcap::{Capture, Device, Stat};
+use chrono::{DateTime, Utc};
+use log::{debug, info, trace};
+use pcap::{Activated, BpfProgram, Capture, Device, Stat};
+pub use pcap::{ConnectionStatus, Linktype};
 
+use crate::address::DeviceAddress;
+use crate::anonymise::Anonymiser;
+use crate::compress;
+use crate::compress::Decompressed;
 use crate::error::Error;
-use crate::packet::Packet;
+use crate::link::LinkType;
+use crate::merge::Merger;
+use crate::packet;
+use crate::packet::{CapturedPacket, FromCapture, Packet, PacketDirection, PacketHeader};
+use crate::pcapng;
+use crate::pcapng::Annotations;
+use crate::ring::PacketRing;
+use crate::subscribe::Subscribers;
+use crate::tzsp;
 
-/// A sniffer is used to capture network packets on a specific network device.
+/// An opened [`PacketSource`].
+enum Opened {
+    /// One capture per device.
+    Live(Vec<Capture<dyn Activated>>),
+    /// A capture file to replay.
+    File {
+        capture: Capture<dyn Activated>,
+        /// The guard of a decompressed capture file, which must be kept alive while it is
+        /// replayed.
+        decompressed: Option<Decompressed>,
+        speed: ReplaySpeed,
+    },
+    /// A remote capture received on a socket.
+    Remote {
+        /// A dead capture of the link type of the remote capture, which is only used to write
+        /// files.
+        capture: Capture<dyn Activated>,
+        socket: UdpSocket,
+        /// The compiled capture filter, which is applied to the received frames.
+        program: Option<BpfProgram>,
+    },
+}
+
+impl Opened {
+    /// The captures of the source, which all have the same link type.
+    fn captures(&self) -> &[Capture<dyn Activated>] {
+        match self {
+            Opened::Live(captures) => captures,
+            Opened::File { capture, .. } | Opened::Remote { capture, .. } => {
+                std::slice::from_ref(capture)
+            }
+        }
+    }
+}
+
+/// Where a [`Sniffer`] gets its packets from.
+pub enum PacketSource {
+    /// Capture live traffic on a network device.
+    Device(Device),
+    /// Capture live traffic on several network devices at once, e.g. a mirrored port and Wi-Fi.
+    ///
+    /// The packets of all devices are merged in the order they were captured and frames seen on
+    /// more than one device are only stored once. All devices must have the same link type.
+    Devices(Vec<Device>),
+    /// Replay the packets of an existing pcap file.
+    ///
+    /// This does not require root privileges or a network device.
+    File { path: PathBuf, speed: ReplaySpeed },
+    /// Receive the traffic captured by a router that streams it over UDP using TZSP, e.g. the
+    /// packet sniffer of MikroTik or OpenWrt routers.
+    ///
+    /// `address` is the local address to listen on, usually on port [`tzsp::DEFAULT_PORT`]. Only
+    /// frames of the given link type are stored. Packets are timestamped when they are received.
+    Tzsp {
+        address: SocketAddr,
+        linktype: Linktype,
+    },
+}
+
+impl PacketSource {
+    /// The name of the device or the path of the file packets are read from.
+    ///
+    /// The names of several devices are separated by commas.
+    pub fn name(&self) -> String {
+        match self {
+            PacketSource::File { path, .. } => path.display().to_string(),
+            _ => self.interfaces().join(","),
+        }
+    }
+
+    /// The names of the devices packets are captured on, which is empty when replaying a file.
+    ///
+    /// A remote capture is named after the address it is received on, e.g. `tzsp:0.0.0.0:37008`.
+    pub fn interfaces(&self) -> Vec<String> {
+        match self {
+            PacketSource::Device(device) => vec![device.name.clone()],
+            PacketSource::Devices(devices) => {
+                devices.iter().map(|device| device.name.clone()).collect()
+            }
+            PacketSource::File { .. } => Vec::new(),
+            PacketSource::Tzsp { address, .. } => vec![format!("tzsp:{address}")],
+        }
+    }
+}
+
+impl From<Device> for PacketSource {
+    fn from(device: Device) -> Self {
+        PacketSource::Device(device)
+    }
+}
+
+/// How fast a [`PacketSource::File`] is replayed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keep the time between packets as it was recorded.
+    #[default]
+    Recorded,
+    /// Replay all packets as fast as possible.
+    Unlimited,
+}
+
+/// The file format captures are stored in.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CaptureFormat {
+    /// The classic pcap format, which cannot store any metadata.
+    #[default]
+    Pcap,
+    /// The pcapng format, which stores the [`Annotations`] of a capture as comments.
+    Pcapng,
+}
+
+impl CaptureFormat {
+    /// The file extension of captures in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Pcap => "pcap",
+            CaptureFormat::Pcapng => "pcapng",
+        }
+    }
+}
+
+/// A sniffer is used to capture network packets on a specific network device or to replay them from
+/// an existing capture.
 pub struct Sniffer {
-    device: Device,
+    source: PacketSource,
+    /// The optional BPF capture filter to apply in the kernel.
+    ///
+    /// Only packets matching the filter are captured and written to disk. Use
+    /// [`MacAddress::capture_filter`](crate::address::MacAddress::capture_filter) to only keep the
+    /// traffic of a single device.
+    ///
+    /// See <https://www.tcpdump.org/manpages/pcap-filter.7.html> for the filter syntax.
+    pub filter: Option<String>,
+    /// The optional anonymiser applied to every packet before it is stored.
+    ///
+    /// Share the same anonymiser between all captures of a session to keep the pseudonyms
+    /// consistent. Anonymisation is only supported for Ethernet captures.
+    pub anonymiser: Option<Arc<Mutex<Anonymiser>>>,
+    /// The address of the device whose packets are counted as incoming and outgoing in the
+    /// [`SnifferStats`].
+    ///
+    /// Its IP address is only needed if the link type of the capture does not carry MAC addresses.
+    pub relative_to: Option<DeviceAddress>,
+    /// The zstd level to compress stored captures with or `None` to store them uncompressed.
+    ///
+    /// Captures are compressed once they are complete, so they are written to the path returned by
+    /// [`Sniffer::capture_path`] instead of the one passed to [`Sniffer::start`] or
+    /// [`ContinuousInstance::save`].
+    pub compression: Option<i32>,
+    /// The format captures are stored in.
+    ///
+    /// Captures of several devices are always stored as pcapng, because pcap files cannot record
+    /// which device captured a packet. Live captures are always written as pcap files while
+    /// running and converted to pcapng once they are stopped with [`SnifferInstance::stop_with`],
+    /// because their annotations are only known at the end.
+    pub format: CaptureFormat,
 }
 
 impl Sniffer {
-    /// Start sniffing on this device.
+    /// Create a sniffer that replays the packets of an existing pcap file.
     ///
-    /// This requires root privileges to access the network devices, otherwise an error is returned.
-    /// This also returns an error if a `file_path` was provided which could not be written to.
+    /// # Arguments
+    ///
+    /// * `path`: The path to the pcap file to replay.
+    /// * `speed`: How fast to replay the packets.
+    pub fn replay<P: AsRef<Path>>(path: P, speed: ReplaySpeed) -> Self {
+        Sniffer::from(PacketSource::File {
+            path: path.as_ref().to_path_buf(),
+            speed,
+        })
+    }
+
+    /// Start sniffing on this device or start replaying the packets of the file.
+    ///
+    /// Capturing on a device requires root privileges to access the network devices, otherwise an
+    /// error is returned. This also returns an error if a `file_path` was provided which could not
+    /// be written to, if the file to replay could not be read or if the [`Sniffer::filter`] is
+    /// invalid.
+    ///
+    /// A replay writes the replayed packets to `file_path` just like a live capture. Once all
+    /// packets were replayed, the instance waits to be stopped.
     ///
     /// # Arguments
     ///
     /// * `file_path`: The path to which the captured traffic is written. The extension `.pcap` will
-    /// be added if it isn't already in the path.
+    ///   be added if it isn't already in the path.
     ///
     /// Returns a [`SnifferInstance`], on which [`SnifferInstance::stop`] can be called to stop
     /// capturing the traffic.
@@ -43,41 +231,340 @@ impl Sniffer {
     /// let instance = sniffer.start(Path::new("/path/to/capture.pcap")).unwrap();
     /// # instance.stop().unwrap();
     /// ```
+    ///
+    /// Only capture the traffic of a single device:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use std::str::FromStr;
+    /// # use varys_network::address::MacAddress;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::Sniffer;
+    /// let mut sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// sniffer.filter = Some(MacAddress::from_str("00:1a:2b:3c:4d:5e").unwrap().capture_filter());
+    ///
+    /// let instance = sniffer.start(Path::new("/path/to/capture.pcap")).unwrap();
+    /// # instance.stop().unwrap();
+    /// ```
+    ///
+    /// Replay an existing capture as fast as possible:
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use varys_network::sniff::{ReplaySpeed, Sniffer};
+    /// let sniffer = Sniffer::replay("/path/to/recorded.pcap", ReplaySpeed::Unlimited);
+    ///
+    /// let instance = sniffer.start(Path::new("/path/to/capture.pcap")).unwrap();
+    /// let stats = instance.stop().unwrap();
+    /// ```
     pub fn start(&self, file_path: &Path) -> Result<SnifferInstance, Error> {
-        let mut file_path = file_path.to_owned();
-        file_path.set_extension("pcap");
+        let format = self.stored_format();
+        let file_path = file_path.with_extension(format.extension());
+        let written_path = match format {
+            CaptureFormat::Pcap => file_path.clone(),
+            CaptureFormat::Pcapng => file_path.with_extension("capturing.pcap"),
+        };
+
+        info!("{} starting (writing to {:?})...", self, written_path);
+
+        let opened = self.open()?;
+        let mut file = opened.captures()[0].savefile(&written_path)?;
+        let interface_ids = Arc::new(Mutex::new(Vec::new()));
+        // the interfaces only need to be recorded if there is more than one
+        let record_ids =
+            matches!(self.source, PacketSource::Devices(_)).then(|| interface_ids.clone());
+        let mut instance = self.spawn(opened, move |packet, interface| {
+            file.write(packet);
+            if let Some(ids) = &record_ids {
+                ids.lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(interface);
+            }
+        });
+        instance.finish = Some(Finish {
+            written_path,
+            file_path,
+            format,
+            compression: self.compression,
+            interfaces: self.source.interfaces(),
+            interface_ids,
+        });
+
+        Ok(instance)
+    }
+
+    /// The path a capture started with `file_path` is finally stored at.
+    ///
+    /// This is `file_path` with the extension of its [`CaptureFormat`], followed by `.zst` if the
+    /// sniffer compresses its captures.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path`: The path passed to [`Sniffer::start`] or [`ContinuousInstance::save`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::path::{Path, PathBuf};
+    /// # use varys_network::compress;
+    /// # use varys_network::sniff::{ReplaySpeed, Sniffer};
+    /// let mut sniffer = Sniffer::replay("recorded.pcap", ReplaySpeed::Unlimited);
+    /// sniffer.compression = Some(compress::DEFAULT_LEVEL);
+    ///
+    /// assert_eq!(
+    ///     sniffer.capture_path(Path::new("capture")),
+    ///     PathBuf::from("capture.pcap.zst")
+    /// );
+    /// ```
+    pub fn capture_path(&self, file_path: &Path) -> PathBuf {
+        let file_path = file_path.with_extension(self.stored_format().extension());
+
+        match self.compression {
+            Some(_) => compress::compressed_path(file_path),
+            None => file_path,
+        }
+    }
+
+    /// Start sniffing continuously, keeping the packets of a rolling time window in memory.
+    ///
+    /// Unlike [`Sniffer::start`], nothing is written to disk until a slice of the window is saved
+    /// with [`ContinuousInstance::save`]. This avoids opening the device for every capture and
+    /// allows saving packets that were received before the slice was requested.
+    ///
+    /// Returns the same errors as [`Sniffer::start`].
+    ///
+    /// # Arguments
+    ///
+    /// * `window`: How long packets are kept in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use std::time::Duration;
+    /// # use chrono::Utc;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::Sniffer;
+    /// let sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// let instance = sniffer.start_continuous(Duration::from_secs(120)).unwrap();
+    ///
+    /// let start = Utc::now();
+    /// // ...
+    /// let pre_roll = chrono::Duration::seconds(1);
+    /// instance
+    ///     .save(start - pre_roll, Utc::now(), Path::new("capture.pcap"))
+    ///     .unwrap();
+    /// let stats = instance.stop().unwrap();
+    /// ```
+    pub fn start_continuous(&self, window: Duration) -> Result<ContinuousInstance, Error> {
+        info!("{} starting continuously (keeping {window:?})...", self);
 
-        info!("{} starting (writing to {:?})...", self, file_path);
+        let opened = self.open()?;
+        let mut ring = PacketRing::new(window, opened.captures()[0].get_datalink());
+        ring.relative_to = self.stored_relative_to();
+        ring.interfaces = self.source.interfaces();
+        let ring = Arc::new(Mutex::new(ring));
+        let sink_ring = ring.clone();
+        let instance = self.spawn(opened, move |packet, interface| {
+            if let Ok(mut ring) = sink_ring.lock() {
+                ring.push_on(packet, interface);
+            }
+        });
 
-        let mut capture = Capture::from_device(self.device.clone())?
+        Ok(ContinuousInstance {
+            ring,
+            instance,
+            compression: self.compression,
+            format: self.stored_format(),
+        })
+    }
+
+    /// The format captures are actually stored in, see [`Sniffer::format`].
+    fn stored_format(&self) -> CaptureFormat {
+        match self.source {
+            PacketSource::Devices(_) => CaptureFormat::Pcapng,
+            _ => self.format,
+        }
+    }
+
+    /// Open the packet source and apply the capture filter.
+    ///
+    /// A compressed capture to replay is decompressed first. A remote capture binds its socket and
+    /// compiles the capture filter, which is applied to the received frames.
+    ///
+    /// Returns an error if an anonymiser is set but the capture does not contain Ethernet frames,
+    /// if the devices have different link types or if the socket could not be bound.
+    fn open(&self) -> Result<Opened, Error> {
+        let opened = match &self.source {
+            PacketSource::Device(device) => Opened::Live(vec![self.open_device(device)?]),
+            PacketSource::Devices(devices) => Opened::Live(
+                devices
+                    .iter()
+                    .map(|device| self.open_device(device))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            PacketSource::File { path, speed } => {
+                let (mut capture, decompressed) = compress::open_capture(path)?;
+                self.apply_filter(&mut capture)?;
+                Opened::File {
+                    capture: capture.into(),
+                    decompressed,
+                    speed: *speed,
+                }
+            }
+            PacketSource::Tzsp { address, linktype } => {
+                let capture = Capture::dead(*linktype)?;
+                let program = match &self.filter {
+                    Some(filter) => {
+                        debug!("Compiling capture filter \"{filter}\"");
+
+                        Some(capture.compile(filter, true)?)
+                    }
+                    None => None,
+                };
+                let socket = UdpSocket::bind(address)?;
+                socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+                Opened::Remote {
+                    capture: capture.into(),
+                    socket,
+                    program,
+                }
+            }
+        };
+
+        let captures = opened.captures();
+        let linktype = captures.first().ok_or(Error::NoDeviceGiven)?.get_datalink();
+        if let Some(other) = captures
+            .iter()
+            .map(|capture| capture.get_datalink())
+            .find(|other| *other != linktype)
+        {
+            return Err(Error::MixedLinkTypes(linktype.0, other.0));
+        }
+        if self.anonymiser.is_some() && linktype != Linktype::ETHERNET {
+            return Err(Error::UnsupportedLinkType(linktype.0));
+        }
+
+        Ok(opened)
+    }
+
+    fn open_device(&self, device: &Device) -> Result<Capture<dyn Activated>, Error> {
+        let mut capture = Capture::from_device(device.clone())?
             .promisc(true)
             .immediate_mode(true)
             .buffer_size(100_000_000)
-            .open()?
-            .setnonblock()?;
-        let mut file = capture.savefile(file_path)?;
+            .open()?;
+        self.apply_filter(&mut capture)?;
+
+        Ok(capture.setnonblock()?.into())
+    }
+
+    /// Pass all packets of the captures to `sink` and to the subscribers of the instance on a new
+    /// thread until the instance is stopped.
+    ///
+    /// `sink` also receives the index of the capture a packet came from. Packets are anonymised
+    /// first if the sniffer has an anonymiser.
+    fn spawn<F: FnMut(&pcap::Packet, usize) + Send + 'static>(
+        &self,
+        opened: Opened,
+        mut sink: F,
+    ) -> SnifferInstance {
         let (shutdown_channel, receiver) = channel();
+        let subscribers = Subscribers::default();
+        let stats = Arc::new(Mutex::new(SnifferStats::default()));
+        let anonymiser = self.anonymiser.clone();
+        let relative_to = self.stored_relative_to();
+        let link_type = LinkType::from(opened.captures()[0].get_datalink());
+        let publisher = subscribers.clone();
+        let counter = stats.clone();
+        let mut store = move |packet: &pcap::Packet, interface: usize| {
+            sink(packet, interface);
+            lock_stats(&counter).count(
+                &PacketHeader::from_capture(
+                    pcap::Packet {
+                        header: packet.header,
+                        data: packet.data,
+                    },
+                    link_type,
+                ),
+                relative_to.as_ref(),
+            );
+            publisher.publish(packet, link_type);
+        };
+        let sink =
+            move |packet: &pcap::Packet, interface: usize| match anonymised(&anonymiser, packet) {
+                Some((header, data)) => store(
+                    &pcap::Packet {
+                        header: &header,
+                        data: &data,
+                    },
+                    interface,
+                ),
+                None => store(packet, interface),
+            };
 
-        let join_handle = thread::spawn(move || {
-            while receiver.try_recv() == Err(TryRecvError::Empty) {
-                match capture.next_packet() {
-                    Ok(packet) => {
-                        file.write(&packet);
-                        trace!("{}", Packet::from(packet));
-                    }
-                    Err(_) => thread::sleep(Duration::from_millis(10)),
-                }
+        let thread_stats = stats.clone();
+        let join_handle = match opened {
+            Opened::Live(captures) => {
+                thread::spawn(move || capture_live(captures, sink, receiver, thread_stats))
             }
+            Opened::File {
+                capture,
+                decompressed,
+                speed,
+            } => thread::spawn(move || {
+                let _decompressed = decompressed;
+                replay(capture, sink, speed, receiver, thread_stats)
+            }),
+            Opened::Remote {
+                capture,
+                socket,
+                program,
+            } => {
+                let linktype = capture.get_datalink();
+                thread::spawn(move || {
+                    receive_tzsp(socket, linktype, program, sink, receiver, thread_stats)
+                })
+            }
+        };
 
-            capture.stats().map_err(Error::from)
-        });
-
-        Ok(SnifferInstance {
+        SnifferInstance {
             shutdown_channel,
             join_handle,
+            subscribers,
+            stats,
+            finish: None,
+        }
+    }
+
+    /// The address packets are counted relative to in the form it is stored in, i.e. its
+    /// pseudonym if packets are anonymised.
+    fn stored_relative_to(&self) -> Option<DeviceAddress> {
+        let address = self.relative_to?;
+
+        Some(match &self.anonymiser {
+            Some(anonymiser) => {
+                let mut anonymiser = anonymiser.lock().unwrap_or_else(PoisonError::into_inner);
+
+                DeviceAddress {
+                    mac: anonymiser.mac(address.mac),
+                    ip: address.ip.map(|ip| anonymiser.ip(ip)),
+                }
+            }
+            None => address,
         })
     }
 
+    fn apply_filter<T: Activated + ?Sized>(&self, capture: &mut Capture<T>) -> Result<(), Error> {
+        if let Some(filter) = &self.filter {
+            debug!("Applying capture filter \"{filter}\"");
+
+            capture.filter(filter, true)?;
+        }
+
+        Ok(())
+    }
+
     /// Run a sniffer for a specified amount of seconds and stop it automatically afterwards. The
     /// current thread is blocked until the sniffer is done.
     ///
@@ -88,7 +575,7 @@ impl Sniffer {
     ///
     /// * `seconds`: How many seconds to capture traffic for.
     /// * `file_path`: The path to which the captured traffic is written. The extension `.pcap` will
-    /// be added if it isn't already in the path.
+    ///   be added if it isn't already in the path.
     ///
     /// Returns [`SnifferStats`] with statistics about the capture.
     ///
@@ -117,29 +604,367 @@ impl Sniffer {
 
 impl From<Device> for Sniffer {
     fn from(device: Device) -> Self {
-        Sniffer { device }
+        Sniffer::from(PacketSource::from(device))
+    }
+}
+
+impl From<PacketSource> for Sniffer {
+    fn from(source: PacketSource) -> Self {
+        Sniffer {
+            source,
+            filter: None,
+            anonymiser: None,
+            relative_to: None,
+            compression: None,
+            format: CaptureFormat::default(),
+        }
     }
 }
 
 impl Display for Sniffer {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Sniffer on {} ({:?} | {:?})",
-            self.device.name, self.device.flags.connection_status, self.device.flags.if_flags
-        )
+        match &self.source {
+            PacketSource::Device(device) => write!(
+                f,
+                "Sniffer on {} ({:?} | {:?})",
+                device.name, device.flags.connection_status, device.flags.if_flags
+            ),
+            PacketSource::Devices(_) => write!(f, "Sniffer on {}", self.source.name()),
+            PacketSource::File { path, speed } => {
+                write!(f, "Sniffer replaying {} ({speed:?})", path.display())
+            }
+            PacketSource::Tzsp { address, .. } => {
+                write!(f, "Sniffer receiving TZSP on {address}")
+            }
+        }
     }
 }
 
+/// Anonymise a packet if an anonymiser is set, returning `None` if it should be stored unchanged.
+fn anonymised(
+    anonymiser: &Option<Arc<Mutex<Anonymiser>>>,
+    packet: &pcap::Packet,
+) -> Option<(pcap::PacketHeader, Vec<u8>)> {
+    // never store a packet unchanged just because another capture using the anonymiser panicked
+    let mut anonymiser = anonymiser
+        .as_ref()?
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    Some(anonymiser.anonymise_packet(packet))
+}
+
+/// How often the statistics of a live capture are updated while it is running.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Capture live on all devices until the instance is stopped.
+///
+/// The packets of several devices are merged by a [`Merger`], which holds them back for a moment
+/// to order and deduplicate them. A single device is passed through directly.
+fn capture_live<F: FnMut(&pcap::Packet, usize)>(
+    mut captures: Vec<Capture<dyn Activated>>,
+    mut sink: F,
+    shutdown: Receiver<()>,
+    stats: Arc<Mutex<SnifferStats>>,
+) -> Result<SnifferStats, Error> {
+    let link_type = LinkType::from(captures[0].get_datalink());
+    let mut merger = (captures.len() > 1).then(|| Merger::new(link_type));
+    let mut stats_updated = Instant::now();
+
+    while shutdown.try_recv() == Err(TryRecvError::Empty) {
+        let mut idle = true;
+        for (interface, capture) in captures.iter_mut().enumerate() {
+            if let Ok(packet) = capture.next_packet() {
+                idle = false;
+                match &mut merger {
+                    Some(merger) => merger.push(interface, &packet),
+                    None => {
+                        sink(&packet, interface);
+                        trace!("{}", Packet::from_capture(packet, link_type));
+                    }
+                }
+            }
+        }
+        if let Some(merger) = &mut merger {
+            while let Some(merged) = merger.pop(Utc::now()) {
+                sink(&merged.packet(), merged.interface);
+                trace!("{}", Packet::from_capture(merged.packet(), link_type));
+            }
+        }
+        if idle {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        if stats_updated.elapsed() >= STATS_INTERVAL {
+            if let Ok(capture_stats) = total_stats(&mut captures) {
+                update_live_stats(&mut lock_stats(&stats), capture_stats, merger.as_ref());
+            }
+            stats_updated = Instant::now();
+        }
+    }
+
+    if let Some(merger) = &mut merger {
+        while let Some(merged) = merger.pop_any() {
+            sink(&merged.packet(), merged.interface);
+        }
+    }
+
+    let mut stats = lock_stats(&stats);
+    update_live_stats(&mut stats, total_stats(&mut captures)?, merger.as_ref());
+
+    Ok(*stats)
+}
+
+/// Update the statistics of a live capture with those reported by libpcap.
+///
+/// Frames that were captured on several devices are only counted as received once.
+fn update_live_stats(stats: &mut SnifferStats, capture_stats: Stat, merger: Option<&Merger>) {
+    stats.update(capture_stats);
+    stats.duplicates = merger.map_or(0, |merger| merger.duplicates);
+    stats.received = stats.received.saturating_sub(stats.duplicates);
+}
+
+/// The statistics reported by libpcap for all captures added up.
+///
+/// Frames captured on several devices are counted once per device.
+fn total_stats(captures: &mut [Capture<dyn Activated>]) -> Result<Stat, Error> {
+    captures.iter_mut().try_fold(
+        Stat {
+            received: 0,
+            dropped: 0,
+            if_dropped: 0,
+        },
+        |total, capture| {
+            let stats = capture.stats()?;
+
+            Ok(Stat {
+                received: total.received + stats.received,
+                dropped: total.dropped + stats.dropped,
+                if_dropped: total.if_dropped + stats.if_dropped,
+            })
+        },
+    )
+}
+
+fn replay<F: FnMut(&pcap::Packet, usize)>(
+    mut capture: Capture<dyn Activated>,
+    mut sink: F,
+    speed: ReplaySpeed,
+    shutdown: Receiver<()>,
+    stats: Arc<Mutex<SnifferStats>>,
+) -> Result<SnifferStats, Error> {
+    let link_type = LinkType::from(capture.get_datalink());
+    let start = Instant::now();
+    let mut first_timestamp = None;
+
+    while shutdown.try_recv() == Err(TryRecvError::Empty) {
+        let packet = match capture.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::NoMorePackets) => {
+                debug!("Replayed all {} packets", lock_stats(&stats).received);
+
+                // wait until the instance is stopped, like a live capture would
+                let _ = shutdown.recv();
+                break;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if speed == ReplaySpeed::Recorded
+            && wait_for_recorded_time(packet.header, start, &mut first_timestamp, &shutdown)
+        {
+            break;
+        }
+
+        sink(&packet, 0);
+        lock_stats(&stats).received += 1;
+        trace!("Replayed {}", Packet::from_capture(packet, link_type));
+    }
+
+    let stats = *lock_stats(&stats);
+
+    Ok(stats)
+}
+
+/// Wait until a packet is due when replaying packets with the time between them as recorded.
+///
+/// Waiting is interrupted as soon as `shutdown` receives a message or its sender is dropped.
+///
+/// Returns whether replaying should stop because waiting was interrupted.
+///
+/// # Arguments
+///
+/// * `header`: The header of the packet to replay next.
+/// * `start`: When replaying started.
+/// * `first_timestamp`: The timestamp of the first replayed packet, set by the first call.
+/// * `shutdown`: The channel on which replaying is stopped.
+pub(crate) fn wait_for_recorded_time(
+    header: &pcap::PacketHeader,
+    start: Instant,
+    first_timestamp: &mut Option<DateTime<Utc>>,
+    shutdown: &Receiver<()>,
+) -> bool {
+    let timestamp = packet::packet_timestamp(header);
+    let offset = timestamp - *first_timestamp.get_or_insert(timestamp);
+    match offset
+        .to_std()
+        .ok()
+        .and_then(|offset| offset.checked_sub(start.elapsed()))
+    {
+        Some(delay) => shutdown.recv_timeout(delay) != Err(RecvTimeoutError::Timeout),
+        None => false,
+    }
+}
+
+/// Receive TZSP datagrams until the instance is stopped, passing the decapsulated frames that
+/// match the capture filter to `sink`.
+///
+/// Datagrams that are malformed or contain frames of another link type are dropped.
+fn receive_tzsp<F: FnMut(&pcap::Packet, usize)>(
+    socket: UdpSocket,
+    linktype: Linktype,
+    program: Option<BpfProgram>,
+    mut sink: F,
+    shutdown: Receiver<()>,
+    stats: Arc<Mutex<SnifferStats>>,
+) -> Result<SnifferStats, Error> {
+    let link_type = LinkType::from(linktype);
+    let mut buffer = vec![0; tzsp::MAX_DATAGRAM_LEN];
+
+    while shutdown.try_recv() == Err(TryRecvError::Empty) {
+        let len = match socket.recv(&mut buffer) {
+            Ok(len) => len,
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                continue
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let frame = match tzsp::decapsulate(&buffer[..len]) {
+            Ok((received, frame)) if received == linktype => frame,
+            Ok((received, _)) => {
+                debug!("Dropping TZSP frame with link type {}", received.0);
+                continue;
+            }
+            Err(error) => {
+                debug!("Dropping TZSP datagram: {error}");
+                continue;
+            }
+        };
+        if program
+            .as_ref()
+            .is_some_and(|program| !program.filter(frame))
+        {
+            continue;
+        }
+
+        let header = packet::packet_header(Utc::now(), frame.len());
+        let packet = pcap::Packet {
+            header: &header,
+            data: frame,
+        };
+        sink(&packet, 0);
+        lock_stats(&stats).received += 1;
+        trace!("Received {}", Packet::from_capture(packet, link_type));
+    }
+
+    let stats = *lock_stats(&stats);
+
+    Ok(stats)
+}
+
+fn lock_stats(stats: &Mutex<SnifferStats>) -> MutexGuard<'_, SnifferStats> {
+    stats.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 /// A handle to a running sniffer instance. It can be stopped with [`SnifferInstance::stop`].
 pub struct SnifferInstance {
     shutdown_channel: Sender<()>,
-    join_handle: JoinHandle<Result<Stat, Error>>,
+    join_handle: JoinHandle<Result<SnifferStats, Error>>,
+    subscribers: Subscribers,
+    stats: Arc<Mutex<SnifferStats>>,
+    /// How to finish the stored capture once it is stopped.
+    finish: Option<Finish>,
 }
 
 impl SnifferInstance {
+    /// The statistics of the capture so far.
+    ///
+    /// The statistics reported by libpcap are only updated about once per second while capturing
+    /// live.
+    pub fn stats(&self) -> SnifferStats {
+        *lock_stats(&self.stats)
+    }
+
+    /// Receive the packets captured from now on through a bounded channel.
+    ///
+    /// Packets are still stored as usual and are received in the form they are stored in, e.g.
+    /// anonymised. If the channel is full because packets are not received quickly enough, new
+    /// packets are dropped for this subscriber so the capture is never blocked. Drop the receiver
+    /// to unsubscribe.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: How many packets the channel can hold.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use std::thread;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::Sniffer;
+    /// let sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// let instance = sniffer.start(Path::new("capture.pcap")).unwrap();
+    ///
+    /// let packets = instance.subscribe(1024);
+    /// thread::spawn(move || {
+    ///     for packet in packets {
+    ///         println!("{packet}");
+    ///     }
+    /// });
+    /// # instance.stop().unwrap();
+    /// ```
+    pub fn subscribe(&self, capacity: usize) -> Receiver<Packet> {
+        self.subscribers.channel(capacity)
+    }
+
+    /// Call `callback` with every packet captured from now on.
+    ///
+    /// The callback runs on the sniffer thread, so it should return quickly to not delay the
+    /// capture. Like [`SnifferInstance::subscribe`], packets are received in the form they are
+    /// stored in.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback`: The function to call with each packet.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::Sniffer;
+    /// let sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// let instance = sniffer.start(Path::new("capture.pcap")).unwrap();
+    ///
+    /// let mut bytes = 0;
+    /// instance.subscribe_with(move |packet| {
+    ///     bytes += packet.len;
+    ///     println!("{bytes} bytes so far");
+    /// });
+    /// # instance.stop().unwrap();
+    /// ```
+    pub fn subscribe_with<F: FnMut(&Packet) + Send + 'static>(&self, callback: F) {
+        self.subscribers.callback(Box::new(callback));
+    }
+
     /// Stop the running sniffer consuming the instance and get the statistics from the run.
     ///
+    /// If the sniffer compresses its captures, the capture is compressed before this returns. A
+    /// pcapng capture is stored without annotations, use [`SnifferInstance::stop_with`] to add
+    /// them.
+    ///
     /// Returns [`SnifferStats`] with statistics about the capture.
     ///
     /// # Examples
@@ -154,40 +979,295 @@ impl SnifferInstance {
     /// let stats = instance.stop().unwrap();
     /// ```
     pub fn stop(self) -> Result<SnifferStats, Error> {
+        self.stop_with(&Annotations::default())
+    }
+
+    /// Stop the running sniffer like [`SnifferInstance::stop`], storing `annotations` in the
+    /// capture if the sniffer writes pcapng files.
+    ///
+    /// Annotations are ignored for pcap files, which cannot store them.
+    ///
+    /// # Arguments
+    ///
+    /// * `annotations`: The metadata to store in the capture.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::path::Path;
+    /// # use chrono::Utc;
+    /// # use varys_network::pcapng::{Annotations, Mark};
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::{CaptureFormat, Sniffer};
+    /// let mut sniffer = Sniffer::from(sniff::default_device().unwrap());
+    /// sniffer.format = CaptureFormat::Pcapng;
+    /// let instance = sniffer.start(Path::new("capture")).unwrap();
+    ///
+    /// let started = Utc::now();
+    /// // ...
+    /// let annotations = Annotations {
+    ///     marks: vec![Mark::new(started, "query started")],
+    ///     ..Annotations::default()
+    /// }
+    /// .with("query", "What time is it?");
+    /// let stats = instance.stop_with(&annotations).unwrap();
+    /// ```
+    pub fn stop_with(self, annotations: &Annotations) -> Result<SnifferStats, Error> {
         info!("Sniffer stopping");
 
         self.shutdown_channel
             .send(())
             .map_err(|_| Error::CannotStop)?;
-        self.join_handle
+        let stats = self
+            .join_handle
             .join()
-            .map_err(|_| Error::NoStatsReceived)?
-            .map(SnifferStats::from)
+            .map_err(|_| Error::NoStatsReceived)??;
+
+        if let Some(finish) = self.finish {
+            finish.run(annotations)?;
+        }
+
+        Ok(stats)
     }
 }
 
-/// Statistics about a finished capture.
+/// How to turn the file written by a [`SnifferInstance`] into the stored capture.
+struct Finish {
+    /// The pcap file the packets were written to.
+    written_path: PathBuf,
+    /// The path of the uncompressed capture in its final format.
+    file_path: PathBuf,
+    format: CaptureFormat,
+    compression: Option<i32>,
+    /// The names of the devices the packets were captured on.
+    interfaces: Vec<String>,
+    /// The index of the device that captured each written packet, if there are several.
+    interface_ids: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Finish {
+    fn run(self, annotations: &Annotations) -> Result<(), Error> {
+        if self.format == CaptureFormat::Pcapng {
+            let mut annotations = annotations.clone();
+            if !self.interfaces.is_empty() {
+                annotations.interfaces = self.interfaces;
+            }
+            let interface_ids = self
+                .interface_ids
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            pcapng::convert_interfaces(
+                &self.written_path,
+                &self.file_path,
+                &annotations,
+                &interface_ids,
+            )?;
+            fs::remove_file(&self.written_path)?;
+        }
+        if let Some(level) = self.compression {
+            compress::compress_file(&self.file_path, level)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A handle to a sniffer instance running continuously. Slices of the packets it keeps in memory
+/// can be saved with [`ContinuousInstance::save`] and it can be stopped with
+/// [`ContinuousInstance::stop`].
+pub struct ContinuousInstance {
+    ring: Arc<Mutex<PacketRing>>,
+    instance: SnifferInstance,
+    compression: Option<i32>,
+    format: CaptureFormat,
+}
+
+impl ContinuousInstance {
+    /// Save all packets received between `from` and `to` to a capture file.
+    ///
+    /// Packets that were received before the start of the rolling window are no longer available.
+    ///
+    /// Returns [`SnifferStats`] about the saved packets or an error if the file could not be
+    /// written. Dropped packets cannot be attributed to a slice, so they are always zero. Compare
+    /// [`SnifferInstance::stats`] before and after the slice to get the packets dropped meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The start of the slice, e.g. the start of an interaction minus a pre-roll.
+    /// * `to`: The end of the slice, e.g. the end of an interaction plus a post-roll.
+    /// * `file_path`: The path to which the packets are written. The extension of the
+    ///   [`CaptureFormat`] will be added if it isn't already in the path. If the sniffer compresses
+    ///   its captures, `.zst` is appended as well, see [`Sniffer::capture_path`].
+    pub fn save(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        file_path: &Path,
+    ) -> Result<SnifferStats, Error> {
+        self.save_with(from, to, file_path, &Annotations::default())
+    }
+
+    /// Save a slice of packets like [`ContinuousInstance::save`], storing `annotations` in the
+    /// capture if the sniffer writes pcapng files.
+    ///
+    /// Marks are attached to the first packet received at or after their timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: The start of the slice.
+    /// * `to`: The end of the slice.
+    /// * `file_path`: The path to which the packets are written.
+    /// * `annotations`: The metadata to store in the capture.
+    pub fn save_with(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        file_path: &Path,
+        annotations: &Annotations,
+    ) -> Result<SnifferStats, Error> {
+        let file_path = file_path.with_extension(self.format.extension());
+
+        let stats = self
+            .ring
+            .lock()
+            .map_err(|_| Error::CaptureBufferUnavailable)?
+            .write_slice(from, to, &file_path, self.format, annotations)?;
+
+        if let Some(level) = self.compression {
+            compress::compress_file(file_path, level)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// The instance that captures the packets, e.g. to subscribe to them with
+    /// [`SnifferInstance::subscribe`].
+    pub fn instance(&self) -> &SnifferInstance {
+        &self.instance
+    }
+
+    /// Stop the running sniffer consuming the instance and get the statistics from the run.
+    ///
+    /// Returns [`SnifferStats`] with statistics about the whole capture.
+    pub fn stop(self) -> Result<SnifferStats, Error> {
+        self.instance.stop()
+    }
+}
+
+/// Statistics about a capture.
 ///
-/// `received` is the number of packets received in total.
+/// `received` is the number of packets received in total. For live captures, this is reported
+/// by libpcap and may include packets that were not stored, depending on the platform.
+///
+/// `stored` is the number of packets written to the capture.
 ///
 /// `buffer_dropped` is the number of packets dropped because the buffer for incoming packets was
 /// too small or packets were not processed quickly enough.
 ///
 /// `interface_dropped` is the number of packets dropped by the network interface.
-#[derive(Debug)]
+///
+/// `bytes` is the total length of the stored packets, including data that was not captured.
+///
+/// `packets_in` and `packets_out` are the numbers of stored packets received and sent by the
+/// device at [`Sniffer::relative_to`].
+///
+/// `first_packet` and `last_packet` are the timestamps of the first and last stored packet.
+///
+/// `duplicates` is the number of frames that were not stored because they were already captured
+/// on another device. `received` counts each of them once.
+#[derive(Copy, Clone, Debug, Default)]
 pub struct SnifferStats {
     pub received: u32,
+    pub stored: u32,
     pub buffer_dropped: u32,
     pub interface_dropped: u32,
+    pub bytes: u64,
+    pub packets_in: u32,
+    pub packets_out: u32,
+    pub first_packet: Option<DateTime<Utc>>,
+    pub last_packet: Option<DateTime<Utc>>,
+    pub duplicates: u32,
+}
+
+impl SnifferStats {
+    /// Count a stored packet.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet`: The packet to count.
+    /// * `relative_to`: The address of the device to count incoming and outgoing packets for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::net::{IpAddr, Ipv4Addr};
+    /// # use std::str::FromStr;
+    /// # use chrono::Utc;
+    /// # use varys_network::address::{DeviceAddress, MacAddress};
+    /// # use varys_network::packet::PacketHeader;
+    /// # use varys_network::sniff::SnifferStats;
+    /// let assistant_ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+    /// let assistant = DeviceAddress::new(
+    ///     MacAddress::from_str("00:1a:2b:3c:4d:5e").unwrap(),
+    ///     Some(assistant_ip),
+    /// );
+    /// // captured on a tun device, so the packet has no MAC addresses
+    /// let packet = PacketHeader {
+    ///     timestamp: Utc::now(),
+    ///     len: 60,
+    ///     source: None,
+    ///     destination: None,
+    ///     source_ip: Some(assistant_ip),
+    ///     destination_ip: Some(IpAddr::V4(Ipv4Addr::new(17, 253, 144, 10))),
+    /// };
+    ///
+    /// let mut stats = SnifferStats::default();
+    /// stats.count(&packet, Some(&assistant));
+    ///
+    /// assert_eq!(stats.stored, 1);
+    /// assert_eq!(stats.bytes, 60);
+    /// assert_eq!(stats.packets_out, 1);
+    /// assert_eq!(stats.first_packet, Some(packet.timestamp));
+    /// ```
+    pub fn count<P: CapturedPacket>(&mut self, packet: &P, relative_to: Option<&DeviceAddress>) {
+        let timestamp = packet.timestamp();
+
+        self.stored += 1;
+        self.bytes += packet.length() as u64;
+        match relative_to.and_then(|address| packet.direction(address)) {
+            Some(PacketDirection::In) => self.packets_in += 1,
+            Some(PacketDirection::Out) => self.packets_out += 1,
+            None => {}
+        }
+        self.first_packet = Some(
+            self.first_packet
+                .map_or(timestamp, |first| first.min(timestamp)),
+        );
+        self.last_packet = Some(
+            self.last_packet
+                .map_or(timestamp, |last| last.max(timestamp)),
+        );
+    }
+
+    /// Update the statistics reported by libpcap.
+    fn update(&mut self, stats: Stat) {
+        self.received = stats.received;
+        self.buffer_dropped = stats.dropped;
+        self.interface_dropped = stats.if_dropped;
+    }
+
+    /// Whether any packets were dropped.
+    pub fn has_drops(&self) -> bool {
+        self.buffer_dropped > 0 || self.interface_dropped > 0
+    }
 }
 
 impl From<Stat> for SnifferStats {
     fn from(stats: Stat) -> Self {
-        SnifferStats {
-            received: stats.received,
-            buffer_dropped: stats.dropped,
-            interface_dropped: stats.if_dropped,
-        }
+        let mut sniffer_stats = SnifferStats::default();
+        sniffer_stats.update(stats);
+
+        sniffer_stats
     }
 }
 
@@ -195,9 +1275,20 @@ impl Display for SnifferStats {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Captured {} packets (buffer dropped: {}, interface dropped: {})",
-            self.received, self.buffer_dropped, self.interface_dropped
-        )
+            "Captured {} packets, stored {} (buffer dropped: {}, interface dropped: {}, {} bytes, in: {}, out: {})",
+            self.received,
+            self.stored,
+            self.buffer_dropped,
+            self.interface_dropped,
+            self.bytes,
+            self.packets_in,
+            self.packets_out
+        )?;
+        if self.duplicates > 0 {
+            write!(f, ", dropped {} duplicates", self.duplicates)?;
+        }
+
+        Ok(())
     }
 }
 
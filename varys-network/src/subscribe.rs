@@ -0,0 +1,84 @@
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use log::{debug, warn};
+
+use crate::link::LinkType;
+use crate::packet::{FromCapture, Packet};
+
+/// A callback that is called with every packet a sniffer captures.
+pub type PacketCallback = Box<dyn FnMut(&Packet) + Send>;
+
+enum Subscriber {
+    Channel {
+        sender: SyncSender<Packet>,
+        /// How many packets were dropped because the channel was full.
+        dropped: usize,
+    },
+    Callback(PacketCallback),
+}
+
+/// The subscribers of a running sniffer, shared between the sniffer thread and its instance.
+///
+/// Subscribers receive every packet after it was stored, in the form it was stored in, e.g.
+/// anonymised if the sniffer has an anonymiser.
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers(Arc<Mutex<Vec<Subscriber>>>);
+
+impl Subscribers {
+    /// Add a subscriber that receives packets through a bounded channel.
+    ///
+    /// Packets are dropped for this subscriber if the channel is full, so a slow subscriber never
+    /// blocks the capture.
+    pub fn channel(&self, capacity: usize) -> Receiver<Packet> {
+        let (sender, receiver) = sync_channel(capacity);
+        self.lock().push(Subscriber::Channel { sender, dropped: 0 });
+
+        receiver
+    }
+
+    /// Add a subscriber that is called with every packet on the sniffer thread.
+    pub fn callback(&self, callback: PacketCallback) {
+        self.lock().push(Subscriber::Callback(callback));
+    }
+
+    /// Pass a packet to all subscribers and remove the ones whose receiver was dropped.
+    pub fn publish(&self, packet: &pcap::Packet, link_type: LinkType) {
+        let mut subscribers = self.lock();
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let packet = Packet::from_capture(
+            pcap::Packet {
+                header: packet.header,
+                data: packet.data,
+            },
+            link_type,
+        );
+        subscribers.retain_mut(|subscriber| match subscriber {
+            Subscriber::Channel { sender, dropped } => match sender.try_send(packet.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    if *dropped == 0 {
+                        warn!("A packet subscriber is too slow, dropping packets for it");
+                    }
+                    *dropped += 1;
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    debug!("A packet subscriber disconnected after {dropped} packets were dropped");
+                    false
+                }
+            },
+            Subscriber::Callback(callback) => {
+                callback(&packet);
+                true
+            }
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Subscriber>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
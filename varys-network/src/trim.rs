@@ -0,0 +1,266 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info};
+use pcap::Linktype;
+
+use crate::address::DeviceAddress;
+use crate::burst;
+use crate::error::Error;
+use crate::packet;
+use crate::packet::CapturedPacket;
+use crate::pcapng::Annotations;
+use crate::rewrite::{self, Rewrite, Rewriter};
+
+/// The suffix added to the file name of trimmed captures, before their extensions.
+const TRIMMED_SUFFIX: &str = "-trimmed";
+
+/// A time window of a capture, including its start and end.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TrimWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TrimWindow {
+    /// Create a window between two points in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `start`: The start of the window.
+    /// * `end`: The end of the window.
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        TrimWindow { start, end }
+    }
+
+    /// Extend the window at both ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `before`: How much earlier the window should start.
+    /// * `after`: How much later the window should end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use chrono::{Duration, TimeZone, Utc};
+    /// # use varys_network::trim::TrimWindow;
+    /// let start = Utc.timestamp_opt(10, 0).unwrap();
+    /// let window =
+    ///     TrimWindow::new(start, start).padded(Duration::seconds(1), Duration::seconds(5));
+    ///
+    /// assert!(window.contains(start - Duration::seconds(1)));
+    /// assert!(window.contains(start + Duration::seconds(5)));
+    /// assert!(!window.contains(start + Duration::seconds(6)));
+    /// ```
+    pub fn padded(self, before: Duration, after: Duration) -> Self {
+        TrimWindow {
+            start: self.start - before,
+            end: self.end + after,
+        }
+    }
+
+    /// Whether a point in time lies within the window.
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        self.start <= timestamp && timestamp <= self.end
+    }
+}
+
+impl Display for TrimWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to {}", self.start, self.end)
+    }
+}
+
+impl Rewriter for TrimWindow {
+    /// Drop the marks outside the window, which have no packet to be attached to.
+    fn prepare(
+        &mut self,
+        _linktype: Linktype,
+        mut annotations: Annotations,
+    ) -> Result<Annotations, Error> {
+        annotations
+            .marks
+            .retain(|mark| self.contains(mark.timestamp));
+
+        Ok(annotations)
+    }
+
+    fn rewrite(&mut self, packet: &pcap::Packet) -> Rewrite {
+        if self.contains(packet::packet_timestamp(packet.header)) {
+            Rewrite::Keep
+        } else {
+            Rewrite::Drop
+        }
+    }
+}
+
+/// The timestamp of the first packet sent or received by a device.
+///
+/// Returns `None` if the device did not send or receive any packet.
+///
+/// # Arguments
+///
+/// * `packets`: The captured packets.
+/// * `address`: The address of the device, e.g. the voice assistant.
+pub fn first_packet<P: CapturedPacket>(
+    packets: &[P],
+    address: &DeviceAddress,
+) -> Option<DateTime<Utc>> {
+    packets
+        .iter()
+        .filter(|packet| packet.direction(address).is_some())
+        .map(|packet| packet.timestamp())
+        .min()
+}
+
+/// The first burst of traffic sent or received by a device.
+///
+/// The burst starts at the first packet of the device and ends at its last packet before a pause
+/// longer than `gap`, see [`burst::bursts`].
+///
+/// This separates the traffic of a response from traffic that follows later, e.g. music that is
+/// streamed after the response.
+///
+/// Returns `None` if the device did not send or receive any packet.
+///
+/// # Arguments
+///
+/// * `packets`: The captured packets.
+/// * `address`: The address of the device, e.g. the voice assistant.
+/// * `gap`: How long the device must be silent to end the burst.
+///
+/// # Examples
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use chrono::{Duration, TimeZone, Utc};
+/// # use varys_network::address::{DeviceAddress, MacAddress};
+/// # use varys_network::packet::PacketHeader;
+/// # use varys_network::trim;
+/// let assistant = MacAddress::from_str("f0:18:98:12:34:56").unwrap();
+/// let router = MacAddress::from_str("00:00:5e:00:53:01").unwrap();
+/// let start = Utc.timestamp_opt(0, 0).unwrap();
+/// let packet = |seconds| PacketHeader {
+///     timestamp: start + Duration::seconds(seconds),
+///     len: 1000,
+///     source: Some(assistant),
+///     destination: Some(router),
+///     source_ip: None,
+///     destination_ip: None,
+/// };
+/// let packets = [packet(1), packet(2), packet(3), packet(30), packet(31)];
+///
+/// let burst = trim::first_burst(&packets, &DeviceAddress::from(assistant), Duration::seconds(5))
+///     .unwrap();
+/// assert_eq!(burst.start, start + Duration::seconds(1));
+/// assert_eq!(burst.end, start + Duration::seconds(3));
+/// ```
+pub fn first_burst<P: CapturedPacket>(
+    packets: &[P],
+    address: &DeviceAddress,
+    gap: Duration,
+) -> Option<TrimWindow> {
+    burst::bursts(packets, address, gap)
+        .first()
+        .map(|burst| TrimWindow::new(burst.start_time, burst.end_time))
+}
+
+/// The path to store a trimmed copy of a capture at.
+///
+/// `-trimmed` is added to the file name before all of its extensions, so the format and compression
+/// of the capture are still recognised. The path of a capture that is already trimmed is returned
+/// unchanged.
+///
+/// # Arguments
+///
+/// * `path`: The path to the capture.
+///
+/// # Examples
+///
+/// ```
+/// # use std::path::{Path, PathBuf};
+/// # use varys_network::trim;
+/// let trimmed = trim::trimmed_path(Path::new("data/s1i2-capture.pcap.zst"));
+///
+/// assert_eq!(trimmed, PathBuf::from("data/s1i2-capture-trimmed.pcap.zst"));
+/// assert_eq!(trim::trimmed_path(&trimmed), trimmed);
+/// assert_eq!(trim::original_path(&trimmed), Some(PathBuf::from("data/s1i2-capture.pcap.zst")));
+/// ```
+pub fn trimmed_path(path: &Path) -> PathBuf {
+    if original_path(path).is_some() {
+        return path.to_path_buf();
+    }
+
+    let (stem, extensions) = split_file_name(path);
+    path.with_file_name(format!("{stem}{TRIMMED_SUFFIX}{extensions}"))
+}
+
+/// The path of the capture a trimmed capture was cut from, or `None` if the capture is not trimmed.
+///
+/// # Arguments
+///
+/// * `path`: The path to the trimmed capture, see [`trimmed_path`].
+pub fn original_path(path: &Path) -> Option<PathBuf> {
+    let (stem, extensions) = split_file_name(path);
+    let stem = stem.strip_suffix(TRIMMED_SUFFIX)?;
+
+    Some(path.with_file_name(format!("{stem}{extensions}")))
+}
+
+/// Split a file name at its first dot into the stem and all extensions, including the dot.
+pub(crate) fn split_file_name(path: &Path) -> (String, String) {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match name.find('.') {
+        Some(index) => (name[..index].to_string(), name[index..].to_string()),
+        None => (name, String::new()),
+    }
+}
+
+/// Cut a capture to a time window, writing the packets within it to a new file.
+///
+/// The capture keeps its format and compression. The annotations of a pcapng file are kept, but
+/// marks outside the window are dropped. `from` and `to` may be the same path, in which case the
+/// file is replaced.
+///
+/// Returns the number of packets written or an error if the file could not be read or written.
+///
+/// # Arguments
+///
+/// * `from`: The capture to trim.
+/// * `to`: Where to write the trimmed capture.
+/// * `window`: The packets to keep by their timestamp.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use std::str::FromStr;
+/// # use chrono::Duration;
+/// # use varys_network::address::{DeviceAddress, MacAddress};
+/// # use varys_network::{packet, trim};
+/// let path = Path::new("capture.pcap");
+/// let assistant = DeviceAddress::from(MacAddress::from_str("f0:18:98:12:34:56").unwrap());
+/// let packets = packet::read_headers(path)
+///     .unwrap()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+///
+/// if let Some(burst) = trim::first_burst(&packets, &assistant, Duration::seconds(5)) {
+///     trim::trim_file(path, Path::new("trimmed.pcap"), &burst).unwrap();
+/// }
+/// ```
+pub fn trim_file(from: &Path, to: &Path, window: &TrimWindow) -> Result<usize, Error> {
+    info!("Trimming {from:?} to {window}, writing to {to:?}...");
+
+    let written = rewrite::rewrite_file(from, to, &mut { *window })?;
+
+    debug!("Kept {written} packets");
+
+    Ok(written)
+}
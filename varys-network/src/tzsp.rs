@@ -0,0 +1,187 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
+
+use log::{debug, info};
+use pcap::Linktype;
+
+use crate::compress;
+use crate::error::Error;
+use crate::sniff;
+use crate::sniff::ReplaySpeed;
+
+/// The UDP port TZSP streams are sent to by default.
+pub const DEFAULT_PORT: u16 = 37008;
+/// The largest datagram that is received, enough for any frame with its TZSP header.
+pub(crate) const MAX_DATAGRAM_LEN: usize = 65_535;
+
+const VERSION: u8 = 1;
+/// The type of a packet that was received by the sniffer.
+const TYPE_RECEIVED: u8 = 0;
+const TAG_PADDING: u8 = 0;
+const TAG_END: u8 = 1;
+/// The TZSP encapsulation ids and the link types they correspond to.
+const ENCAPSULATIONS: [(u16, i32); 4] = [
+    (1, 1),     // Ethernet
+    (18, 105),  // 802.11
+    (119, 119), // Prism
+    (127, 163), // AVS
+];
+
+/// Decapsulate a frame from a TZSP datagram, as sent by the packet sniffers of MikroTik and
+/// OpenWrt routers.
+///
+/// Returns the link type and the frame or an error if the datagram is malformed, does not contain
+/// a received packet or uses an unknown encapsulation.
+///
+/// See <https://en.wikipedia.org/wiki/TZSP> for details.
+///
+/// # Arguments
+///
+/// * `datagram`: The payload of the UDP datagram.
+///
+/// # Examples
+///
+/// ```
+/// # use pcap::Linktype;
+/// # use varys_network::tzsp;
+/// let frame = [0xff; 60];
+/// let datagram = tzsp::encapsulate(Linktype::ETHERNET, &frame).unwrap();
+///
+/// let (linktype, decapsulated) = tzsp::decapsulate(&datagram).unwrap();
+/// assert_eq!(linktype, Linktype::ETHERNET);
+/// assert_eq!(decapsulated, frame);
+/// ```
+pub fn decapsulate(datagram: &[u8]) -> Result<(Linktype, &[u8]), Error> {
+    let header = datagram
+        .get(..4)
+        .ok_or(Error::MalformedPacket("TZSP header is too short"))?;
+    if header[0] != VERSION {
+        return Err(Error::MalformedPacket("unknown TZSP version"));
+    }
+    if header[1] != TYPE_RECEIVED {
+        return Err(Error::MalformedPacket(
+            "TZSP packet does not contain a frame",
+        ));
+    }
+    let encapsulation = u16::from_be_bytes([header[2], header[3]]);
+    let linktype = ENCAPSULATIONS
+        .iter()
+        .find(|(id, _)| *id == encapsulation)
+        .map(|(_, linktype)| Linktype(*linktype))
+        .ok_or(Error::MalformedPacket("unknown TZSP encapsulation"))?;
+
+    // skip the tagged fields, which end with an end tag
+    let mut offset = 4;
+    loop {
+        match *datagram
+            .get(offset)
+            .ok_or(Error::MalformedPacket("TZSP tags are not terminated"))?
+        {
+            TAG_END => break,
+            TAG_PADDING => offset += 1,
+            _ => {
+                let len = *datagram
+                    .get(offset + 1)
+                    .ok_or(Error::MalformedPacket("TZSP tag is too short"))?;
+                offset += 2 + len as usize;
+            }
+        }
+    }
+
+    Ok((linktype, &datagram[offset + 1..]))
+}
+
+/// Encapsulate a frame in a TZSP datagram without any tags.
+///
+/// Returns an error if the link type cannot be encapsulated.
+///
+/// # Arguments
+///
+/// * `linktype`: The link type of the frame.
+/// * `frame`: The captured frame.
+pub fn encapsulate(linktype: Linktype, frame: &[u8]) -> Result<Vec<u8>, Error> {
+    let encapsulation = ENCAPSULATIONS
+        .iter()
+        .find(|(_, other)| *other == linktype.0)
+        .map(|(id, _)| *id)
+        .ok_or(Error::UnsupportedLinkType(linktype.0))?;
+
+    let mut datagram = Vec::with_capacity(5 + frame.len());
+    datagram.extend_from_slice(&[VERSION, TYPE_RECEIVED]);
+    datagram.extend_from_slice(&encapsulation.to_be_bytes());
+    datagram.push(TAG_END);
+    datagram.extend_from_slice(frame);
+
+    Ok(datagram)
+}
+
+/// Send the packets of a capture as a TZSP stream, like a router would.
+///
+/// This is useful to test receiving a remote capture with
+/// [`PacketSource::Tzsp`](crate::sniff::PacketSource::Tzsp) locally.
+///
+/// Sending stops early once `stop` receives a message or its sender is dropped.
+///
+/// Returns the number of packets sent or an error if the capture could not be read, its link type
+/// cannot be encapsulated or a packet could not be sent.
+///
+/// # Arguments
+///
+/// * `path`: The capture to send, which may be compressed.
+/// * `target`: The address the stream is sent to.
+/// * `speed`: How fast to send the packets.
+/// * `stop`: The channel on which sending is stopped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::net::SocketAddr;
+/// # use std::path::Path;
+/// # use std::sync::mpsc::channel;
+/// # use varys_network::sniff::ReplaySpeed;
+/// # use varys_network::tzsp;
+/// let target = SocketAddr::from(([127, 0, 0, 1], tzsp::DEFAULT_PORT));
+/// let (_stop_sender, stop) = channel();
+///
+/// tzsp::send_capture(Path::new("capture.pcap"), target, ReplaySpeed::Recorded, &stop).unwrap();
+/// ```
+pub fn send_capture(
+    path: &Path,
+    target: SocketAddr,
+    speed: ReplaySpeed,
+    stop: &Receiver<()>,
+) -> Result<usize, Error> {
+    info!("Sending {path:?} to {target} as TZSP...");
+
+    let (mut capture, _decompressed) = compress::open_capture(path)?;
+    let linktype = capture.get_datalink();
+    let socket = match target {
+        SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+        SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+    };
+    let start = Instant::now();
+    let mut first_timestamp = None;
+    let mut sent = 0;
+
+    loop {
+        let packet = match capture.next_packet() {
+            Ok(packet) => packet,
+            Err(pcap::Error::NoMorePackets) => break,
+            Err(error) => return Err(error.into()),
+        };
+
+        if speed == ReplaySpeed::Recorded
+            && sniff::wait_for_recorded_time(packet.header, start, &mut first_timestamp, stop)
+        {
+            break;
+        }
+        socket.send_to(&encapsulate(linktype, packet.data)?, target)?;
+        sent += 1;
+    }
+
+    debug!("Sent {sent} packets");
+
+    Ok(sent)
+}
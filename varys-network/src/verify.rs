@@ -0,0 +1,230 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+
+use crate::address::DeviceAddress;
+use crate::error::Error;
+use crate::packet;
+use crate::packet::CapturedPacket;
+use crate::rewrite::{self, Rewrite, Rewriter};
+use crate::trim;
+
+/// The suffix added to the file name of repaired captures, before their extensions.
+const REPAIRED_SUFFIX: &str = "-repaired";
+
+/// What is wrong with a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaptureProblem {
+    /// The file does not exist.
+    Missing,
+    /// The file cannot be opened as a capture, e.g. because its header is cut off.
+    Unreadable(String),
+    /// Reading stopped with an error before the end of the file, usually because it was cut off
+    /// while capturing. The packets before the error can be salvaged with [`repair_file`].
+    Truncated(String),
+    /// The capture does not contain any packets.
+    Empty,
+    /// None of the packets was sent or received by the voice assistant.
+    NoAssistantTraffic,
+}
+
+impl CaptureProblem {
+    /// A short name of the problem for machine-readable reports, e.g. `truncated`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CaptureProblem::Missing => "missing",
+            CaptureProblem::Unreadable(_) => "unreadable",
+            CaptureProblem::Truncated(_) => "truncated",
+            CaptureProblem::Empty => "empty",
+            CaptureProblem::NoAssistantTraffic => "no-assistant-traffic",
+        }
+    }
+}
+
+impl Display for CaptureProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureProblem::Missing => write!(f, "the capture is missing"),
+            CaptureProblem::Unreadable(error) => write!(f, "the capture is unreadable ({error})"),
+            CaptureProblem::Truncated(error) => write!(f, "the capture is truncated ({error})"),
+            CaptureProblem::Empty => write!(f, "the capture is empty"),
+            CaptureProblem::NoAssistantTraffic => {
+                write!(f, "the capture contains no traffic of the assistant")
+            }
+        }
+    }
+}
+
+/// The result of verifying a capture with [`verify_file`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureCheck {
+    /// The number of packets that could be read.
+    pub packets: usize,
+    /// The number of readable packets sent or received by the voice assistant.
+    pub assistant_packets: usize,
+    /// The most severe problem of the capture or `None` if it is fine.
+    pub problem: Option<CaptureProblem>,
+}
+
+impl CaptureCheck {
+    fn failed(problem: CaptureProblem) -> Self {
+        CaptureCheck {
+            packets: 0,
+            assistant_packets: 0,
+            problem: Some(problem),
+        }
+    }
+
+    /// Whether the capture has no problems.
+    pub fn is_ok(&self) -> bool {
+        self.problem.is_none()
+    }
+
+    /// Whether packets can be salvaged from the capture with [`repair_file`].
+    pub fn is_repairable(&self) -> bool {
+        matches!(self.problem, Some(CaptureProblem::Truncated(_))) && self.packets > 0
+    }
+}
+
+/// Check whether a capture can be read completely and contains traffic of the voice assistant.
+///
+/// Problems are reported in the returned [`CaptureCheck`] instead of as an error, so all captures
+/// of a dataset can be checked in one pass.
+///
+/// # Arguments
+///
+/// * `path`: The capture to check, which may be compressed.
+/// * `address`: The address of the voice assistant or `None` to skip checking for its traffic.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use varys_network::verify;
+/// let check = verify::verify_file(Path::new("capture.pcap"), None);
+///
+/// if let Some(problem) = &check.problem {
+///     println!("{problem} after {} packets", check.packets);
+/// }
+/// ```
+pub fn verify_file(path: &Path, address: Option<&DeviceAddress>) -> CaptureCheck {
+    if !path.exists() {
+        return CaptureCheck::failed(CaptureProblem::Missing);
+    }
+
+    let headers = match packet::read_headers(path) {
+        Ok(headers) => headers,
+        Err(error) => return CaptureCheck::failed(CaptureProblem::Unreadable(error.to_string())),
+    };
+    let mut check = CaptureCheck {
+        packets: 0,
+        assistant_packets: 0,
+        problem: None,
+    };
+
+    for header in headers {
+        match header {
+            Ok(header) => {
+                check.packets += 1;
+                if address.is_some_and(|address| header.direction(address).is_some()) {
+                    check.assistant_packets += 1;
+                }
+            }
+            Err(error) => {
+                check.problem = Some(CaptureProblem::Truncated(error.to_string()));
+                break;
+            }
+        }
+    }
+
+    if check.problem.is_none() {
+        if check.packets == 0 {
+            check.problem = Some(CaptureProblem::Empty);
+        } else if address.is_some() && check.assistant_packets == 0 {
+            check.problem = Some(CaptureProblem::NoAssistantTraffic);
+        }
+    }
+    debug!("Verified {path:?}: {check:?}");
+
+    check
+}
+
+/// Keeps all packets that can be read and stops at the first that cannot.
+struct Salvage;
+
+impl Rewriter for Salvage {
+    fn rewrite(&mut self, _packet: &pcap::Packet) -> Rewrite {
+        Rewrite::Keep
+    }
+
+    fn read_failed(&mut self, error: Error) -> Result<(), Error> {
+        warn!("Stopped reading at {error}");
+
+        Ok(())
+    }
+}
+
+/// The path to store a repaired copy of a capture at.
+///
+/// `-repaired` is added to the file name before all of its extensions, like
+/// [`trim::trimmed_path`]. The path of a capture that is already repaired is returned unchanged.
+///
+/// # Arguments
+///
+/// * `path`: The path to the capture.
+///
+/// # Examples
+///
+/// ```
+/// # use std::path::{Path, PathBuf};
+/// # use varys_network::verify;
+/// let repaired = verify::repaired_path(Path::new("data/s1i2-capture.pcap"));
+///
+/// assert_eq!(repaired, PathBuf::from("data/s1i2-capture-repaired.pcap"));
+/// assert_eq!(verify::repaired_path(&repaired), repaired);
+/// ```
+pub fn repaired_path(path: &Path) -> PathBuf {
+    let (stem, extensions) = trim::split_file_name(path);
+    if stem.ends_with(REPAIRED_SUFFIX) {
+        return path.to_path_buf();
+    }
+
+    path.with_file_name(format!("{stem}{REPAIRED_SUFFIX}{extensions}"))
+}
+
+/// Salvage the packets of a truncated capture, writing all packets before the first one that
+/// cannot be read to a new file.
+///
+/// The capture keeps its format and compression. The annotations of a pcapng file are kept if
+/// they can still be read. `from` and `to` may be the same path, in which case the file is
+/// replaced.
+///
+/// Returns the number of packets written or an error if the file cannot be opened at all or could
+/// not be written.
+///
+/// # Arguments
+///
+/// * `from`: The capture to repair.
+/// * `to`: Where to write the repaired capture.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::path::Path;
+/// # use varys_network::verify;
+/// let path = Path::new("capture.pcap");
+///
+/// if verify::verify_file(path, None).is_repairable() {
+///     verify::repair_file(path, &verify::repaired_path(path)).unwrap();
+/// }
+/// ```
+pub fn repair_file(from: &Path, to: &Path) -> Result<usize, Error> {
+    info!("Repairing {from:?}, writing to {to:?}...");
+
+    let written = rewrite::rewrite_file(from, to, &mut Salvage)?;
+
+    debug!("Salvaged {written} packets");
+
+    Ok(written)
+}
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use log::warn;
+use varys_audio::tts::Speaker;
 
 use crate::assistant::alexa::Alexa;
 use crate::assistant::interactor::Interactor;
@@ -38,14 +39,19 @@ pub trait VoiceAssistant {
 
     /// Set up voice recognition for a voice assistant.
     ///
+    /// # Arguments
+    ///
+    /// * `speaker`: The speaker to set up voice recognition with.
+    ///
     /// # Examples
     ///
     /// ```no_run
     /// # use varys::assistant::{from, VoiceAssistant};
+    /// # use varys_audio::tts::Speaker;
     /// # let assistant = from("Siri");
-    /// assistant.setup().unwrap();
+    /// assistant.setup(Speaker::new().unwrap()).unwrap();
     /// ```
-    fn setup(&self) -> Result<(), Error>;
+    fn setup(&self, speaker: Speaker) -> Result<(), Error>;
 
     fn prepare_queries(&self, queries: &mut Vec<Query>);
 
@@ -70,17 +76,19 @@ pub trait VoiceAssistant {
     ///$
     /// # Arguments
     ///
+    /// * `speaker`: The speaker to say the sentences with.
     /// * `voices`: The voices to test.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use varys::assistant::{from, VoiceAssistant};
+    /// # use varys_audio::tts::Speaker;
     /// # let assistant = from("Siri");
     /// let voices = vec!["Zoe".to_string(), "Isha".to_string()];
-    /// assistant.test_voices(voices).unwrap();
+    /// assistant.test_voices(Speaker::new().unwrap(), voices).unwrap();
     /// ```
-    fn test_voices(&self, voices: Vec<String>) -> Result<(), Error>;
+    fn test_voices(&self, speaker: Speaker, voices: Vec<String>) -> Result<(), Error>;
 
     /// The length of silence indicating that the assistant is done talking.
     fn silence_after_talking(&self) -> Duration;
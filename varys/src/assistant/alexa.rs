@@ -27,11 +27,9 @@ impl VoiceAssistant for Alexa {
         "Alexa".to_string()
     }
 
-    fn setup(&self) -> Result<(), Error> {
+    fn setup(&self, mut speaker: Speaker) -> Result<(), Error> {
         info!("Starting Alexa setup...");
 
-        let mut speaker = Speaker::new()?;
-
         let voice = interact::user_input(
             &format!(
                 "Choose the voice to set up (The highest quality voices on macOS are {}):",
@@ -123,11 +121,9 @@ impl VoiceAssistant for Alexa {
         Ok(())
     }
 
-    fn test_voices(&self, voices: Vec<String>) -> Result<(), Error> {
+    fn test_voices(&self, mut speaker: Speaker, voices: Vec<String>) -> Result<(), Error> {
         info!("Testing Alexa voices...");
 
-        let mut speaker = Speaker::new()?;
-
         for voice in voices {
             interact::user_confirmation(&format!("Test {}", voice))?;
             speaker.set_voice(&voice).unwrap();
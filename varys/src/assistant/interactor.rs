@@ -1,12 +1,18 @@
 use std::collections::VecDeque;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
 
+use chrono::Utc;
 use log::{error, info, warn};
 use rand::prelude::SliceRandom;
 
 use varys_audio::audio::AudioData;
 use varys_audio::listen::Listener;
+use varys_audio::stt::config::RecogniserConfig;
+use varys_audio::stt::segment::Segment;
 use varys_audio::stt::transcribe::Transcribe;
 use varys_audio::stt::transcriber::{TranscriberHandle, TranscriberReceiver, TranscriberSender};
 use varys_audio::tts::Speaker;
@@ -14,38 +20,86 @@ use varys_database::connection::DatabaseConnection;
 use varys_database::database::interaction::Interaction;
 use varys_database::database::interactor_config::InteractorConfig;
 use varys_database::database::session::Session;
+use varys_database::database::transcript::Transcript;
 use varys_database::file::DataType;
 use varys_database::{database, file};
-use varys_network::sniff;
-use varys_network::sniff::Sniffer;
+use varys_network::address::{DeviceAddress, MacAddress};
+use varys_network::anonymise::{Anonymiser, Truncation};
+use varys_network::pcapng::{Annotations, Mark};
+use varys_network::sniff::{
+    CaptureFormat, ContinuousInstance, PacketSource, Sniffer, SnifferStats,
+};
 
 use crate::assistant::VoiceAssistant;
 use crate::error::Error;
 use crate::monitoring;
 use crate::query::Query;
 
-pub struct TranscribeInteraction(Interaction);
+pub struct TranscribeInteraction(Interaction, Vec<Segment>);
 
 impl Transcribe for TranscribeInteraction {
     fn transcribed(&mut self, text: String) {
         self.0.response = Some(text);
     }
+
+    fn segmented(&mut self, segments: Vec<Segment>) {
+        self.transcribed(varys_audio::stt::segment::text(&segments));
+        self.1 = segments;
+    }
+
+    fn query(&self) -> Option<&str> {
+        Some(&self.0.query)
+    }
 }
 
 impl From<Interaction> for TranscribeInteraction {
     fn from(interaction: Interaction) -> Self {
-        Self(interaction)
+        Self(interaction, Vec::new())
     }
 }
 
+/// How much traffic to keep around each interaction when capturing continuously.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CaptureRoll {
+    /// How much traffic before the start of an interaction to keep.
+    pub pre_roll: Duration,
+    /// How much traffic after the end of an interaction to keep.
+    pub post_roll: Duration,
+}
+
+/// How long a continuous capture keeps packets in addition to the pre- and post-roll.
+const CONTINUOUS_CAPTURE_WINDOW: Duration = Duration::from_secs(300);
+
 pub struct Interactor {
     pub listener: Listener,
     sniffer: Sniffer,
+    /// If this is set, traffic is captured continuously during a session and each interaction's
+    /// capture is cut from it, including the pre- and post-roll. Otherwise, a new capture is
+    /// started for each interaction.
+    pub capture_roll: Option<CaptureRoll>,
+    continuous_capture: Option<(ContinuousInstance, CaptureRoll)>,
+    /// If this is set, captures are anonymised before they are stored. MAC and IP addresses are
+    /// pseudonymised consistently within each session and the stored assistant MAC address is
+    /// the pseudonym of the real one.
+    pub anonymise: Option<Truncation>,
+    /// The IP address of the assistant.
+    ///
+    /// This is needed to tell the direction of packets if the capture's link type does not carry
+    /// MAC addresses, e.g. on a tun device. In that case, also pass a capture filter by IP address.
+    pub assistant_ip: Option<IpAddr>,
+    /// The zstd level to compress captures with once they are complete or `None` to store them
+    /// uncompressed.
+    pub compression: Option<i32>,
+    /// The format captures are stored in. Only pcapng captures store the interaction's metadata
+    /// and when the query and response were spoken.
+    pub capture_format: CaptureFormat,
     interface: String,
     pub speaker: Speaker,
     voices: VecDeque<String>,
     pub sensitivity: f32,
     model: String,
+    /// How the recogniser decodes speech, stored with the session's config.
+    pub recogniser: RecogniserConfig,
     data_dir: PathBuf,
     assistant_mac: String,
 }
@@ -57,11 +111,15 @@ impl Interactor {
     ///
     /// # Arguments
     ///
-    /// * `interface`: The interface to create the sniffer on.
+    /// * `source`: Where the sniffer gets its packets from, a network device or a capture to
+    ///   replay.
     /// * `voices`: The voices to use for the speaker.
     /// * `sensitivity`: The sensitivity of the listener.
     /// * `model`: The model to use for the recogniser.
     /// * `data_dir`: The path to the data directory.
+    /// * `assistant_mac`: The MAC address of the assistant.
+    /// * `capture_filter`: The BPF filter to capture traffic with. If this is `None`, only traffic
+    ///   from and to `assistant_mac` is captured. An empty filter captures all traffic.
     ///
     /// # Examples
     ///
@@ -69,31 +127,53 @@ impl Interactor {
     /// # use std::path::PathBuf;
     /// # use varys::assistant::interactor::Interactor;
     /// # use varys_audio::stt::Model;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::PacketSource;
     /// let mut interactor = Interactor::new(
-    ///     "en0".to_string(),
+    ///     PacketSource::from(sniff::device_by_name("en0").unwrap()),
     ///     vec!["Ava".to_string()],
     ///     0.01,
     ///     Model::Large,
     ///     PathBuf::from("./data"),
     ///     "00:00:00:00:00:00".to_string(),
+    ///     None,
     /// ).unwrap();
     /// ```
     pub fn new(
-        interface: String,
+        source: PacketSource,
         voices: Vec<String>,
         sensitivity: f32,
         model: String,
         data_dir: PathBuf,
         assistant_mac: String,
+        capture_filter: Option<String>,
     ) -> Result<Interactor, Error> {
+        let interface = source.name();
+        let address = MacAddress::from_str(&assistant_mac)?;
+        let mut sniffer = Sniffer::from(source);
+        sniffer.filter = match capture_filter {
+            // an empty filter captures all traffic, which is stored as no filter
+            Some(filter) if filter.trim().is_empty() => None,
+            Some(filter) => Some(filter),
+            None => Some(address.capture_filter()),
+        };
+        sniffer.relative_to = Some(address.into());
+
         Ok(Interactor {
             listener: Listener::new()?,
-            sniffer: Sniffer::from(sniff::device_by_name(interface.as_str())?),
+            sniffer,
+            capture_roll: None,
+            continuous_capture: None,
+            anonymise: None,
+            assistant_ip: None,
+            compression: None,
+            capture_format: CaptureFormat::default(),
             interface,
             speaker: Speaker::new()?,
             voices: voices.into(),
             sensitivity,
             model,
+            recogniser: RecogniserConfig::default(),
             data_dir,
             assistant_mac,
         })
@@ -120,14 +200,17 @@ impl Interactor {
     /// # use varys::query::Query;
     /// # use varys_audio::stt::{Model, Recogniser};
     /// # use varys_audio::stt::transcriber::Transcriber;
+    /// # use varys_network::sniff;
+    /// # use varys_network::sniff::PacketSource;
     /// let (_, transcriber_handle) = Transcriber::new(Recogniser::with_model(Model::default()).unwrap());
     /// let mut interactor = Interactor::new(
-    ///     "en0".to_string(),
+    ///     PacketSource::from(sniff::device_by_name("en0").unwrap()),
     ///     vec!["Ava".to_string()],
     ///     0.01,
     ///     Model::Large,
     ///     PathBuf::from("./data"),
     ///     "00:00:00:00:00:00".to_string(),
+    ///     None,
     /// )
     /// .unwrap();
     /// let mut queries = vec![
@@ -161,6 +244,26 @@ impl Interactor {
         let (mut session, database_pool) = self.create_session(voice.clone()).await?;
         self.listener.recording_timeout = Some(assistant.recording_timeout());
         queries.shuffle(&mut rand::thread_rng());
+        let assistant_mac = MacAddress::from_str(&self.assistant_mac)?;
+        self.sniffer.relative_to = Some(DeviceAddress::new(assistant_mac, self.assistant_ip));
+        self.sniffer.compression = self.compression;
+        self.sniffer.format = self.capture_format;
+        self.sniffer.anonymiser = match self.anonymise {
+            Some(truncation) => {
+                let mut anonymiser = Anonymiser::new(truncation);
+                // assign the first pseudonyms to the assistant
+                anonymiser.mac(assistant_mac);
+                if let Some(ip) = self.assistant_ip {
+                    anonymiser.ip(ip);
+                }
+                Some(Arc::new(Mutex::new(anonymiser)))
+            }
+            None => None,
+        };
+        if let Some(roll) = self.capture_roll {
+            let window = CONTINUOUS_CAPTURE_WINDOW + roll.pre_roll + roll.post_roll;
+            self.continuous_capture = Some((self.sniffer.start_continuous(window)?, roll));
+        }
 
         info!("Starting {}", session);
 
@@ -209,6 +312,10 @@ impl Interactor {
         }
         .stop();
 
+        if let Some((continuous_capture, _)) = self.continuous_capture.take() {
+            info!("{}", continuous_capture.stop()?);
+        }
+
         // complete the session
         session.complete(&database_pool).await?;
 
@@ -232,6 +339,7 @@ impl Interactor {
                 voice,
                 sensitivity: self.sensitivity.to_string(),
                 model: self.model.to_string(),
+                recogniser: self.recogniser.to_json()?,
             },
             crate::version(),
         )
@@ -257,14 +365,16 @@ impl Interactor {
         info!("Starting interaction with \"{query}\"");
 
         // prepare the interaction
+        let (assistant_mac, assistant_ip, capture_filter) = self.recorded_addresses()?;
         let mut interaction = Interaction::create(
             connection,
             session,
             &query.text,
             &query.category,
-            self.assistant_mac.clone(),
+            assistant_mac,
         )
         .await?;
+        interaction.assistant_ip = assistant_ip;
         let capture_path = file::artefact_path(&self.data_dir, DataType::Capture, &interaction);
         let query_audio_path = file::artefact_path(
             &self.data_dir,
@@ -277,14 +387,20 @@ impl Interactor {
             &interaction,
         );
 
-        // start the sniffer
-        let sniffer_instance = self.sniffer.start(&capture_path)?;
+        // start the sniffer unless it is already running continuously
+        let (sniffer_instance, stats_before) = match &self.continuous_capture {
+            None => (Some(self.sniffer.start(&capture_path)?), None),
+            Some((continuous_capture, _)) => (None, Some(continuous_capture.instance().stats())),
+        };
+        let capture_start = Utc::now();
 
         // begin recording the query
         let query_instance = self.listener.start()?;
 
         // say the query
+        let query_started = Utc::now();
         interaction.query_duration = Some(self.speaker.say(&query.text)?);
+        let query_ended = Utc::now();
 
         // stop recording the query
         let query_audio = query_instance.stop()?;
@@ -303,11 +419,55 @@ impl Interactor {
         interaction.response_file = Some(file_name_or_full(&response_audio_path));
         interaction.update(connection).await?;
 
-        // finish the sniffer
-        let stats = sniffer_instance.stop()?;
+        // the recording stops after the silence and its silence is trimmed, so this is an estimate
+        let response_started = Utc::now()
+            - chrono::Duration::from_std(silence_after_talking).unwrap_or_default()
+            - chrono::Duration::milliseconds(response_audio.duration_ms() as i64);
+        let annotations = annotations(
+            &interaction,
+            vec![
+                Mark::new(query_started, "query started"),
+                Mark::new(query_ended, "query ended"),
+                Mark::new(response_started, "response started"),
+            ],
+        );
 
+        // finish the sniffer or cut the interaction from the continuous capture
+        let stats = if let Some(sniffer_instance) = sniffer_instance {
+            sniffer_instance.stop_with(&annotations)?
+        } else if let Some((continuous_capture, roll)) = &self.continuous_capture {
+            tokio::time::sleep(roll.post_roll).await;
+
+            let from =
+                capture_start - chrono::Duration::from_std(roll.pre_roll).unwrap_or_default();
+            let mut stats =
+                continuous_capture.save_with(from, Utc::now(), &capture_path, &annotations)?;
+
+            // only count the packets dropped during this interaction
+            let stats_now = continuous_capture.instance().stats();
+            let stats_before = stats_before.unwrap_or_default();
+            stats.buffer_dropped = stats_now
+                .buffer_dropped
+                .saturating_sub(stats_before.buffer_dropped);
+            stats.interface_dropped = stats_now
+                .interface_dropped
+                .saturating_sub(stats_before.interface_dropped);
+
+            interaction.capture_pre_roll = i32::try_from(roll.pre_roll.as_millis()).ok();
+            interaction.capture_post_roll = i32::try_from(roll.post_roll.as_millis()).ok();
+            stats
+        } else {
+            SnifferStats::default()
+        };
         info!("{stats}");
-        interaction.capture_file = Some(file_name_or_full(&capture_path));
+        if stats.has_drops() {
+            warn!("Packets were dropped while capturing {interaction}");
+        }
+        set_capture_stats(&mut interaction, &stats);
+        interaction.capture_file =
+            Some(file_name_or_full(&self.sniffer.capture_path(&capture_path)));
+        interaction.capture_filter = capture_filter;
+        interaction.capture_anonymised = self.sniffer.anonymiser.is_some();
         interaction.update(connection).await?;
 
         // at this point, the interaction is not yet complete because the response will later be
@@ -315,6 +475,30 @@ impl Interactor {
         Ok((interaction, response_audio))
     }
 
+    /// The assistant MAC and IP addresses and capture filter to store with an interaction.
+    ///
+    /// If captures are anonymised, the real addresses are replaced by their pseudonyms.
+    fn recorded_addresses(&self) -> Result<(String, Option<String>, Option<String>), Error> {
+        let Some(anonymiser) = &self.sniffer.anonymiser else {
+            return Ok((
+                self.assistant_mac.clone(),
+                self.assistant_ip.map(|ip| ip.to_string()),
+                self.sniffer.filter.clone(),
+            ));
+        };
+
+        let mut anonymiser = anonymiser.lock().unwrap_or_else(PoisonError::into_inner);
+        let assistant_mac = anonymiser.mac(MacAddress::from_str(&self.assistant_mac)?);
+        let assistant_ip = self.assistant_ip.map(|ip| anonymiser.ip(ip).to_string());
+        let capture_filter = self
+            .sniffer
+            .filter
+            .as_ref()
+            .map(|filter| anonymiser.anonymise_filter(filter));
+
+        Ok((assistant_mac.to_string(), assistant_ip, capture_filter))
+    }
+
     async fn complete_interaction(
         receiver: TranscriberReceiver<TranscribeInteraction>,
         database_connection: &DatabaseConnection,
@@ -325,10 +509,80 @@ impl Interactor {
         info!("Transcription of {} done, completing it...", interaction.0);
 
         interaction.0.complete(database_connection).await?;
+        store_segments(database_connection, &interaction.0, None, &interaction.1).await?;
         Ok(sender)
     }
 }
 
+/// The metadata stored in a pcapng capture of an interaction.
+///
+/// The assistant MAC address is the stored one, i.e. its pseudonym if captures are anonymised.
+fn annotations(interaction: &Interaction, marks: Vec<Mark>) -> Annotations {
+    Annotations {
+        application: Some(format!("varys {}", crate::version())),
+        marks,
+        ..Annotations::default()
+    }
+    .with("interaction", interaction.id)
+    .with("session", interaction.session_id)
+    .with("query", &interaction.query)
+    .with("category", &interaction.query_category)
+    .with("assistant", &interaction.assistant_mac)
+}
+
+/// Store the statistics of an interaction's capture with it.
+pub fn set_capture_stats(interaction: &mut Interaction, stats: &SnifferStats) {
+    interaction.capture_packets = i32::try_from(stats.stored).ok();
+    interaction.capture_bytes = i64::try_from(stats.bytes).ok();
+    interaction.capture_packets_in = i32::try_from(stats.packets_in).ok();
+    interaction.capture_packets_out = i32::try_from(stats.packets_out).ok();
+    interaction.capture_buffer_dropped = i32::try_from(stats.buffer_dropped).ok();
+    interaction.capture_interface_dropped = i32::try_from(stats.interface_dropped).ok();
+    interaction.capture_first_packet = stats.first_packet;
+    interaction.capture_last_packet = stats.last_packet;
+}
+
+/// Store the segments of a transcribed response of an interaction.
+///
+/// # Arguments
+///
+/// * `connection`: The connection to use.
+/// * `interaction`: The interaction whose response was transcribed.
+/// * `transcript`: The transcript the segments are part of or `None` if they make up the
+///   interaction's `response`.
+/// * `segments`: The segments to store.
+pub async fn store_segments(
+    connection: &DatabaseConnection,
+    interaction: &Interaction,
+    transcript: Option<&Transcript>,
+    segments: &[Segment],
+) -> Result<(), Error> {
+    for (position, segment) in segments.iter().enumerate() {
+        let offsets = (
+            i32::try_from(segment.start.as_millis()).unwrap_or(i32::MAX),
+            i32::try_from(segment.end.as_millis()).unwrap_or(i32::MAX),
+        );
+        let tokens: Vec<_> = segment
+            .tokens
+            .iter()
+            .map(|token| (token.text.clone(), token.probability))
+            .collect();
+
+        varys_database::database::segment::Segment::create(
+            connection,
+            interaction,
+            transcript,
+            position as i32,
+            offsets,
+            &segment.text,
+            &tokens,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
 /// Returns the file name if it exists. Otherwise, returns the full path.
 ///
 /// # Arguments
@@ -27,11 +27,9 @@ impl VoiceAssistant for Siri {
         "Hey Siri".to_string()
     }
 
-    fn setup(&self) -> Result<(), Error> {
+    fn setup(&self, mut speaker: Speaker) -> Result<(), Error> {
         info!("Starting Siri setup...");
 
-        let mut speaker = Speaker::new()?;
-
         let voice = interact::user_input(
             &format!(
                 "Choose the voice to set up (The highest quality voices on macOS are {}):",
@@ -124,11 +122,9 @@ impl VoiceAssistant for Siri {
         Ok(())
     }
 
-    fn test_voices(&self, voices: Vec<String>) -> Result<(), Error> {
+    fn test_voices(&self, mut speaker: Speaker, voices: Vec<String>) -> Result<(), Error> {
         info!("Testing Siri voices...");
 
-        let mut speaker = Speaker::new()?;
-
         for voice in voices {
             interact::user_confirmation(&format!("Test {}", voice))?;
             speaker.set_voice(&voice).unwrap();
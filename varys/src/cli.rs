@@ -1,25 +1,50 @@
 use clap::Parser;
-use log::{debug, error, info};
-use std::path::Path;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::mpsc;
 use std::{thread, time};
 use varys_analysis::ml::data::NumericTraceDataset;
+use varys_analysis::trace::TraceRepresentation;
 use varys_analysis::{ml, plot};
+use varys_audio::audio::resample::ResampleQuality;
 use varys_audio::listen::Listener;
+use varys_audio::stt::config::RecogniserConfig;
 use varys_audio::stt::transcriber::Transcriber;
 use varys_audio::stt::Recogniser;
-use varys_audio::tts::Speaker;
+use varys_audio::tts::{Speaker, TtsBackend};
+use varys_database::connection::DatabaseConnection;
 use varys_database::database;
 use varys_database::database::interaction::Interaction;
-use varys_network::address::MacAddress;
-use varys_network::sniff;
-use varys_network::sniff::{ConnectionStatus, Sniffer};
+use varys_database::database::session::Session;
+use varys_database::database::transcript::Transcript;
+use varys_database::file;
+use varys_database::file::DataType;
+use varys_network::address::{DeviceAddress, MacAddress};
+use varys_network::anonymise::{Anonymiser, Truncation};
+use varys_network::discover::Discovery;
+use varys_network::flow::FlowTable;
+use varys_network::hostname::Hostnames;
+use varys_network::sniff::SnifferStats;
+use varys_network::sniff::{
+    CaptureFormat, ConnectionStatus, Linktype, PacketSource, ReplaySpeed, Sniffer,
+};
+use varys_network::trim::TrimWindow;
+use varys_network::verify::CaptureCheck;
+use varys_network::{compress, packet, sniff, trim, tzsp, verify};
 
 use crate::assistant;
-use crate::assistant::interactor::Interactor;
+use crate::assistant::interactor::{
+    file_name_or_full, set_capture_stats, store_segments, CaptureRoll, Interactor,
+};
 use crate::cli::arguments::{
-    AnalyseSubcommand, Arguments, AssistantCommand, AssistantSubcommand, Command, ListenCommand,
-    SniffCommand,
+    AnalyseSubcommand, Arguments, AssistantCommand, AssistantSubcommand, CapturesCommand,
+    CapturesSubcommand, Command, DiscoverCommand, ListenCommand, RecogniserArguments,
+    ReplayArguments, SniffCommand, SpeakerArguments, TranscribeCommand, TrimAnchor,
+    TruncationArguments, TtsEngine,
 };
 use crate::dataset::DatasetSize;
 use crate::error::Error;
@@ -37,26 +62,36 @@ pub async fn run() -> Result<(), Error> {
     let arguments = Arguments::parse();
 
     match arguments.command {
-        Command::Assistant(command) => assistant_command(command),
+        Command::Assistant(command) => assistant_command(tts_backend(arguments.speaker), command),
         Command::Listen(command) => listen_command(
+            tts_backend(arguments.speaker),
             arguments.voices.first().ok_or(Error::NoVoiceProvided)?,
             arguments.sensitivity,
             arguments.model,
+            recogniser_config(arguments.recogniser)?,
             command,
         ),
         Command::Sniff(command) => sniff_command(&arguments.interface, command),
         Command::Run(command) => {
             run_command(
                 &arguments.interface,
+                tts_backend(arguments.speaker),
                 arguments.voices,
                 arguments.sensitivity,
                 arguments.model,
+                recogniser_config(arguments.recogniser)?,
                 command,
             )
             .await
         }
         Command::Analyse(command) => {
-            analyse_command(command.dataset, command.command, &arguments.interface).await
+            analyse_command(
+                command.dataset,
+                command.bursts.representation(),
+                command.command,
+                &arguments.interface,
+            )
+            .await
         }
         Command::Export(export_command) => {
             export_command
@@ -65,33 +100,52 @@ pub async fn run() -> Result<(), Error> {
                     export_command.data_dir,
                     &export_command.dataset,
                     assistant::from(&export_command.assistant),
+                    chrono::Duration::milliseconds(export_command.burst_gap as i64),
                 )
                 .await
         }
+        Command::Captures(command) => captures_command(command).await,
+        Command::Discover(command) => discover_command(
+            &arguments.interface,
+            tts_backend(arguments.speaker),
+            arguments.voices.first().ok_or(Error::NoVoiceProvided)?,
+            command,
+        ),
+        Command::Transcribe(command) => {
+            transcribe_command(
+                arguments.model,
+                recogniser_config(arguments.recogniser)?,
+                command,
+            )
+            .await
+        }
     }
 }
 
-fn assistant_command(command: AssistantCommand) -> Result<(), Error> {
+fn assistant_command(backend: TtsBackend, command: AssistantCommand) -> Result<(), Error> {
     let assistant = assistant::from(command.assistant.as_str());
+    let speaker = Speaker::with_backend(backend)?;
 
     match command.command {
-        AssistantSubcommand::Setup => assistant.setup()?,
-        AssistantSubcommand::Test(test) => assistant.test_voices(test.voices)?,
+        AssistantSubcommand::Setup => assistant.setup(speaker)?,
+        AssistantSubcommand::Test(test) => assistant.test_voices(speaker, test.voices)?,
     };
 
     Ok(())
 }
 
 fn listen_command<P: AsRef<Path>>(
+    backend: TtsBackend,
     voice: &str,
     sensitivity: f32,
     model: P,
+    recogniser: RecogniserConfig,
     command: ListenCommand,
 ) -> Result<(), Error> {
     if command.calibrate {
         calibrate()
     } else {
-        listen(voice, sensitivity, model, command)
+        listen(backend, voice, sensitivity, model, recogniser, command)
     }
 }
 
@@ -105,9 +159,11 @@ fn calibrate() -> Result<(), Error> {
 }
 
 fn listen<P: AsRef<Path>>(
+    backend: TtsBackend,
     voice: &str,
     sensitivity: f32,
     model: P,
+    recogniser: RecogniserConfig,
     command: ListenCommand,
 ) -> Result<(), Error> {
     info!("Listening...");
@@ -117,18 +173,19 @@ fn listen<P: AsRef<Path>>(
     } else {
         listener.record_until_silent(time::Duration::from_secs(2), sensitivity)?
     };
-    audio.downsample(16000)?;
+    audio.resample(Recogniser::SAMPLE_RATE, ResampleQuality::default());
     if let Some(file) = command.file {
         varys_audio::file::write_audio(&file, &audio)?;
     }
 
     if command.parrot {
         info!("Recognising...");
-        let recogniser = Recogniser::with_model_path(&model.as_ref().to_string_lossy())?;
+        let recogniser = Recogniser::with_config(&model.as_ref().to_string_lossy(), recogniser)?;
         let text = recogniser.recognise(&mut audio)?;
 
         info!("Speaking...");
-        let speaker = Speaker::with_voice(voice)?;
+        let mut speaker = Speaker::with_backend(backend)?;
+        speaker.set_voice(voice)?;
         speaker.say(&text)?;
     }
 
@@ -141,7 +198,8 @@ fn sniff_command(interface: &str, command: SniffCommand) -> Result<(), Error> {
     for device in sniff::devices_with_status(&ConnectionStatus::Connected)? {
         debug!("{}", Sniffer::from(device));
     }
-    let sniffer = Sniffer::from(sniff::device_by_name(interface)?);
+    let mut sniffer = Sniffer::from(packet_source(interface, command.replay)?);
+    sniffer.filter = command.filter;
     debug!("Using: {sniffer}");
     let stats = sniffer.run_for(5, &command.file)?;
     debug!("Stats: {stats}");
@@ -151,26 +209,48 @@ fn sniff_command(interface: &str, command: SniffCommand) -> Result<(), Error> {
 
 async fn run_command<P: AsRef<Path>>(
     interface: &str,
+    backend: TtsBackend,
     voices: Vec<String>,
     sensitivity: f32,
     model: P,
+    recogniser: RecogniserConfig,
     command: arguments::RunCommand,
 ) -> Result<(), Error> {
     let mut interactor = Interactor::new(
-        interface.to_string(),
+        packet_source(interface, command.replay)?,
         voices,
         sensitivity,
         model.as_ref().to_string_lossy().to_string(),
         command.data_dir,
         command.mac,
+        command.capture_filter,
     )?;
+    if command.pre_roll.is_some() || command.post_roll.is_some() {
+        interactor.capture_roll = Some(CaptureRoll {
+            pre_roll: time::Duration::from_millis(command.pre_roll.unwrap_or_default()),
+            post_roll: time::Duration::from_millis(command.post_roll.unwrap_or_default()),
+        });
+    }
+    if command.anonymise {
+        interactor.anonymise = Some(truncation(command.truncation));
+    }
+    if command.compress {
+        interactor.compression = Some(compress::DEFAULT_LEVEL);
+    }
+    if command.pcapng {
+        interactor.capture_format = CaptureFormat::Pcapng;
+    }
+    interactor.assistant_ip = command.ip;
+    interactor.recogniser = recogniser.clone();
+    interactor.speaker = Speaker::with_backend(backend)?;
     let assistant = assistant::from(command.assistant.as_str());
     let mut queries = Query::read_toml(&command.queries)?;
     assistant.prepare_queries(&mut queries);
 
     loop {
-        let (transcriber, transcriber_handle) = Transcriber::new(Recogniser::with_model_path(
+        let (transcriber, transcriber_handle) = Transcriber::new(Recogniser::with_config(
             &model.as_ref().to_string_lossy(),
+            recogniser.clone(),
         )?);
 
         let _ = thread::spawn(move || transcriber.start());
@@ -186,32 +266,45 @@ async fn run_command<P: AsRef<Path>>(
 
 async fn analyse_command(
     dataset_size: DatasetSize,
+    representation: TraceRepresentation,
     analyse_subcommand: AnalyseSubcommand,
     interface: &str,
 ) -> Result<(), Error> {
     match analyse_subcommand {
-        AnalyseSubcommand::Train { data_dir } => {
-            ml::train(data_dir, get_filtered_interactions(&dataset_size).await?)?
-        }
+        AnalyseSubcommand::Train { data_dir } => ml::train(
+            data_dir,
+            get_filtered_interactions(&dataset_size).await?,
+            representation,
+        )?,
         AnalyseSubcommand::Test { data_dir } => ml::test_dataset(data_dir)?,
-        AnalyseSubcommand::Demo { data_dir, mac } => demo(data_dir, interface, mac)?,
+        AnalyseSubcommand::Demo {
+            data_dir,
+            mac,
+            replay,
+        } => demo(data_dir, packet_source(interface, replay)?, mac)?,
         AnalyseSubcommand::CompileLogs { data_dir, id } => ml::compile_all_logs(data_dir, &id)?,
         AnalyseSubcommand::Plot { data_dir } => {
             let mut dataset = NumericTraceDataset::new(
                 &data_dir,
                 get_filtered_interactions(&dataset_size).await?,
+                representation,
             )?;
             dataset.resize_all(475).shuffle();
 
             plot::plot_queries(&data_dir, dataset_size.queries(), &dataset);
         }
+        AnalyseSubcommand::Hostnames { data_dir } => {
+            hostnames(data_dir, get_filtered_interactions(&dataset_size).await?)?
+        }
     }
 
     Ok(())
 }
 
-fn demo<P: AsRef<Path>>(data_dir: P, interface: &str, address: String) -> Result<(), Error> {
-    let sniffer = Sniffer::from(sniff::device_by_name(interface)?);
+fn demo<P: AsRef<Path>>(data_dir: P, source: PacketSource, address: String) -> Result<(), Error> {
+    let address = MacAddress::from_str(&address)?;
+    let mut sniffer = Sniffer::from(source);
+    sniffer.filter = Some(address.capture_filter());
     let capture_path = data_dir.as_ref().join("captures/demo.pcap");
     let data_dir = data_dir.as_ref().to_path_buf();
 
@@ -223,12 +316,725 @@ fn demo<P: AsRef<Path>>(data_dir: P, interface: &str, address: String) -> Result
     let sniffer = sniffer.start(&capture_path)?;
     interact::user_confirmation("Confirm when the voice assistant has finished speaking.")?;
     let _ = sniffer.stop()?;
-    let output = ml::test_single(&data_dir, &capture_path, &MacAddress::from_str(&address)?)?;
+    let output = ml::test_single(&data_dir, &capture_path, &address.into())?;
     println!("{output:?}");
 
     Ok(())
 }
 
+fn hostnames<P: AsRef<Path>>(data_dir: P, interactions: Vec<Interaction>) -> Result<(), Error> {
+    for interaction in interactions {
+        let Some(capture_file) = &interaction.capture_file else {
+            continue;
+        };
+        let capture_path = file::session_path(&data_dir, interaction.session_id).join(capture_file);
+        let packets = match packet::load_packets(&capture_path) {
+            Ok(packets) => packets,
+            Err(error) => {
+                warn!("Cannot read the capture of {interaction} at {capture_path:?}: {error}");
+                continue;
+            }
+        };
+        let hostnames: Hostnames = packets.iter().collect();
+        let flows: FlowTable = packets.into_iter().collect();
+
+        println!("{interaction}");
+        for flow in flows.flows() {
+            println!(
+                "  {}: {flow}",
+                hostnames
+                    .label(flow)
+                    .unwrap_or_else(|| "unknown".to_string())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn discover_command(
+    interface: &str,
+    backend: TtsBackend,
+    voice: &str,
+    command: DiscoverCommand,
+) -> Result<(), Error> {
+    let assistant = assistant::from(command.assistant.as_str());
+    let probe = command
+        .probe
+        .unwrap_or_else(|| format!("{}. What time is it?", assistant.wake_word()));
+    let response_window = time::Duration::from_secs(command.response_window);
+    let mut speaker = Speaker::with_backend(backend)?;
+    speaker.set_voice(voice)?;
+    let mut discovery =
+        Discovery::start(&Sniffer::from(packet_source(interface, command.replay)?))?;
+
+    // observe the traffic without probes first
+    thread::sleep(response_window);
+    for _ in 0..command.probes {
+        let start = chrono::Utc::now();
+        speaker.say(&probe)?;
+        discovery.probe(start, chrono::Utc::now());
+        thread::sleep(response_window + assistant.silence_between_interactions());
+    }
+
+    let candidates =
+        discovery.finish(chrono::Duration::from_std(response_window).unwrap_or_default())?;
+    for candidate in candidates.iter().take(5) {
+        println!("{candidate}");
+    }
+
+    let Some(best) = candidates.first() else {
+        println!("No devices were seen, check the interface");
+        return Ok(());
+    };
+    match command.mac {
+        Some(mac) if MacAddress::from_str(&mac)? == best.address => {
+            println!("Confirmed {mac} as the MAC address of {}", assistant.name())
+        }
+        Some(mac) => println!(
+            "{mac} does not look like {}, consider using {} instead",
+            assistant.name(),
+            best.address
+        ),
+        None => println!(
+            "{} is most likely the MAC address of {}",
+            best.address,
+            assistant.name()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Transcribe the stored responses of the selected interactions again with another model.
+///
+/// Each new transcript is stored with the name of the model, the recogniser config and its
+/// segments, leaving the original response and earlier transcripts untouched. Interactions
+/// without a readable response file or with a response that is too short to recognise are
+/// skipped.
+async fn transcribe_command<P: AsRef<Path>>(
+    model: P,
+    recogniser: RecogniserConfig,
+    command: TranscribeCommand,
+) -> Result<(), Error> {
+    let model_name = file_name_or_full(model.as_ref());
+    let recogniser_json = recogniser.to_json()?;
+    let recogniser = Recogniser::with_config(&model.as_ref().to_string_lossy(), recogniser)?;
+    let connection = database::connect().await?;
+    let interactions = match command.session {
+        Some(id) => Interaction::get_by_session(&connection, id).await?,
+        None => Interaction::get_all(&connection).await?,
+    };
+
+    let mut transcribed = 0;
+    for interaction in interactions {
+        let started = interaction.started.date_naive();
+        if command.since.is_some_and(|since| started < since)
+            || command.until.is_some_and(|until| started > until)
+            || (command.empty
+                && interaction
+                    .response
+                    .as_ref()
+                    .is_some_and(|response| !response.is_empty()))
+        {
+            continue;
+        }
+        let Some(response_file) = &interaction.response_file else {
+            continue;
+        };
+        let response_path =
+            file::session_path(&command.data_dir, interaction.session_id).join(response_file);
+
+        let segments = match varys_audio::file::read_audio(&response_path).and_then(|mut audio| {
+            recogniser.recognise_segments(&mut audio, command.tokens, Some(&interaction.query))
+        }) {
+            Ok(segments) => segments,
+            Err(error) => {
+                warn!("Cannot transcribe the response of {interaction}: {error}");
+                continue;
+            }
+        };
+        let text = varys_audio::stt::segment::text(&segments);
+        let transcript = Transcript::create(
+            &connection,
+            &interaction,
+            &model_name,
+            &recogniser_json,
+            &text,
+        )
+        .await?;
+        store_segments(&connection, &interaction, Some(&transcript), &segments).await?;
+        info!("{transcript}: {text}");
+        transcribed += 1;
+    }
+
+    info!("Transcribed {transcribed} responses with {model_name}");
+
+    Ok(())
+}
+
+async fn captures_command(command: CapturesCommand) -> Result<(), Error> {
+    match command.command {
+        CapturesSubcommand::Anonymise {
+            data_dir,
+            session,
+            truncation: truncation_arguments,
+        } => anonymise(data_dir, session, truncation(truncation_arguments)).await,
+        CapturesSubcommand::Problems => capture_problems().await,
+        CapturesSubcommand::Compress {
+            data_dir,
+            session,
+            level,
+        } => compress_captures(data_dir, session, level).await,
+        CapturesSubcommand::Trim {
+            data_dir,
+            session,
+            interaction,
+            anchor,
+            before,
+            after,
+            gap,
+            in_place,
+        } => {
+            let padding = (
+                chrono::Duration::milliseconds(before as i64),
+                chrono::Duration::milliseconds(after as i64),
+            );
+            trim_captures(
+                data_dir,
+                (session, interaction),
+                anchor,
+                padding,
+                chrono::Duration::milliseconds(gap as i64),
+                in_place,
+            )
+            .await
+        }
+        CapturesSubcommand::Verify {
+            data_dir,
+            session,
+            repair,
+            report,
+        } => verify_captures(data_dir, session, repair, report).await,
+        CapturesSubcommand::Send { file, target, fast } => {
+            let speed = if fast {
+                ReplaySpeed::Unlimited
+            } else {
+                ReplaySpeed::Recorded
+            };
+            // keep the sender so sending is never stopped early
+            let (_stop_sender, stop) = mpsc::channel();
+            let sent = tzsp::send_capture(&file, target, speed, &stop)?;
+            println!("Sent {sent} packets to {target}");
+
+            Ok(())
+        }
+    }
+}
+
+/// Anonymise the captures of a session or of all sessions in place.
+///
+/// Each session is anonymised with its own [`Anonymiser`], so pseudonyms are consistent within a
+/// session. The assistant MAC address and capture filter of each interaction are replaced by their
+/// pseudonyms.
+async fn anonymise<P: AsRef<Path>>(
+    data_dir: P,
+    session: Option<i32>,
+    truncation: Truncation,
+) -> Result<(), Error> {
+    let connection = database::connect().await?;
+    let session_ids = match session {
+        Some(id) => vec![id],
+        None => Session::get_all(&connection)
+            .await?
+            .iter()
+            .map(|session| session.id)
+            .collect(),
+    };
+
+    for session_id in session_ids {
+        let mut anonymiser = Anonymiser::new(truncation);
+        let mut interactions = Interaction::get_by_session(&connection, session_id).await?;
+        interactions.sort_by_key(|interaction| interaction.id);
+
+        info!("Anonymising the captures of session {session_id}...");
+
+        for interaction in &mut interactions {
+            if interaction.capture_anonymised {
+                warn!("The capture of {interaction} is already anonymised");
+                continue;
+            }
+            let Some(capture_file) = &interaction.capture_file else {
+                continue;
+            };
+            let assistant = DeviceAddress::parse(
+                &interaction.assistant_mac,
+                interaction.assistant_ip.as_deref(),
+            )?;
+            let capture_path = file::session_path(&data_dir, session_id).join(capture_file);
+
+            // only replace the capture once the database is updated, so an interaction whose
+            // update failed still has its original capture and can be anonymised again
+            let anonymised_path = capture_path.with_extension("anonymised");
+            anonymiser.anonymise_file(&capture_path, &anonymised_path)?;
+            interaction.assistant_mac = anonymiser.mac(assistant.mac).to_string();
+            interaction.assistant_ip = assistant.ip.map(|ip| anonymiser.ip(ip).to_string());
+            interaction.capture_filter = interaction
+                .capture_filter
+                .as_ref()
+                .map(|filter| anonymiser.anonymise_filter(filter));
+            interaction.capture_anonymised = true;
+            if let Err(error) = interaction.update(&connection).await {
+                let _ = fs::remove_file(&anonymised_path);
+                return Err(error.into());
+            }
+            fs::rename(&anonymised_path, &capture_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Compress the captures of a session or of all sessions in place.
+///
+/// The capture file of each interaction is updated to the compressed file. Captures that are
+/// already compressed or missing are skipped.
+async fn compress_captures<P: AsRef<Path>>(
+    data_dir: P,
+    session: Option<i32>,
+    level: i32,
+) -> Result<(), Error> {
+    let connection = database::connect().await?;
+    let interactions = match session {
+        Some(id) => Interaction::get_by_session(&connection, id).await?,
+        None => Interaction::get_all(&connection).await?,
+    };
+
+    for mut interaction in interactions {
+        let Some(capture_file) = &interaction.capture_file else {
+            continue;
+        };
+        let capture_path = file::session_path(&data_dir, interaction.session_id).join(capture_file);
+        if !capture_path.exists() {
+            warn!("The capture of {interaction} is missing at {capture_path:?}");
+            continue;
+        }
+        if compress::is_compressed(&capture_path)? {
+            debug!("The capture of {interaction} is already compressed");
+            continue;
+        }
+
+        let compressed = compress::compress_file(&capture_path, level)?;
+        interaction.capture_file = Some(file_name_or_full(&compressed));
+        interaction.update(&connection).await?;
+    }
+
+    Ok(())
+}
+
+/// Cut the captures of an interaction, a session or all sessions to a window around an anchor.
+///
+/// The capture file and statistics of each interaction are updated to the trimmed capture. The
+/// numbers of dropped packets are kept, since they cannot be attributed to the window.
+async fn trim_captures<P: AsRef<Path>>(
+    data_dir: P,
+    (session, interaction): (Option<i32>, Option<i32>),
+    anchor: TrimAnchor,
+    (before, after): (chrono::Duration, chrono::Duration),
+    gap: chrono::Duration,
+    in_place: bool,
+) -> Result<(), Error> {
+    let connection = database::connect().await?;
+    let interactions = match (interaction, session) {
+        (Some(id), _) => Interaction::get(&connection, id)
+            .await?
+            .into_iter()
+            .collect(),
+        (None, Some(id)) => Interaction::get_by_session(&connection, id).await?,
+        (None, None) => Interaction::get_all(&connection).await?,
+    };
+
+    for mut interaction in interactions {
+        let Some(capture_file) = &interaction.capture_file else {
+            continue;
+        };
+        let capture_path = file::session_path(&data_dir, interaction.session_id).join(capture_file);
+        // trim captures that were already trimmed from their original again
+        let original_path = trim::original_path(&capture_path)
+            .filter(|original| original.exists())
+            .unwrap_or(capture_path);
+        if !original_path.exists() {
+            warn!("The capture of {interaction} is missing at {original_path:?}");
+            continue;
+        }
+        let address = DeviceAddress::parse(
+            &interaction.assistant_mac,
+            interaction.assistant_ip.as_deref(),
+        )?;
+        let packets = packet::read_headers(&original_path)?.collect::<Result<Vec<_>, _>>()?;
+
+        let window = match anchor {
+            TrimAnchor::FirstPacket => trim::first_packet(&packets, &address)
+                .map(|timestamp| TrimWindow::new(timestamp, timestamp)),
+            TrimAnchor::Interaction => interaction
+                .ended
+                .map(|ended| TrimWindow::new(interaction.started, ended)),
+            TrimAnchor::Burst => trim::first_burst(&packets, &address, gap),
+        };
+        let Some(window) = window.map(|window| window.padded(before, after)) else {
+            warn!("Cannot find the {anchor:?} anchor of {interaction}, skipping it");
+            continue;
+        };
+        // an empty window would drop almost the whole capture, which cannot be undone in place
+        if window.start >= window.end {
+            warn!("The window of {interaction} is empty, skipping it (widen it with --after)");
+            continue;
+        }
+
+        let trimmed_path = if in_place {
+            original_path.clone()
+        } else {
+            trim::trimmed_path(&original_path)
+        };
+        let written = trim::trim_file(&original_path, &trimmed_path, &window)?;
+        info!(
+            "Kept {written} of {} packets of {interaction} from {window}",
+            packets.len()
+        );
+
+        let mut stats = SnifferStats {
+            received: u32::try_from(written).unwrap_or(u32::MAX),
+            ..SnifferStats::default()
+        };
+        for packet in packets
+            .iter()
+            .filter(|packet| window.contains(packet.timestamp))
+        {
+            stats.count(packet, Some(&address));
+        }
+        let dropped = (
+            interaction.capture_buffer_dropped,
+            interaction.capture_interface_dropped,
+        );
+        set_capture_stats(&mut interaction, &stats);
+        (
+            interaction.capture_buffer_dropped,
+            interaction.capture_interface_dropped,
+        ) = dropped;
+        interaction.capture_file = Some(file_name_or_full(&trimmed_path));
+        interaction.update(&connection).await?;
+    }
+
+    Ok(())
+}
+
+/// The verification result of one capture in the report of `captures verify`.
+#[derive(Serialize, Debug)]
+struct CaptureReport {
+    session: i32,
+    interaction: i32,
+    query: String,
+    path: PathBuf,
+    /// Whether the capture is stored with the interaction, which is not the case if varys was
+    /// stopped while capturing.
+    registered: bool,
+    packets: usize,
+    assistant_packets: usize,
+    problem: Option<&'static str>,
+    details: Option<String>,
+    /// The path of the repaired copy of the capture, if it was repaired.
+    repaired: Option<PathBuf>,
+    salvaged_packets: Option<usize>,
+    /// Why repairing the capture failed, if it did.
+    repair_error: Option<String>,
+}
+
+/// Verify the captures of a session or of all sessions, optionally repairing them.
+///
+/// Interactions without a stored capture are checked for a capture that was left behind when
+/// varys stopped while capturing. Such captures are registered with their interaction when they
+/// are repaired.
+async fn verify_captures<P: AsRef<Path>>(
+    data_dir: P,
+    session: Option<i32>,
+    repair: bool,
+    report_path: Option<PathBuf>,
+) -> Result<(), Error> {
+    let connection = database::connect().await?;
+    let interactions = match session {
+        Some(id) => Interaction::get_by_session(&connection, id).await?,
+        None => Interaction::get_all(&connection).await?,
+    };
+    let mut report = Vec::new();
+
+    for mut interaction in interactions {
+        let artefact_path = file::artefact_path(&data_dir, DataType::Capture, &interaction);
+        let (capture_path, registered) = match &interaction.capture_file {
+            Some(capture_file) => (
+                file::session_path(&data_dir, interaction.session_id).join(capture_file),
+                true,
+            ),
+            None => {
+                // captures in pcapng are written to a temporary pcap file first
+                let Some(left_behind) = [
+                    artefact_path.clone(),
+                    artefact_path.with_extension("capturing.pcap"),
+                ]
+                .into_iter()
+                .find(|path| path.exists()) else {
+                    continue;
+                };
+                (left_behind, false)
+            }
+        };
+        let address = DeviceAddress::parse(
+            &interaction.assistant_mac,
+            interaction.assistant_ip.as_deref(),
+        )
+        .ok();
+        let check = verify::verify_file(&capture_path, address.as_ref());
+
+        match &check.problem {
+            Some(problem) => println!("{interaction}: {problem} ({} packets)", check.packets),
+            None if !registered => println!("{interaction}: the capture was not stored"),
+            None => debug!("The capture of {interaction} is fine"),
+        }
+
+        let (mut repaired, mut repair_error) = (None, None);
+        if repair && (check.is_repairable() || (!registered && check.packets > 0)) {
+            let repaired_path = if registered {
+                verify::repaired_path(&capture_path)
+            } else {
+                verify::repaired_path(&artefact_path)
+            };
+            match repair_capture(
+                &connection,
+                &mut interaction,
+                &capture_path,
+                &repaired_path,
+                address.as_ref(),
+            )
+            .await
+            {
+                Ok(salvaged) => {
+                    println!("{interaction}: salvaged {salvaged} packets to {repaired_path:?}");
+                    repaired = Some((repaired_path, salvaged));
+                }
+                Err(error) => {
+                    warn!("Cannot repair the capture of {interaction}: {error}");
+                    repair_error = Some(error.to_string());
+                }
+            }
+        }
+
+        report.push(capture_report(
+            &interaction,
+            capture_path,
+            registered,
+            &check,
+            (repaired, repair_error),
+        ));
+    }
+
+    let problems = report
+        .iter()
+        .filter(|report| report.problem.is_some() || !report.registered)
+        .count();
+    let failed_repairs = report
+        .iter()
+        .filter(|report| report.repair_error.is_some())
+        .count();
+    println!(
+        "Verified {} captures, {problems} with problems, {failed_repairs} could not be repaired",
+        report.len()
+    );
+
+    if let Some(report_path) = report_path {
+        serde_json::to_writer_pretty(File::create(&report_path)?, &report)?;
+        info!("Wrote the report to {report_path:?}");
+    }
+
+    Ok(())
+}
+
+/// Repair a capture and store the repaired copy with its interaction.
+///
+/// Returns the number of salvaged packets.
+async fn repair_capture(
+    connection: &DatabaseConnection,
+    interaction: &mut Interaction,
+    capture_path: &Path,
+    repaired_path: &Path,
+    address: Option<&DeviceAddress>,
+) -> Result<usize, Error> {
+    let salvaged = verify::repair_file(capture_path, repaired_path)?;
+    register_repaired_capture(interaction, repaired_path, address)?;
+    interaction.update(connection).await?;
+
+    Ok(salvaged)
+}
+
+/// Store a repaired capture with its interaction, counting its packets again.
+///
+/// The dropped packets of the original capture are kept.
+fn register_repaired_capture(
+    interaction: &mut Interaction,
+    repaired_path: &Path,
+    address: Option<&DeviceAddress>,
+) -> Result<(), Error> {
+    let mut stats = SnifferStats::default();
+    for packet in packet::read_headers(repaired_path)? {
+        stats.received += 1;
+        stats.count(&packet?, address);
+    }
+
+    let dropped = (
+        interaction.capture_buffer_dropped,
+        interaction.capture_interface_dropped,
+    );
+    set_capture_stats(interaction, &stats);
+    (
+        interaction.capture_buffer_dropped,
+        interaction.capture_interface_dropped,
+    ) = dropped;
+    interaction.capture_file = Some(file_name_or_full(repaired_path));
+
+    Ok(())
+}
+
+fn capture_report(
+    interaction: &Interaction,
+    path: PathBuf,
+    registered: bool,
+    check: &CaptureCheck,
+    (repaired, repair_error): (Option<(PathBuf, usize)>, Option<String>),
+) -> CaptureReport {
+    let (repaired, salvaged_packets) = repaired.unzip();
+
+    CaptureReport {
+        session: interaction.session_id,
+        interaction: interaction.id,
+        query: interaction.query.clone(),
+        path,
+        registered,
+        packets: check.packets,
+        assistant_packets: check.assistant_packets,
+        problem: check.problem.as_ref().map(|problem| problem.kind()),
+        details: check.problem.as_ref().map(|problem| problem.to_string()),
+        repaired,
+        salvaged_packets,
+        repair_error,
+    }
+}
+
+/// Print all interactions whose captures dropped packets or are empty.
+async fn capture_problems() -> Result<(), Error> {
+    let connection = database::connect().await?;
+
+    for interaction in Interaction::get_all(&connection).await? {
+        if interaction.has_empty_capture() {
+            println!("{interaction}: empty capture");
+        } else if interaction.has_capture_drops() {
+            println!(
+                "{interaction}: {} packets dropped by the buffer, {} by the interface",
+                interaction.capture_buffer_dropped.unwrap_or_default(),
+                interaction.capture_interface_dropped.unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The config of the speech recogniser, read from the config file if one was passed and
+/// overridden by the other recogniser arguments.
+fn recogniser_config(arguments: RecogniserArguments) -> Result<RecogniserConfig, Error> {
+    let mut config = match arguments.recogniser_config {
+        Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+        None => RecogniserConfig::default(),
+    };
+
+    if arguments.beam_size.is_some() {
+        config.beam_size = arguments.beam_size;
+    }
+    if let Some(temperature) = arguments.temperature {
+        config.temperature = temperature;
+    }
+    if let Some(temperature_increment) = arguments.temperature_increment {
+        config.temperature_increment = temperature_increment;
+    }
+    if let Some(language) = arguments.language {
+        config.language = language;
+    }
+    if arguments.threads.is_some() {
+        config.threads = arguments.threads;
+    }
+    if arguments.prompt.is_some() {
+        config.initial_prompt = arguments.prompt;
+    }
+    if let Some(query_prompt) = arguments.query_prompt {
+        config.query_prompt = query_prompt;
+    }
+
+    Ok(config)
+}
+
+fn tts_backend(arguments: SpeakerArguments) -> TtsBackend {
+    match arguments.tts {
+        TtsEngine::System => TtsBackend::System,
+        TtsEngine::Piper => TtsBackend::Piper {
+            model: arguments.piper_model,
+        },
+        TtsEngine::Espeak => TtsBackend::Espeak,
+        TtsEngine::Recorded => TtsBackend::Recorded {
+            directory: arguments.recordings,
+        },
+        TtsEngine::Sink => TtsBackend::Sink {
+            file: arguments.tts_file,
+        },
+    }
+}
+
+/// How much of each packet to keep when anonymising captures.
+fn truncation(arguments: TruncationArguments) -> Truncation {
+    match arguments.snaplen {
+        Some(snaplen) => Truncation::Snaplen(snaplen),
+        None if arguments.keep_payload => Truncation::Keep,
+        None => Truncation::Headers,
+    }
+}
+
+/// Capture on the given interface, replay a capture file or receive a remote capture if one of
+/// them was passed.
+///
+/// Several interfaces separated by commas are captured on at once and their packets are merged.
+fn packet_source(interface: &str, replay: ReplayArguments) -> Result<PacketSource, Error> {
+    if let Some(address) = replay.tzsp {
+        return Ok(PacketSource::Tzsp {
+            address,
+            linktype: Linktype::ETHERNET,
+        });
+    }
+
+    Ok(match replay.replay {
+        Some(path) => PacketSource::File {
+            path,
+            speed: if replay.replay_fast {
+                ReplaySpeed::Unlimited
+            } else {
+                ReplaySpeed::Recorded
+            },
+        },
+        None if interface.contains(',') => PacketSource::Devices(
+            interface
+                .split(',')
+                .map(|name| sniff::device_by_name(name.trim()))
+                .collect::<Result<_, _>>()?,
+        ),
+        None => PacketSource::from(sniff::device_by_name(interface)?),
+    })
+}
+
 async fn get_filtered_interactions(dataset_size: &DatasetSize) -> Result<Vec<Interaction>, Error> {
     let connection = database::connect().await?;
     let all_interactions = Interaction::get_all(&connection).await?;
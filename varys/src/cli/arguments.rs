@@ -1,6 +1,11 @@
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
-use clap::{Args, Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use varys_analysis::trace;
+use varys_analysis::trace::TraceRepresentation;
+use varys_network::{compress, tzsp};
 
 use crate::dataset::DatasetSize;
 
@@ -11,7 +16,8 @@ use super::export::ExportType;
 pub struct Arguments {
     #[clap(subcommand)]
     pub command: Command,
-    /// The network interface to listen on
+    /// The network interface to listen on, separate several interfaces with commas to capture on
+    /// all of them and merge their traffic
     #[arg(short, long, global = true, default_value = "en0")]
     pub interface: String,
     /// The voices to use for speaking, one random voice is used per session
@@ -28,6 +34,58 @@ pub struct Arguments {
         default_value = "data/models/ggml-model-whisper-medium.en-q5_0.bin"
     )]
     pub model: PathBuf,
+    #[command(flatten)]
+    pub recogniser: RecogniserArguments,
+    #[command(flatten)]
+    pub speaker: SpeakerArguments,
+}
+
+#[derive(Debug, Args)]
+pub struct SpeakerArguments {
+    /// The speech synthesizer to speak with, which decides what the names passed to `--voices`
+    /// refer to
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub tts: TtsEngine,
+    /// The piper voice model to speak with, its config is expected next to it with `.json`
+    /// appended
+    #[arg(long, global = true, default_value = varys_audio::tts::piper::DEFAULT_MODEL)]
+    pub piper_model: PathBuf,
+    /// The directory with a subdirectory of recorded phrases for each voice
+    #[arg(long, global = true, default_value = varys_audio::tts::recorded::DEFAULT_DIRECTORY)]
+    pub recordings: PathBuf,
+    /// The file to write the phrases to instead of saying them when using the sink
+    #[arg(long, global = true)]
+    pub tts_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RecogniserArguments {
+    /// A TOML file with the config of the speech recogniser, which the other recogniser options
+    /// override
+    #[arg(long, global = true)]
+    pub recogniser_config: Option<PathBuf>,
+    /// Decode speech with a beam search of this many beams instead of greedily
+    #[arg(long, global = true)]
+    pub beam_size: Option<u32>,
+    /// The temperature to decode speech with first
+    #[arg(long, global = true)]
+    pub temperature: Option<f32>,
+    /// How much to increase the temperature when decoding fails, 0 disables the fallback
+    #[arg(long, global = true)]
+    pub temperature_increment: Option<f32>,
+    /// The language of the speech (e.g. "en") or "auto" to detect it
+    #[arg(long, global = true)]
+    pub language: Option<String>,
+    /// The number of threads to recognise speech with
+    #[arg(long, global = true)]
+    pub threads: Option<u32>,
+    /// Text to prompt the speech recogniser with, e.g. to introduce vocabulary
+    #[arg(long, global = true)]
+    pub prompt: Option<String>,
+    /// Add the query to the prompt when recognising its response, pass `false` to turn this off
+    /// if the recogniser config turns it on
+    #[arg(long, global = true, num_args = 0..=1, default_missing_value = "true")]
+    pub query_prompt: Option<bool>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -44,6 +102,14 @@ pub enum Command {
     Analyse(AnalyseCommand),
     /// Export data captured with varys in different formats
     Export(ExportCommand),
+    /// Process the traffic captured with varys
+    Captures(CapturesCommand),
+    /// Find the MAC address of the assistant by speaking its wake word and watching the traffic
+    Discover(DiscoverCommand),
+    /// Transcribe stored responses again with the speech recognition model given by `--model`
+    ///
+    /// The new transcripts are stored alongside the original responses, which are kept.
+    Transcribe(TranscribeCommand),
 }
 
 #[derive(Debug, Args)]
@@ -90,6 +156,11 @@ pub struct SniffCommand {
     /// The duration in seconds to listen for
     #[arg(short, long, default_value_t = 5)]
     pub duration: u32,
+    /// The BPF filter to capture traffic with (e.g. "ether host 00:1a:2b:3c:4d:5e")
+    #[arg(short, long)]
+    pub filter: Option<String>,
+    #[command(flatten)]
+    pub replay: ReplayArguments,
     /// Where to store the recorded traffic
     pub file: PathBuf,
 }
@@ -99,6 +170,34 @@ pub struct RunCommand {
     /// The MAC address of the assistant
     #[arg(long, required(true))]
     pub mac: String,
+    /// The IP address of the assistant, needed to tell the direction of packets on interfaces
+    /// without MAC addresses, e.g. tun devices
+    #[arg(long)]
+    pub ip: Option<IpAddr>,
+    /// The BPF filter to capture traffic with. Defaults to only capturing traffic from and to the
+    /// assistant's MAC address, pass an empty filter to capture all traffic on the interface
+    #[arg(long)]
+    pub capture_filter: Option<String>,
+    /// Capture continuously and keep this many milliseconds of traffic before each interaction
+    #[arg(long)]
+    pub pre_roll: Option<u64>,
+    /// Capture continuously and keep this many milliseconds of traffic after each interaction
+    #[arg(long)]
+    pub post_roll: Option<u64>,
+    /// Pseudonymise addresses and truncate payloads before captures are stored
+    #[arg(long)]
+    pub anonymise: bool,
+    /// Compress captures with zstd once they are complete
+    #[arg(long)]
+    pub compress: bool,
+    /// Store captures as pcapng files with the interaction's metadata and when the query and
+    /// response were spoken as comments
+    #[arg(long)]
+    pub pcapng: bool,
+    #[command(flatten)]
+    pub truncation: TruncationArguments,
+    #[command(flatten)]
+    pub replay: ReplayArguments,
     /// Which voice assistant to interact with
     pub assistant: String,
     /// The file with queries to ask the assistant
@@ -107,16 +206,107 @@ pub struct RunCommand {
     pub data_dir: PathBuf,
 }
 
+#[derive(Debug, Args)]
+pub struct DiscoverCommand {
+    /// How many times to speak the probe
+    #[arg(short, long, default_value_t = 3)]
+    pub probes: u32,
+    /// What to say as the probe. Defaults to the wake word followed by a short question
+    #[arg(long)]
+    pub probe: Option<String>,
+    /// How many seconds after each probe traffic is counted as a response
+    #[arg(long, default_value_t = 10)]
+    pub response_window: u64,
+    /// A MAC address to confirm instead of only suggesting one
+    #[arg(long)]
+    pub mac: Option<String>,
+    #[command(flatten)]
+    pub replay: ReplayArguments,
+    /// Which voice assistant to discover
+    pub assistant: String,
+}
+
+#[derive(Debug, Args)]
+pub struct TranscribeCommand {
+    /// The directory in which data files are stored
+    pub data_dir: PathBuf,
+    /// Only transcribe the responses of this session
+    #[arg(long)]
+    pub session: Option<i32>,
+    /// Only transcribe the responses of interactions started on or after this date (e.g.
+    /// 2024-01-31)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Only transcribe the responses of interactions started on or before this date
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+    /// Only transcribe the responses that were not recognised the first time
+    #[arg(long)]
+    pub empty: bool,
+    /// Store the tokens of each segment with their probabilities
+    #[arg(long)]
+    pub tokens: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArguments {
+    /// Replay the packets of a pcap file instead of capturing traffic on the interface
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    /// Replay the packets as fast as possible instead of at the recorded speed
+    #[arg(long, requires = "replay")]
+    pub replay_fast: bool,
+    /// Receive the Ethernet frames a router streams over TZSP on this address instead of capturing
+    /// traffic on the interface, e.g. 0.0.0.0:37008
+    #[arg(long, conflicts_with = "replay")]
+    pub tzsp: Option<SocketAddr>,
+}
+
+#[derive(Debug, Args)]
+pub struct TruncationArguments {
+    /// Keep at most this many bytes of each anonymised packet instead of only its headers
+    #[arg(long, conflicts_with = "keep_payload")]
+    pub snaplen: Option<usize>,
+    /// Keep the whole anonymised packets instead of only their headers
+    #[arg(long)]
+    pub keep_payload: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct AnalyseCommand {
     /// The dataset to use
     #[arg(short, long, value_enum, default_value_t)]
     pub dataset: DatasetSize,
+    #[command(flatten)]
+    pub bursts: BurstArguments,
     /// What type of analysis to perform
     #[clap(subcommand)]
     pub command: AnalyseSubcommand,
 }
 
+#[derive(Debug, Args)]
+pub struct BurstArguments {
+    /// Use bursts of packets instead of single packets as traces when creating a dataset
+    #[arg(long, global = true)]
+    pub bursts: bool,
+    /// How many milliseconds the traffic must pause to end a burst
+    #[arg(long, global = true, default_value_t = trace::DEFAULT_BURST_GAP)]
+    pub burst_gap: u64,
+}
+
+impl BurstArguments {
+    /// How traces are created from the captured traffic.
+    pub fn representation(&self) -> TraceRepresentation {
+        if self.bursts {
+            TraceRepresentation::Bursts {
+                gap: self.burst_gap,
+            }
+        } else {
+            TraceRepresentation::Packets
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum AnalyseSubcommand {
     /// Train varys traffic fingerprinting
@@ -135,6 +325,8 @@ pub enum AnalyseSubcommand {
         data_dir: PathBuf,
         /// The MAC address of the assistant
         mac: String,
+        #[command(flatten)]
+        replay: ReplayArguments,
     },
     /// Compile training logs into a training and validation `.csv` summary
     CompileLogs {
@@ -148,6 +340,11 @@ pub enum AnalyseSubcommand {
         /// The directory in which data files are stored
         data_dir: PathBuf,
     },
+    /// List the connections of each interaction labelled with the hostnames they were made to
+    Hostnames {
+        /// The directory in which data files are stored
+        data_dir: PathBuf,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -161,4 +358,129 @@ pub struct ExportCommand {
     pub data_dir: PathBuf,
     /// Which voice assistant to export data for
     pub assistant: String,
+    /// How many milliseconds the traffic must pause to end a burst when exporting bursts
+    #[arg(long, default_value_t = trace::DEFAULT_BURST_GAP)]
+    pub burst_gap: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct CapturesCommand {
+    /// What to do with the captures
+    #[clap(subcommand)]
+    pub command: CapturesSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CapturesSubcommand {
+    /// Pseudonymise the addresses and truncate the payloads of stored captures in place
+    Anonymise {
+        /// The directory in which data files are stored
+        data_dir: PathBuf,
+        /// Only anonymise the captures of this session instead of all sessions
+        #[arg(long)]
+        session: Option<i32>,
+        #[command(flatten)]
+        truncation: TruncationArguments,
+    },
+    /// List the interactions whose captures dropped packets or are empty
+    Problems,
+    /// Compress stored captures with zstd in place
+    Compress {
+        /// The directory in which data files are stored
+        data_dir: PathBuf,
+        /// Only compress the captures of this session instead of all sessions
+        #[arg(long)]
+        session: Option<i32>,
+        /// The zstd compression level
+        #[arg(long, default_value_t = compress::DEFAULT_LEVEL)]
+        level: i32,
+    },
+    /// Cut stored captures to a time window, e.g. to remove traffic that followed the response
+    ///
+    /// Trimmed copies are stored next to the captures and the interactions are updated to use
+    /// them. Captures that were already trimmed are trimmed again from their original.
+    Trim {
+        /// The directory in which data files are stored
+        data_dir: PathBuf,
+        /// Only trim the captures of this session
+        #[arg(long)]
+        session: Option<i32>,
+        /// Only trim the capture of this interaction
+        #[arg(long)]
+        interaction: Option<i32>,
+        /// What the window is relative to
+        #[arg(long, value_enum, default_value_t)]
+        anchor: TrimAnchor,
+        /// How many milliseconds before the anchor to keep
+        #[arg(long, default_value_t = 0)]
+        before: u64,
+        /// How many milliseconds after the anchor to keep. Windows that are empty, e.g. the first
+        /// packet without padding, are not trimmed
+        #[arg(long, default_value_t = 0)]
+        after: u64,
+        /// How many milliseconds the assistant must be silent to end a burst
+        #[arg(long, default_value_t = 5000)]
+        gap: u64,
+        /// Replace the captures instead of storing trimmed copies
+        #[arg(long)]
+        in_place: bool,
+    },
+    /// Check that all captures can be read and contain traffic of the assistant
+    ///
+    /// Captures of interactions that were interrupted before their capture was stored, e.g.
+    /// because varys was killed, are checked as well.
+    Verify {
+        /// The directory in which data files are stored
+        data_dir: PathBuf,
+        /// Only verify the captures of this session instead of all sessions
+        #[arg(long)]
+        session: Option<i32>,
+        /// Salvage the readable packets of truncated captures into repaired copies and update the
+        /// interactions to use them
+        #[arg(long)]
+        repair: bool,
+        /// Write a JSON report of all verified captures to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Stream a capture over TZSP like a router would, e.g. to test receiving it with `--tzsp`
+    Send {
+        /// The capture to send
+        file: PathBuf,
+        /// The address to send the capture to
+        #[arg(default_value_t = SocketAddr::from(([127, 0, 0, 1], tzsp::DEFAULT_PORT)))]
+        target: SocketAddr,
+        /// Send the packets as fast as possible instead of at the recorded speed
+        #[arg(long)]
+        fast: bool,
+    },
+}
+
+/// The speech synthesizers that can be spoken with.
+#[derive(ValueEnum, Copy, Clone, Debug, Default)]
+pub enum TtsEngine {
+    /// The speech synthesis of the operating system (macOS only)
+    #[cfg_attr(target_os = "macos", default)]
+    System,
+    /// The piper neural speech synthesizer, voices are speakers of the model
+    #[cfg_attr(not(target_os = "macos"), default)]
+    Piper,
+    /// The eSpeak NG speech synthesizer
+    Espeak,
+    /// Recordings of the phrases, voices are subdirectories of the recordings
+    Recorded,
+    /// Don't say anything, optionally writing the phrases to a file
+    Sink,
+}
+
+/// What the window of a trimmed capture is relative to.
+#[derive(ValueEnum, Copy, Clone, Debug, Default)]
+pub enum TrimAnchor {
+    /// The first packet sent or received by the assistant
+    FirstPacket,
+    /// The start and end of the interaction
+    Interaction,
+    /// The first burst of traffic of the assistant
+    #[default]
+    Burst,
 }
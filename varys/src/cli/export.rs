@@ -2,16 +2,15 @@ use std::{
     fs::{self, File},
     io::Write,
     path::Path,
-    str::FromStr,
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use clap::ValueEnum;
 use regex::Regex;
 use serde::Serialize;
 use varys_analysis::trace::TrafficTrace;
 use varys_database::{database::interaction::Interaction, file};
-use varys_network::{address::MacAddress, packet};
+use varys_network::{address::DeviceAddress, packet};
 
 use crate::{assistant::VoiceAssistant, cli, dataset::DatasetSize, error::Error};
 
@@ -19,6 +18,8 @@ use crate::{assistant::VoiceAssistant, cli, dataset::DatasetSize, error::Error};
 pub enum ExportType {
     Wang,
     Ahmed,
+    /// The bursts of each interaction with their direction-wise totals
+    Bursts,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -44,6 +45,7 @@ impl ExportType {
         data_dir: P,
         dataset_size: &DatasetSize,
         voice_assistant: Box<dyn VoiceAssistant>,
+        burst_gap: Duration,
     ) -> Result<(), Error> {
         let export_dir = data_dir
             .as_ref()
@@ -51,6 +53,7 @@ impl ExportType {
             .join(match self {
                 ExportType::Wang => "wang",
                 ExportType::Ahmed => "ahmed",
+                ExportType::Bursts => "bursts",
             })
             .join(dataset_size.to_string());
 
@@ -69,6 +72,9 @@ impl ExportType {
                 )
                 .await
             }
+            ExportType::Bursts => {
+                Self::export_bursts(data_dir.as_ref(), &export_dir, dataset_size, burst_gap).await
+            }
         }
     }
 
@@ -199,8 +205,11 @@ impl ExportType {
                 .enumerate()
             {
                 log::info!("Processing interaction: {:?}", interaction.id);
-                let mac_address =
-                    MacAddress::from_str(&interaction.assistant_mac).expect("Cannot load MAC");
+                let address = DeviceAddress::parse(
+                    &interaction.assistant_mac,
+                    interaction.assistant_ip.as_deref(),
+                )
+                .expect("Cannot load assistant address");
                 let capture_path = interaction
                     .capture_file
                     .clone()
@@ -213,7 +222,9 @@ impl ExportType {
                     continue;
                 }
     
-                let packets = match packet::load_packets(&capture_path) {
+                let packets = match packet::read_headers(&capture_path)
+                    .and_then(|headers| headers.collect::<Result<Vec<_>, _>>())
+                {
                     Ok(packets) => packets,
                     Err(e) => {
                         log::error!("Could not load packets from capture file: {:?}", e);
@@ -222,7 +233,7 @@ impl ExportType {
                 };
     
                 let traffic_trace = match TrafficTrace::try_from(packets) {
-                    Ok(trace) => trace.as_wang_traffic_trace(&mac_address),
+                    Ok(trace) => trace.as_wang_traffic_trace(&address),
                     Err(e) => {
                         log::error!("Cannot load traffic trace: {:?}", e);
                         continue;
@@ -252,6 +263,74 @@ impl ExportType {
         Ok(())
     }
 
+    async fn export_bursts<P: AsRef<Path>>(
+        data_dir: P,
+        export_dir: P,
+        dataset_size: &DatasetSize,
+        gap: Duration,
+    ) -> Result<(), Error> {
+        let interactions = Self::get_interactions(dataset_size).await?;
+        fs::create_dir_all(&export_dir)?;
+
+        for interaction in interactions
+            .iter()
+            .filter(|interaction| interaction.capture_file.is_some())
+        {
+            let Ok(address) = DeviceAddress::parse(
+                &interaction.assistant_mac,
+                interaction.assistant_ip.as_deref(),
+            ) else {
+                log::error!("Cannot load assistant address of {}", interaction);
+                continue;
+            };
+            let capture_path = interaction
+                .capture_file
+                .clone()
+                .map(|path| file::session_path(&data_dir, interaction.session_id).join(path))
+                .expect("Cannot load capture path");
+
+            let trace = match packet::read_headers(&capture_path)
+                .and_then(|headers| headers.collect::<Result<Vec<_>, _>>())
+            {
+                Ok(packets) => match TrafficTrace::try_from(packets) {
+                    Ok(trace) => trace,
+                    Err(e) => {
+                        log::error!("Cannot load traffic trace: {:?}", e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    log::error!("Could not load packets from capture file: {:?}", e);
+                    continue;
+                }
+            };
+
+            let interaction_path = export_dir
+                .as_ref()
+                .join(format!("s{}i{}-bursts.csv", interaction.session_id, interaction.id));
+            let mut csv = File::create(&interaction_path)?;
+
+            writeln!(csv, "query,start,duration,sent_packets,sent_bytes,received_packets,received_bytes")?;
+            for burst in trace.bursts(&address, gap) {
+                writeln!(
+                    csv,
+                    "\"{}\",{},{},{},{},{},{}",
+                    interaction.query.replace('"', "\"\""),
+                    Self::datetime_to_timestamp(burst.start_time),
+                    burst.duration().num_microseconds().unwrap_or_default() as f64 * 1e-6,
+                    burst.sent_packets,
+                    burst.sent_bytes,
+                    burst.received_packets,
+                    burst.received_bytes
+                )?;
+            }
+
+            log::trace!("Exported {:?}", interaction_path);
+        }
+
+        Ok(())
+    }
+
     fn datetime_to_timestamp(datetime: DateTime<Utc>) -> f64 {
         datetime.timestamp() as f64 + datetime.timestamp_subsec_nanos() as f64 * 1e-9
     }
@@ -17,6 +17,8 @@ pub enum Error {
     Dotenv(String),
     #[error(transparent)]
     TomlDeserializeError(#[from] toml::de::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
     #[error("At least one voice is required")]
     NoVoiceProvided,
 